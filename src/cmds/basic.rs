@@ -1,9 +1,14 @@
 use std::sync::Arc;
 
 use crate::error::{Error, Result};
-use crate::protocol::Data;
+use crate::protocol::{Data, RespVersion};
 use crate::storage::Db;
 
+use super::Session;
+
+/// Redis version reported to the clients, clients use it to detect the supported features
+const REDIS_VERSION: &str = "7.4.0";
+
 /// Process the Ping command
 pub fn ping_execute() -> Data {
     Data::SimpleString("PONG".to_string())
@@ -11,42 +16,110 @@ pub fn ping_execute() -> Data {
 
 pub fn echo_execute(args: &[Data]) -> Result<Data> {
     let Some(Data::BulkString(data)) = args.first() else {
-        return Err(Error::InvalidResp);
+        return Err(Error::WrongNumberOfArgs("echo".to_string()));
     };
 
-    Ok(Data::BulkString(data.to_string()))
+    Ok(Data::BulkString(data.clone()))
 }
 pub fn command_execute(_: &[Data]) -> Data {
-    Data::BulkString(String::new())
+    Data::bulk_string(Vec::new())
 }
 
+/// Implement the info command as described here <https://redis.io/docs/latest/commands/info/>
+/// Without section all the supported sections are returned, unknown sections are ignored.
 pub fn info_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
-    let [_, ..] = args else {
-        return Err(Error::ArgsMissing(
-            "arg missing in info command".to_string(),
-        ));
-    };
+    let sections = args
+        .iter()
+        .map(|section| <&str>::try_from(section).map(str::to_ascii_lowercase))
+        .collect::<Result<Vec<_>>>()?;
+    let all = sections.is_empty()
+        || sections
+            .iter()
+            .any(|s| matches!(s.as_str(), "all" | "default" | "everything"));
+    let wants = |name: &str| all || sections.iter().any(|s| s == name);
 
-    let info = state.info().replication.to_string();
-    Ok(Data::BulkString(info))
+    let mut info = Vec::new();
+    if wants("replication") {
+        info.push(state.info().replication.to_string());
+    }
+    if wants("stats") {
+        info.push(state.stats().to_string());
+    }
+    Ok(Data::VerbatimString(
+        "txt".to_string(),
+        info.join("\n").into_bytes(),
+    ))
 }
 pub fn config_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
-    let [Data::BulkString(sub_cmd), Data::BulkString(config_key), ..] = args else {
-        return Err(Error::InvalidResp);
+    let [sub_cmd, config_key, ..] = args else {
+        return Err(Error::WrongNumberOfArgs("config".to_string()));
     };
+    let sub_cmd: &str = sub_cmd.try_into()?;
+    let config_key: &str = config_key.try_into()?;
 
-    let config_value = match (sub_cmd.to_uppercase().as_str(), config_key.as_str()) {
+    let config_value = match (sub_cmd.to_uppercase().as_str(), config_key) {
         ("GET", "dir") => Ok(state.config.dir.clone()),
         ("GET", "dbfilename") => Ok(state.config.dbfilename.clone()),
+        ("GET", "hz") => Ok(Some(state.config.hz.to_string())),
         other => Err(Error::Unsupported(format!(
             "Unsupported Config sub command {other:?}"
         ))),
     }?;
 
-    Ok(config_value.map_or(Data::NullBuilkString, |v| {
-        Data::Array(vec![
-            Data::BulkString(config_key.clone()),
-            Data::BulkString(v),
-        ])
-    }))
+    Ok(Data::Map(config_value.map_or_else(Vec::new, |v| {
+        vec![(Data::bulk_string(config_key), Data::bulk_string(v))]
+    })))
+}
+
+/// Switch the connection protocol as described here <https://redis.io/docs/latest/commands/hello/>
+/// HELLO [protover [AUTH username password] [SETNAME clientname]]
+pub fn hello_execute(args: &[Data], state: &Arc<Db>, session: &mut Session) -> Result<Data> {
+    if let [protover, options @ ..] = args {
+        let protover: &str = protover.try_into()?;
+        let version = protover
+            .parse()
+            .ok()
+            .and_then(RespVersion::from_protover)
+            .ok_or(Error::NoProto)?;
+
+        // There is no authentication and client names are not tracked, options are only validated
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            let option: &str = option.try_into()?;
+            let arity = match option.to_ascii_uppercase().as_str() {
+                "AUTH" => 2,
+                "SETNAME" => 1,
+                _ => return Err(Error::Syntax),
+            };
+            if options.by_ref().take(arity).count() != arity {
+                return Err(Error::Syntax);
+            }
+        }
+
+        session.resp_version = version;
+    }
+
+    let role = if state.info().is_master() {
+        "master"
+    } else {
+        "replica"
+    };
+    Ok(Data::Map(vec![
+        (Data::bulk_string("server"), Data::bulk_string("redis")),
+        (
+            Data::bulk_string("version"),
+            Data::bulk_string(REDIS_VERSION),
+        ),
+        (
+            Data::bulk_string("proto"),
+            Data::Integer(session.resp_version.protover()),
+        ),
+        (
+            Data::bulk_string("id"),
+            Data::Integer(i64::try_from(session.id).unwrap_or(i64::MAX)),
+        ),
+        (Data::bulk_string("mode"), Data::bulk_string("standalone")),
+        (Data::bulk_string("role"), Data::bulk_string(role)),
+        (Data::bulk_string("modules"), Data::Array(vec![])),
+    ]))
 }
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use super::string::parse_integer;
+use crate::error::{Error, Result};
+use crate::protocol::Data;
+use crate::storage::{BitField, BitFieldOp, BitOperation, BitUnit, Db, Overflow};
+
+/// As in Redis the bits are addressed within the maximum string size, 512MB
+const MAX_BIT_OFFSET: usize = 512 * 1024 * 1024 * 8;
+
+fn parse_offset(offset: &Data) -> Result<usize> {
+    let offset = parse_integer(offset.try_into()?).map_err(|_| Error::BitOffset)?;
+    usize::try_from(offset)
+        .ok()
+        .filter(|offset| *offset < MAX_BIT_OFFSET)
+        .ok_or(Error::BitOffset)
+}
+
+/// 0 or 1
+fn parse_bit(bit: &Data) -> Result<bool> {
+    let bit: &[u8] = bit.try_into()?;
+    match bit {
+        b"0" => Ok(false),
+        b"1" => Ok(true),
+        _ => Err(Error::BitValue),
+    }
+}
+
+fn parse_unit(unit: &Data) -> Result<BitUnit> {
+    let unit: &str = unit.try_into()?;
+    match unit.to_ascii_uppercase().as_str() {
+        "BYTE" => Ok(BitUnit::Byte),
+        "BIT" => Ok(BitUnit::Bit),
+        _ => Err(Error::Syntax),
+    }
+}
+
+fn parse_index(index: &Data) -> Result<i64> {
+    parse_integer(index.try_into()?)
+}
+
+/// Implement setbit as described here <https://redis.io/docs/latest/commands/setbit/>
+pub fn setbit_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, offset, bit] = args else {
+        return Err(Error::WrongNumberOfArgs("setbit".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let (offset, bit) = (parse_offset(offset)?, parse_bit(bit)?);
+    Ok(Data::Integer(i64::from(state.setbit(key, offset, bit)?)))
+}
+
+/// Implement getbit as described here <https://redis.io/docs/latest/commands/getbit/>
+pub fn getbit_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, offset] = args else {
+        return Err(Error::WrongNumberOfArgs("getbit".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    Ok(Data::Integer(i64::from(
+        state.getbit(key, parse_offset(offset)?)?,
+    )))
+}
+
+/// Implement bitcount as described here <https://redis.io/docs/latest/commands/bitcount/>
+/// BITCOUNT key [start end [BYTE | BIT]]
+pub fn bitcount_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, range) = match args {
+        [key] => (key, None),
+        [key, start, end] => (key, Some((start, end, BitUnit::Byte))),
+        [key, start, end, unit] => (key, Some((start, end, parse_unit(unit)?))),
+        [] => return Err(Error::WrongNumberOfArgs("bitcount".to_string())),
+        _ => return Err(Error::Syntax),
+    };
+    let range = range
+        .map(|(start, end, unit)| Ok::<_, Error>((parse_index(start)?, parse_index(end)?, unit)))
+        .transpose()?;
+    Ok(Data::from(state.bitcount(key.try_into()?, range)?))
+}
+
+/// Implement bitpos as described here <https://redis.io/docs/latest/commands/bitpos/>
+/// BITPOS key bit [start [end [BYTE | BIT]]]
+pub fn bitpos_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, bit, range @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("bitpos".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let bit = parse_bit(bit).map_err(|_| Error::BitArgument)?;
+    let (start, end, unit) = match range {
+        [] => (0, None, BitUnit::Byte),
+        [start] => (parse_index(start)?, None, BitUnit::Byte),
+        [start, end] => (parse_index(start)?, Some(parse_index(end)?), BitUnit::Byte),
+        [start, end, unit] => (
+            parse_index(start)?,
+            Some(parse_index(end)?),
+            parse_unit(unit)?,
+        ),
+        _ => return Err(Error::Syntax),
+    };
+    let position = state.bitpos(key, bit, start, end, unit)?;
+    Ok(position.map_or(Data::Integer(-1), Data::from))
+}
+
+/// Implement bitop as described here <https://redis.io/docs/latest/commands/bitop/>
+pub fn bitop_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [op, destination, keys @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("bitop".to_string()));
+    };
+    if keys.is_empty() {
+        return Err(Error::WrongNumberOfArgs("bitop".to_string()));
+    }
+    let op: &str = op.try_into()?;
+    let op = match op.to_ascii_uppercase().as_str() {
+        "AND" => BitOperation::And,
+        "OR" => BitOperation::Or,
+        "XOR" => BitOperation::Xor,
+        "NOT" => BitOperation::Not,
+        _ => return Err(Error::Syntax),
+    };
+    if op == BitOperation::Not && keys.len() != 1 {
+        return Err(Error::BitopNotSingleKey);
+    }
+    let keys = keys
+        .iter()
+        .map(<&str>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::from(state.bitop(
+        op,
+        destination.try_into()?,
+        &keys,
+    )?))
+}
+
+/// `i<bits>` or `u<bits>` and the offset of the field, `#<n>` meaning the n-th field of this type
+fn parse_field(field_type: &Data, offset: &Data) -> Result<BitField> {
+    let field_type: &str = field_type.try_into()?;
+    let (signed, bits) = match field_type.split_at_checked(1) {
+        Some(("i" | "I", bits)) => (true, bits),
+        Some(("u" | "U", bits)) => (false, bits),
+        _ => return Err(Error::BitfieldType),
+    };
+    let bits: u32 = bits.parse().map_err(|_| Error::BitfieldType)?;
+    let max_bits = if signed { 64 } else { 63 };
+    if bits == 0 || bits > max_bits {
+        return Err(Error::BitfieldType);
+    }
+
+    let offset: &[u8] = offset.try_into()?;
+    let offset = match offset.strip_prefix(b"#") {
+        Some(index) => parse_integer(index).map(|index| index.saturating_mul(i64::from(bits))),
+        None => parse_integer(offset),
+    };
+    let offset = offset
+        .ok()
+        .and_then(|offset| usize::try_from(offset).ok())
+        .filter(|offset| offset.saturating_add(bits as usize) <= MAX_BIT_OFFSET)
+        .ok_or(Error::BitOffset)?;
+    Ok(BitField {
+        signed,
+        bits,
+        offset,
+    })
+}
+
+/// Implement bitfield as described here <https://redis.io/docs/latest/commands/bitfield/>
+/// BITFIELD key [GET encoding offset | [OVERFLOW WRAP | SAT | FAIL] SET encoding offset value |
+/// INCRBY encoding offset increment ...]
+pub fn bitfield_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("bitfield".to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let mut ops = Vec::new();
+    // OVERFLOW applies to the SET and INCRBY operations after it
+    let mut overflow = Overflow::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        let mut next = || options.next().ok_or(Error::Syntax);
+        match option.to_ascii_uppercase().as_str() {
+            "GET" => ops.push(BitFieldOp::Get(parse_field(next()?, next()?)?)),
+            "SET" => {
+                let field = parse_field(next()?, next()?)?;
+                ops.push(BitFieldOp::Set(field, parse_index(next()?)?, overflow));
+            }
+            "INCRBY" => {
+                let field = parse_field(next()?, next()?)?;
+                ops.push(BitFieldOp::Incrby(field, parse_index(next()?)?, overflow));
+            }
+            "OVERFLOW" => {
+                let kind: &str = next()?.try_into()?;
+                overflow = match kind.to_ascii_uppercase().as_str() {
+                    "WRAP" => Overflow::Wrap,
+                    "SAT" => Overflow::Sat,
+                    "FAIL" => Overflow::Fail,
+                    _ => return Err(Error::InvalidOverflow),
+                };
+            }
+            _ => return Err(Error::Syntax),
+        }
+    }
+
+    Ok(Data::Array(
+        state
+            .bitfield(key, &ops)?
+            .into_iter()
+            .map(|value| value.map_or(Data::NullBuilkString, Data::Integer))
+            .collect(),
+    ))
+}
@@ -0,0 +1,331 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use super::list::parse_list_end;
+use super::stream::{self, XreadArgs};
+use super::string::parse_float;
+use crate::error::{Error, Result};
+use crate::protocol::{Cmd, Data, RespVersion};
+use crate::storage::{Db, ListEnd};
+
+/// How long a blocking command waits for its keys
+#[derive(Debug, Clone, Copy)]
+enum Wait {
+    /// XREAD without BLOCK
+    Never,
+    Forever,
+    For(Duration),
+}
+
+/// What the command does once one of its keys is ready
+#[derive(Debug)]
+enum Serve {
+    Pop(ListEnd),
+    Move {
+        destination: String,
+        from: ListEnd,
+        to: ListEnd,
+    },
+    ZPop {
+        max: bool,
+    },
+    Read(XreadArgs),
+}
+
+/// A parsed blocking command: BLPOP, BRPOP, BLMOVE, BZPOPMIN, BZPOPMAX or XREAD
+#[derive(Debug)]
+pub struct BlockingCmd {
+    keys: Vec<String>,
+    wait: Wait,
+    serve: Serve,
+}
+
+/// The timeout in seconds of the blocking commands, 0 blocks forever
+fn parse_timeout(timeout: &Data) -> Result<Wait> {
+    let timeout = parse_float(timeout.try_into()?).map_err(|_| Error::TimeoutNotFloat)?;
+    if timeout < 0.0 {
+        return Err(Error::NegativeTimeout);
+    }
+    if timeout == 0.0 {
+        return Ok(Wait::Forever);
+    }
+    Duration::try_from_secs_f64(timeout)
+        .map(Wait::For)
+        .map_err(|_| Error::TimeoutNotFloat)
+}
+
+/// key [key ...] timeout
+fn parse_keys_timeout(cmd: &str, args: &[Data]) -> Result<(Vec<String>, Wait)> {
+    let [keys @ .., timeout] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    if keys.is_empty() {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    }
+    let keys = keys
+        .iter()
+        .map(|key| Ok(<&str>::try_from(key)?.to_string()))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((keys, parse_timeout(timeout)?))
+}
+
+fn list_end_arg(end: ListEnd) -> Data {
+    match end {
+        ListEnd::Left => Data::bulk_string("LEFT"),
+        ListEnd::Right => Data::bulk_string("RIGHT"),
+    }
+}
+
+impl BlockingCmd {
+    pub fn from_cmd(cmd: &Cmd, state: &Arc<Db>) -> Result<Self> {
+        let (keys, wait, serve) = match cmd {
+            Cmd::Blpop { args } => {
+                let (keys, wait) = parse_keys_timeout("blpop", args)?;
+                (keys, wait, Serve::Pop(ListEnd::Left))
+            }
+            Cmd::Brpop { args } => {
+                let (keys, wait) = parse_keys_timeout("brpop", args)?;
+                (keys, wait, Serve::Pop(ListEnd::Right))
+            }
+            Cmd::Bzpopmin { args } => {
+                let (keys, wait) = parse_keys_timeout("bzpopmin", args)?;
+                (keys, wait, Serve::ZPop { max: false })
+            }
+            Cmd::Bzpopmax { args } => {
+                let (keys, wait) = parse_keys_timeout("bzpopmax", args)?;
+                (keys, wait, Serve::ZPop { max: true })
+            }
+            Cmd::Blmove { args } => {
+                let [source, destination, from, to, timeout] = args.as_slice() else {
+                    return Err(Error::WrongNumberOfArgs("blmove".to_string()));
+                };
+                let source: &str = source.try_into()?;
+                let destination: &str = destination.try_into()?;
+                let serve = Serve::Move {
+                    destination: destination.to_string(),
+                    from: parse_list_end(from)?,
+                    to: parse_list_end(to)?,
+                };
+                (vec![source.to_string()], parse_timeout(timeout)?, serve)
+            }
+            Cmd::Xread { args } => {
+                let xread = stream::parse_xread(args, state)?;
+                let keys = xread.streams.iter().map(|(key, _)| key.clone()).collect();
+                let wait = match xread.block {
+                    None => Wait::Never,
+                    Some(0) => Wait::Forever,
+                    Some(ms) => Wait::For(Duration::from_millis(ms)),
+                };
+                (keys, wait, Serve::Read(xread))
+            }
+            other => {
+                return Err(Error::Unsupported(format!(
+                    "Invalid blocking command {other:?}"
+                )))
+            }
+        };
+        // the slaves only pop the elements popped by the master
+        if !matches!(serve, Serve::Read(_)) && !state.info().is_master() {
+            return Err(Error::ReadOnly);
+        }
+        Ok(Self { keys, wait, serve })
+    }
+
+    /// The reply when the command times out
+    fn timeout_reply(&self) -> Data {
+        match self.serve {
+            Serve::Move { .. } => Data::NullBuilkString,
+            _ => Data::NullArray,
+        }
+    }
+
+    /// Serve the command if one of its keys is ready, None otherwise.
+    /// A blocked client only consumes the keys it is the first in line for, the reads of XREAD
+    /// don't consume anything and are served in any case.
+    /// The consumed elements are propagated to the slaves as the equivalent non blocking command.
+    fn try_serve(
+        &self,
+        state: &Arc<Db>,
+        client_id: Option<u64>,
+        version: RespVersion,
+    ) -> Result<Option<Data>> {
+        if let Serve::Read(xread) = &self.serve {
+            return stream::xread(xread, state, version);
+        }
+        for key in &self.keys {
+            if client_id.is_some_and(|client_id| !state.is_first_blocked(client_id, key)) {
+                continue;
+            }
+            let served = match &self.serve {
+                Serve::Pop(end) => state
+                    .pop(key, *end, 1)?
+                    .and_then(|popped| popped.into_iter().next())
+                    .map(|element| {
+                        let args = vec![Data::bulk_string(key.as_str())];
+                        let cmd = match end {
+                            ListEnd::Left => Cmd::Lpop { args },
+                            ListEnd::Right => Cmd::Rpop { args },
+                        };
+                        super::propagate(&cmd, state);
+                        Data::Array(vec![
+                            Data::bulk_string(key.as_str()),
+                            Data::BulkString(element),
+                        ])
+                    }),
+                Serve::Move {
+                    destination,
+                    from,
+                    to,
+                } => state.lmove(key, destination, *from, *to)?.map(|element| {
+                    let args = vec![
+                        Data::bulk_string(key.as_str()),
+                        Data::bulk_string(destination.as_str()),
+                        list_end_arg(*from),
+                        list_end_arg(*to),
+                    ];
+                    super::propagate(&Cmd::Lmove { args }, state);
+                    Data::BulkString(element)
+                }),
+                Serve::ZPop { max } => state.zpop(key, 1, *max)?.pop().map(|(member, score)| {
+                    let args = vec![Data::bulk_string(key.as_str())];
+                    let cmd = if *max {
+                        Cmd::Zpopmax { args }
+                    } else {
+                        Cmd::Zpopmin { args }
+                    };
+                    super::propagate(&cmd, state);
+                    Data::Array(vec![
+                        Data::bulk_string(key.as_str()),
+                        Data::BulkString(member),
+                        Data::Double(score),
+                    ])
+                }),
+                Serve::Read(_) => None,
+            };
+            if served.is_some() {
+                return Ok(served);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Serve the command right away, used when the command can't block such as when it is
+    /// replayed by a slave
+    pub fn execute_now(&self, state: &Arc<Db>, version: RespVersion) -> Result<Data> {
+        Ok(self
+            .try_serve(state, None, version)?
+            .unwrap_or_else(|| self.timeout_reply()))
+    }
+
+    /// Park the client until one of the keys is ready or the timeout fires.
+    /// The client is queued on its keys first so the clients already waiting are served before it.
+    pub async fn block(
+        &self,
+        state: &Arc<Db>,
+        client_id: u64,
+        version: RespVersion,
+    ) -> Result<Data> {
+        // the pops are propagated in the order they are served, as the other writes
+        let serve = || {
+            let _ordered = state.order_writes();
+            self.try_serve(state, Some(client_id), version)
+        };
+        let deadline = match self.wait {
+            Wait::Never => {
+                let _ordered = state.order_writes();
+                return self.execute_now(state, version);
+            }
+            Wait::Forever => None,
+            Wait::For(timeout) => Some(Instant::now() + timeout),
+        };
+
+        let notify = Arc::new(Notify::new());
+        let consumes = !matches!(self.serve, Serve::Read(_));
+        state.block_client(client_id, &self.keys, &notify, consumes);
+        // unblock the client however the wait ends, including when the connection is closed
+        let _blocked = Blocked {
+            state,
+            client_id,
+            keys: &self.keys,
+        };
+
+        let mut woken = false;
+        loop {
+            if let Some(reply) = serve()? {
+                return Ok(reply);
+            }
+            // the key was taken or can't serve this client, it may serve the next one
+            if woken && consumes {
+                state.pass_wakeup(client_id, &self.keys);
+            }
+            woken = true;
+            let notified = notify.notified();
+            match deadline {
+                None => notified.await,
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                        return Ok(self.timeout_reply());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Remove the client from the queues of its keys when dropped
+struct Blocked<'a> {
+    state: &'a Db,
+    client_id: u64,
+    keys: &'a [String],
+}
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        self.state.unblock_client(self.client_id, self.keys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::BlockingCmd;
+    use crate::protocol::{Cmd, Data, RespVersion};
+    use crate::storage::{Config, Db, XaddId};
+
+    fn xread(args: &[&str]) -> Cmd {
+        let args = args.iter().map(|arg| Data::bulk_string(*arg)).collect();
+        Cmd::Xread { args }
+    }
+
+    #[tokio::test]
+    async fn readers_are_not_starved_test() {
+        let state = Arc::new(Db::new(Config::default()));
+        let add = || state.xadd("s", XaddId::Auto, &[(&b"f"[..], &b"v"[..])], true, None);
+        add().unwrap();
+        // the first client waits for an ID past the new entry, it can't be served
+        let first = BlockingCmd::from_cmd(
+            &xread(&["BLOCK", "0", "STREAMS", "s", "99999999999999-0"]),
+            &state,
+        )
+        .unwrap();
+        let second =
+            BlockingCmd::from_cmd(&xread(&["BLOCK", "0", "STREAMS", "s", "$"]), &state).unwrap();
+
+        let version = RespVersion::Resp2;
+        let (first, second, ()) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(100), first.block(&state, 1, version)),
+            tokio::time::timeout(Duration::from_secs(1), second.block(&state, 2, version)),
+            async {
+                tokio::task::yield_now().await;
+                add().unwrap();
+            }
+        );
+        assert!(first.is_err(), "{first:?}");
+        assert!(matches!(second, Ok(Ok(Data::Array(_)))), "{second:?}");
+    }
+}
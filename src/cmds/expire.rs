@@ -0,0 +1,224 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::protocol::{Cmd, Data};
+use crate::storage::{Db, ExpireCondition, ExpireOutcome};
+
+/// Unit of the time given to or returned by the expire commands
+#[derive(Debug, Clone, Copy)]
+pub enum TimeUnit {
+    Seconds,
+    Milliseconds,
+}
+
+impl TimeUnit {
+    fn to_ms(self, time: i64) -> Option<i64> {
+        match self {
+            TimeUnit::Seconds => time.checked_mul(1000),
+            TimeUnit::Milliseconds => Some(time),
+        }
+    }
+
+    /// Seconds are rounded to the closest value, as Redis does
+    fn round_ms(self, ms: i64) -> i64 {
+        match self {
+            TimeUnit::Seconds => (ms + 500) / 1000,
+            TimeUnit::Milliseconds => ms,
+        }
+    }
+}
+
+/// Milliseconds since the unix epoch, the format used to replicate expirations
+pub fn unix_time_ms(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| {
+        i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX)
+    })
+}
+
+/// Timestamps before the epoch are clamped to the epoch, they are in the past anyway
+pub fn from_unix_time_ms(ms: i64) -> SystemTime {
+    u64::try_from(ms).map_or(UNIX_EPOCH, |ms| UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+/// Expiration in milliseconds since the epoch, relative times start now
+pub fn expiration_ms(cmd: &str, time: i64, unit: TimeUnit, absolute: bool) -> Result<i64> {
+    let invalid_time = || Error::InvalidExpireTime(cmd.to_string());
+    let at_ms = unit.to_ms(time).ok_or_else(invalid_time)?;
+    if absolute {
+        return Ok(at_ms);
+    }
+    at_ms
+        .checked_add(unix_time_ms(SystemTime::now()))
+        .ok_or_else(invalid_time)
+}
+
+/// NX, XX, GT and LT options, XX can be combined with GT or LT
+pub fn parse_expire_conditions(options: &[Data]) -> Result<Vec<ExpireCondition>> {
+    let mut conditions = Vec::with_capacity(options.len());
+    for option in options {
+        let option: &str = option.try_into()?;
+        let condition = match option.to_ascii_uppercase().as_str() {
+            "NX" => ExpireCondition::Nx,
+            "XX" => ExpireCondition::Xx,
+            "GT" => ExpireCondition::Gt,
+            "LT" => ExpireCondition::Lt,
+            _ => return Err(Error::Unsupported(format!("Unsupported option {option}"))),
+        };
+        conditions.push(condition);
+    }
+
+    let has = |condition| conditions.contains(&condition);
+    if has(ExpireCondition::Nx)
+        && (has(ExpireCondition::Xx) || has(ExpireCondition::Gt) || has(ExpireCondition::Lt))
+    {
+        return Err(Error::IncompatibleOptions(
+            "NX and XX, GT or LT".to_string(),
+        ));
+    }
+    if has(ExpireCondition::Gt) && has(ExpireCondition::Lt) {
+        return Err(Error::IncompatibleOptions("GT and LT".to_string()));
+    }
+    Ok(conditions)
+}
+
+/// Implement expire, pexpire, expireat and pexpireat as described here <https://redis.io/docs/latest/commands/expire/>
+/// The expiration is propagated as an absolute PEXPIREAT, so the replication lag doesn't extend the TTL.
+pub fn expire_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    unit: TimeUnit,
+    absolute: bool,
+) -> Result<Data> {
+    let [key, time, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let time: &str = time.try_into()?;
+    let time: i64 = time.parse()?;
+    let conditions = parse_expire_conditions(options)?;
+
+    let at_ms = expiration_ms(cmd, time, unit, absolute)?;
+
+    let propagated = match state.expire(key, from_unix_time_ms(at_ms), &conditions) {
+        ExpireOutcome::NotSet => return Ok(Data::Integer(0)),
+        ExpireOutcome::Set => Cmd::Pexpireat {
+            args: vec![Data::bulk_string(key), Data::bulk_string(at_ms.to_string())],
+        },
+        ExpireOutcome::Deleted => Cmd::Del {
+            args: vec![Data::bulk_string(key)],
+        },
+    };
+    super::propagate(&propagated, state);
+    Ok(Data::Integer(1))
+}
+
+/// Implement ttl, pttl, expiretime and pexpiretime as described here <https://redis.io/docs/latest/commands/ttl/>
+/// -2 is returned if the key doesn't exist and -1 if it has no expiration.
+pub fn ttl_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    unit: TimeUnit,
+    absolute: bool,
+) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let expiration = match state.expiration(key) {
+        Ok(Some(expiration)) => expiration,
+        Ok(None) => return Ok(Data::Integer(-1)),
+        Err(Error::NoSuchKey) => return Ok(Data::Integer(-2)),
+        Err(e) => return Err(e),
+    };
+    Ok(Data::Integer(ttl(expiration, unit, absolute)))
+}
+
+/// Remaining time to live, or the expiration itself if `absolute`
+pub fn ttl(expiration: SystemTime, unit: TimeUnit, absolute: bool) -> i64 {
+    let at_ms = unix_time_ms(expiration);
+    let ms = if absolute {
+        at_ms
+    } else {
+        (at_ms - unix_time_ms(SystemTime::now())).max(0)
+    };
+    unit.round_ms(ms)
+}
+
+/// Implement persist as described here <https://redis.io/docs/latest/commands/persist/>
+pub fn persist_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("persist".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    Ok(Data::Integer(i64::from(state.persist(key))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::{parse_expire_conditions, ExpireCondition, TimeUnit};
+    use crate::cmds::{execute, Session};
+    use crate::protocol::{Cmd, Data};
+    use crate::storage::{Config, Db};
+
+    fn options(options: &[&str]) -> Vec<Data> {
+        options.iter().map(|o| Data::bulk_string(*o)).collect()
+    }
+
+    #[test]
+    fn parse_expire_conditions_test() {
+        assert_eq!(
+            parse_expire_conditions(&options(&["xx", "GT"])).unwrap(),
+            vec![ExpireCondition::Xx, ExpireCondition::Gt]
+        );
+        for invalid in [&["NX", "XX"][..], &["nx", "lt"], &["GT", "LT"], &["EX"]] {
+            assert!(parse_expire_conditions(&options(invalid)).is_err());
+        }
+    }
+
+    #[test]
+    fn ttl_rounding_test() {
+        assert_eq!(TimeUnit::Seconds.round_ms(1499), 1);
+        assert_eq!(TimeUnit::Seconds.round_ms(1500), 2);
+        assert_eq!(TimeUnit::Milliseconds.round_ms(1499), 1499);
+        assert_eq!(TimeUnit::Seconds.to_ms(i64::MAX), None);
+    }
+
+    #[test]
+    fn lazy_expiration_propagated_test() {
+        let state = Arc::new(Db::new(Config::default()));
+        let mut session = Session::new(&state);
+        let mut run = |cmd: &[&str]| {
+            let cmd = Cmd::from_args(options(cmd)).unwrap();
+            execute(cmd, &state, &mut session).unwrap()
+        };
+        run(&["SET", "k", "v", "PX", "10"]);
+        run(&["HSET", "h", "a", "1", "b", "2"]);
+        run(&["HPEXPIRE", "h", "10", "FIELDS", "1", "a"]);
+        std::thread::sleep(Duration::from_millis(20));
+
+        let (tx, mut rx) = unbounded_channel();
+        state.register_slave(tx);
+        // the reads remove what expired, the slaves are sent the deletes
+        assert_eq!(run(&["GET", "k"]), Data::NullBuilkString);
+        assert_eq!(run(&["HLEN", "h"]), Data::Integer(1));
+        let sent: Vec<Data> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|cmd| cmd.to_data().unwrap())
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                Data::Array(options(&["DEL", "k"])),
+                Data::Array(options(&["HDEL", "h", "a"]))
+            ]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::sync::Arc;
+
+use super::string::{parse_float, parse_integer};
+use crate::error::{Error, Result};
+use crate::protocol::Data;
+use crate::storage::{
+    geohash_score, valid_lon_lat, Db, GeoFrom, GeoMatch, GeoSearch, GeoShape, GeoSort, GeoUnit,
+    SetCondition, ZaddOptions,
+};
+
+fn parse_unit(unit: &Data) -> Result<GeoUnit> {
+    let unit: &str = unit.try_into()?;
+    match unit.to_ascii_lowercase().as_str() {
+        "m" => Ok(GeoUnit::Meters),
+        "km" => Ok(GeoUnit::Kilometers),
+        "ft" => Ok(GeoUnit::Feet),
+        "mi" => Ok(GeoUnit::Miles),
+        _ => Err(Error::InvalidGeoUnit),
+    }
+}
+
+/// A longitude and a latitude within the limits of the geohashes
+fn parse_lon_lat(longitude: &Data, latitude: &Data) -> Result<(f64, f64)> {
+    let longitude = parse_float(longitude.try_into()?)?;
+    let latitude = parse_float(latitude.try_into()?)?;
+    if !valid_lon_lat(longitude, latitude) {
+        return Err(Error::InvalidLonLat(format!(
+            "{longitude:.6},{latitude:.6}"
+        )));
+    }
+    Ok((longitude, latitude))
+}
+
+/// A distance of a shape, `name` is used in the error
+fn parse_distance(distance: &Data, name: &str) -> Result<f64> {
+    parse_float(distance.try_into()?).map_err(|_| Error::NeedNumeric(name.to_string()))
+}
+
+/// Distances are replied with a precision of 0.1mm in the requested unit
+fn distance_reply(meters: f64, unit: GeoUnit) -> Data {
+    Data::BulkString(format!("{:.4}", meters / unit.meters()).into_bytes())
+}
+
+/// Implement geoadd as described here <https://redis.io/docs/latest/commands/geoadd/>
+/// GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+pub fn geoadd_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("geoadd".to_string()));
+    };
+    if args.len() < 3 {
+        return Err(Error::WrongNumberOfArgs("geoadd".to_string()));
+    }
+    let key: &str = key.try_into()?;
+
+    let (mut nx, mut xx, mut ch) = (false, false, false);
+    let mut position = 0;
+    for arg in args {
+        let Ok(option) = <&str>::try_from(arg) else {
+            break;
+        };
+        match option.to_ascii_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "CH" => ch = true,
+            _ => break,
+        }
+        position += 1;
+    }
+    let locations = &args[position..];
+    if (nx && xx) || locations.is_empty() || !locations.len().is_multiple_of(3) {
+        return Err(Error::Syntax);
+    }
+    let options = ZaddOptions {
+        condition: match (nx, xx) {
+            (true, _) => Some(SetCondition::Nx),
+            (_, true) => Some(SetCondition::Xx),
+            _ => None,
+        },
+        ..ZaddOptions::default()
+    };
+
+    let pairs = locations
+        .chunks_exact(3)
+        .map(|location| {
+            let (longitude, latitude) = parse_lon_lat(&location[0], &location[1])?;
+            Ok((
+                geohash_score(longitude, latitude),
+                <&[u8]>::try_from(&location[2])?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let outcome = state.zadd(key, &pairs, options)?;
+    Ok(Data::from(if ch { outcome.changed } else { outcome.added }))
+}
+
+/// Implement geodist as described here <https://redis.io/docs/latest/commands/geodist/>
+/// GEODIST key member1 member2 [M | KM | FT | MI]
+pub fn geodist_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, member1, member2, unit) = match args {
+        [key, member1, member2] => (key, member1, member2, GeoUnit::Meters),
+        [key, member1, member2, unit] => (key, member1, member2, parse_unit(unit)?),
+        [_, _, _, _, ..] => return Err(Error::Syntax),
+        _ => return Err(Error::WrongNumberOfArgs("geodist".to_string())),
+    };
+    let distance = state.geodist(key.try_into()?, member1.try_into()?, member2.try_into()?)?;
+    Ok(distance.map_or(Data::NullBuilkString, |distance| {
+        distance_reply(distance, unit)
+    }))
+}
+
+/// Implement geopos as described here <https://redis.io/docs/latest/commands/geopos/>
+pub fn geopos_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, members @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("geopos".to_string()));
+    };
+    let members = members
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::Array(
+        state
+            .geopos(key.try_into()?, &members)?
+            .into_iter()
+            .map(|position| {
+                position.map_or(Data::NullArray, |(longitude, latitude)| {
+                    Data::Array(vec![Data::Double(longitude), Data::Double(latitude)])
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Implement geohash as described here <https://redis.io/docs/latest/commands/geohash/>
+pub fn geohash_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, members @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("geohash".to_string()));
+    };
+    let members = members
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::Array(
+        state
+            .geohash(key.try_into()?, &members)?
+            .into_iter()
+            .map(|hash| {
+                hash.map_or(Data::NullBuilkString, |hash| {
+                    Data::bulk_string(hash.as_str())
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// The options of GEOSEARCH and GEOSEARCHSTORE
+#[derive(Debug)]
+struct SearchArgs {
+    search: GeoSearch,
+    /// The unit of the shape, also used for the replied or stored distances
+    unit: GeoUnit,
+    with_dist: bool,
+    with_hash: bool,
+    with_coord: bool,
+    /// STOREDIST, the distances are stored in the unit of the shape
+    store_dist: Option<GeoUnit>,
+}
+
+/// FROMMEMBER member | FROMLONLAT longitude latitude
+/// BYRADIUS radius unit | BYBOX width height unit
+/// [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH] or [STOREDIST] when storing
+fn parse_search(options: &[Data], store: bool) -> Result<SearchArgs> {
+    let (mut from, mut shape, mut unit) = (None, None, GeoUnit::Meters);
+    let (mut from_count, mut shape_count) = (0, 0);
+    let mut sort = GeoSort::Unsorted;
+    let (mut count, mut any) = (None, false);
+    let (mut with_dist, mut with_hash, mut with_coord, mut store_dist) =
+        (false, false, false, false);
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        let mut next = || options.next().ok_or(Error::Syntax);
+        match option.to_ascii_uppercase().as_str() {
+            "FROMMEMBER" => {
+                from = Some(GeoFrom::Member(<&[u8]>::try_from(next()?)?.to_vec()));
+                from_count += 1;
+            }
+            "FROMLONLAT" => {
+                let (longitude, latitude) = parse_lon_lat(next()?, next()?)?;
+                from = Some(GeoFrom::LonLat(longitude, latitude));
+                from_count += 1;
+            }
+            "BYRADIUS" => {
+                let radius = parse_distance(next()?, "radius")?;
+                if radius < 0.0 {
+                    return Err(Error::NegativeRadius);
+                }
+                unit = parse_unit(next()?)?;
+                shape = Some(GeoShape::Radius(radius * unit.meters()));
+                shape_count += 1;
+            }
+            "BYBOX" => {
+                let width = parse_distance(next()?, "width")?;
+                let height = parse_distance(next()?, "height")?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(Error::NegativeBoxSize);
+                }
+                unit = parse_unit(next()?)?;
+                shape = Some(GeoShape::Box {
+                    width: width * unit.meters(),
+                    height: height * unit.meters(),
+                });
+                shape_count += 1;
+            }
+            "ASC" => sort = GeoSort::Asc,
+            "DESC" => sort = GeoSort::Desc,
+            "COUNT" => {
+                let n = parse_integer(next()?.try_into()?)?;
+                count = Some(
+                    usize::try_from(n)
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or(Error::CountNotPositive)?,
+                );
+            }
+            "ANY" => any = true,
+            "WITHDIST" if !store => with_dist = true,
+            "WITHHASH" if !store => with_hash = true,
+            "WITHCOORD" if !store => with_coord = true,
+            "STOREDIST" if store => store_dist = true,
+            _ => return Err(Error::Syntax),
+        }
+    }
+
+    let (Some(from), 1) = (from, from_count) else {
+        return Err(Error::GeoSearchFrom);
+    };
+    let (Some(shape), 1) = (shape, shape_count) else {
+        return Err(Error::GeoSearchShape);
+    };
+    if any && count.is_none() {
+        return Err(Error::AnyWithoutCount);
+    }
+    Ok(SearchArgs {
+        search: GeoSearch {
+            from,
+            shape,
+            sort,
+            count,
+            any,
+        },
+        unit,
+        with_dist,
+        with_hash,
+        with_coord,
+        store_dist: store_dist.then_some(unit),
+    })
+}
+
+/// A member found by GEOSEARCH, with the requested details
+// the geohashes have 52 bits
+#[allow(clippy::cast_possible_truncation)]
+fn match_reply(found: GeoMatch, args: &SearchArgs) -> Data {
+    let member = Data::BulkString(found.member);
+    if !(args.with_dist || args.with_hash || args.with_coord) {
+        return member;
+    }
+    let mut reply = vec![member];
+    if args.with_dist {
+        reply.push(distance_reply(found.distance, args.unit));
+    }
+    if args.with_hash {
+        reply.push(Data::Integer(found.score as i64));
+    }
+    if args.with_coord {
+        reply.push(Data::Array(vec![
+            Data::Double(found.longitude),
+            Data::Double(found.latitude),
+        ]));
+    }
+    Data::Array(reply)
+}
+
+/// Implement geosearch as described here <https://redis.io/docs/latest/commands/geosearch/>
+pub fn geosearch_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("geosearch".to_string()));
+    };
+    if options.len() < 5 {
+        return Err(Error::WrongNumberOfArgs("geosearch".to_string()));
+    }
+    let args = parse_search(options, false)?;
+    Ok(Data::Array(
+        state
+            .geosearch(key.try_into()?, &args.search)?
+            .into_iter()
+            .map(|found| match_reply(found, &args))
+            .collect(),
+    ))
+}
+
+/// Implement geosearchstore as described here
+/// <https://redis.io/docs/latest/commands/geosearchstore/>
+pub fn geosearchstore_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [destination, source, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("geosearchstore".to_string()));
+    };
+    if options.len() < 5 {
+        return Err(Error::WrongNumberOfArgs("geosearchstore".to_string()));
+    }
+    let args = parse_search(options, true)?;
+    Ok(Data::from(state.geosearchstore(
+        destination.try_into()?,
+        source.try_into()?,
+        &args.search,
+        args.store_dist,
+    )?))
+}
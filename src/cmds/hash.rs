@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use super::expire::{self, TimeUnit};
+use super::keyspace::{parse_scan_args, scan_response};
+use super::string::parse_integer;
+use crate::error::{Error, Result};
+use crate::protocol::{Cmd, Data};
+use crate::storage::{Db, ExpireOutcome, FieldExpiration};
+
+fn parse_fields(fields: &[Data]) -> Result<Vec<&[u8]>> {
+    fields.iter().map(<&[u8]>::try_from).collect()
+}
+
+/// FIELDS numfields field [field ...], the trailing arguments of the field expiration commands
+fn parse_numfields(args: &[Data]) -> Result<Vec<&[u8]>> {
+    let [_, numfields, fields @ ..] = args else {
+        return Err(Error::Syntax);
+    };
+    let numfields = parse_integer(numfields.try_into()?)?;
+    if usize::try_from(numfields).ok() != Some(fields.len()) || fields.is_empty() {
+        return Err(Error::NumFields);
+    }
+    parse_fields(fields)
+}
+
+/// Position of the FIELDS keyword, the options of the command are before it
+fn fields_position(cmd: &str, args: &[Data]) -> Result<usize> {
+    args.iter()
+        .position(|arg| <&str>::try_from(arg).is_ok_and(|arg| arg.eq_ignore_ascii_case("FIELDS")))
+        .ok_or_else(|| Error::WrongNumberOfArgs(cmd.to_string()))
+}
+
+fn bulk_strings(elements: impl IntoIterator<Item = Vec<u8>>) -> Data {
+    Data::Array(elements.into_iter().map(Data::BulkString).collect())
+}
+
+/// Implement hset as described here <https://redis.io/docs/latest/commands/hset/>
+pub fn hset_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, pairs @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("hset".to_string()));
+    };
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(Error::WrongNumberOfArgs("hset".to_string()));
+    }
+    let key: &str = key.try_into()?;
+    let pairs = pairs
+        .chunks_exact(2)
+        .map(|pair| Ok((<&[u8]>::try_from(&pair[0])?, <&[u8]>::try_from(&pair[1])?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::from(state.hset(key, &pairs)?))
+}
+
+/// Implement hget as described here <https://redis.io/docs/latest/commands/hget/>
+pub fn hget_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, field] = args else {
+        return Err(Error::WrongNumberOfArgs("hget".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let value = state.hget(key, field.try_into()?)?;
+    Ok(value.map_or(Data::NullBuilkString, Data::BulkString))
+}
+
+/// Implement hmget as described here <https://redis.io/docs/latest/commands/hmget/>
+pub fn hmget_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, fields @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("hmget".to_string()));
+    };
+    if fields.is_empty() {
+        return Err(Error::WrongNumberOfArgs("hmget".to_string()));
+    }
+    let key: &str = key.try_into()?;
+    let values = state.hmget(key, &parse_fields(fields)?)?;
+    Ok(Data::Array(
+        values
+            .into_iter()
+            .map(|value| value.map_or(Data::NullBuilkString, Data::BulkString))
+            .collect(),
+    ))
+}
+
+/// Implement hdel as described here <https://redis.io/docs/latest/commands/hdel/>
+pub fn hdel_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, fields @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("hdel".to_string()));
+    };
+    if fields.is_empty() {
+        return Err(Error::WrongNumberOfArgs("hdel".to_string()));
+    }
+    let key: &str = key.try_into()?;
+    Ok(Data::from(state.hdel(key, &parse_fields(fields)?)?))
+}
+
+/// Implement hgetall as described here <https://redis.io/docs/latest/commands/hgetall/>
+pub fn hgetall_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("hgetall".to_string()));
+    };
+    let entries = state.hgetall(key.try_into()?)?;
+    Ok(Data::Map(
+        entries
+            .into_iter()
+            .map(|(field, value)| (Data::BulkString(field), Data::BulkString(value)))
+            .collect(),
+    ))
+}
+
+/// Implement hkeys as described here <https://redis.io/docs/latest/commands/hkeys/>
+pub fn hkeys_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("hkeys".to_string()));
+    };
+    let entries = state.hgetall(key.try_into()?)?;
+    Ok(bulk_strings(entries.into_iter().map(|(field, _)| field)))
+}
+
+/// Implement hvals as described here <https://redis.io/docs/latest/commands/hvals/>
+pub fn hvals_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("hvals".to_string()));
+    };
+    let entries = state.hgetall(key.try_into()?)?;
+    Ok(bulk_strings(entries.into_iter().map(|(_, value)| value)))
+}
+
+/// Implement hlen as described here <https://redis.io/docs/latest/commands/hlen/>
+pub fn hlen_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("hlen".to_string()));
+    };
+    Ok(Data::from(state.hlen(key.try_into()?)?))
+}
+
+/// Implement hexists as described here <https://redis.io/docs/latest/commands/hexists/>
+pub fn hexists_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, field] = args else {
+        return Err(Error::WrongNumberOfArgs("hexists".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let exists = state.hexists(key, field.try_into()?)?;
+    Ok(Data::Integer(i64::from(exists)))
+}
+
+/// Implement hincrby as described here <https://redis.io/docs/latest/commands/hincrby/>
+pub fn hincrby_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, field, increment] = args else {
+        return Err(Error::WrongNumberOfArgs("hincrby".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let increment = parse_integer(increment.try_into()?)?;
+    state
+        .update_field(key, field.try_into()?, |value, exists| {
+            let current = if exists {
+                parse_integer(value).map_err(|_| Error::HashValueNotInteger)?
+            } else {
+                0
+            };
+            let updated = current.checked_add(increment).ok_or(Error::Overflow)?;
+            *value = updated.to_string().into_bytes();
+            Ok(updated)
+        })
+        .map(Data::Integer)
+}
+
+/// Implement hscan as described here <https://redis.io/docs/latest/commands/hscan/>
+pub fn hscan_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("hscan".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let scan_args = parse_scan_args("hscan", args)?;
+    let (cursor, entries) =
+        state.hscan(key, scan_args.cursor, scan_args.count, scan_args.pattern)?;
+
+    let mut elements = Vec::with_capacity(entries.len() * 2);
+    for (field, value) in entries {
+        elements.push(Data::BulkString(field));
+        if !scan_args.no_values {
+            elements.push(Data::BulkString(value));
+        }
+    }
+    Ok(scan_response(cursor, elements))
+}
+
+/// Implement hexpire, hpexpire, hexpireat and hpexpireat as described here <https://redis.io/docs/latest/commands/hexpire/>
+/// Replies for each field -2 if it doesn't exist, 0 if a condition is not met, 1 if the expiration
+/// is set and 2 if the field was deleted because the expiration is in the past.
+/// As for the keys, the expiration is propagated as an absolute HPEXPIREAT.
+pub fn hexpire_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    unit: TimeUnit,
+    absolute: bool,
+) -> Result<Data> {
+    let [key, time, rest @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let time = parse_integer(time.try_into()?)?;
+    if time < 0 {
+        return Err(Error::InvalidExpireTime(cmd.to_string()));
+    }
+    let position = fields_position(cmd, rest)?;
+    let conditions = expire::parse_expire_conditions(&rest[..position])?;
+    if conditions.len() > 1 {
+        return Err(Error::Syntax);
+    }
+    let fields = parse_numfields(&rest[position..])?;
+
+    let at_ms = expire::expiration_ms(cmd, time, unit, absolute)?;
+    let outcomes = state.hexpire(key, &fields, expire::from_unix_time_ms(at_ms), &conditions)?;
+
+    let updated: Vec<Data> = fields
+        .iter()
+        .zip(&outcomes)
+        .filter(|(_, outcome)| matches!(outcome, Some(ExpireOutcome::Set | ExpireOutcome::Deleted)))
+        .map(|(field, _)| Data::BulkString(field.to_vec()))
+        .collect();
+    if !updated.is_empty() {
+        let mut args = vec![
+            Data::bulk_string(key),
+            Data::bulk_string(at_ms.to_string()),
+            Data::bulk_string("FIELDS"),
+            Data::bulk_string(updated.len().to_string()),
+        ];
+        args.extend(updated);
+        super::propagate(&Cmd::Hpexpireat { args }, state);
+    }
+
+    Ok(Data::Array(
+        outcomes
+            .into_iter()
+            .map(|outcome| {
+                Data::Integer(match outcome {
+                    None => -2,
+                    Some(ExpireOutcome::NotSet) => 0,
+                    Some(ExpireOutcome::Set) => 1,
+                    Some(ExpireOutcome::Deleted) => 2,
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Implement httl, hpttl, hexpiretime and hpexpiretime as described here <https://redis.io/docs/latest/commands/httl/>
+/// Replies for each field -2 if it doesn't exist and -1 if it has no expiration.
+pub fn httl_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    unit: TimeUnit,
+    absolute: bool,
+) -> Result<Data> {
+    let [key, fields @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    if fields_position(cmd, fields)? != 0 {
+        return Err(Error::Syntax);
+    }
+    let expirations = state.hexpiration(key, &parse_numfields(fields)?)?;
+    Ok(Data::Array(
+        expirations
+            .into_iter()
+            .map(|expiration| {
+                Data::Integer(match expiration {
+                    FieldExpiration::NoField => -2,
+                    FieldExpiration::Persistent => -1,
+                    FieldExpiration::At(at) => expire::ttl(at, unit, absolute),
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Implement hpersist as described here <https://redis.io/docs/latest/commands/hpersist/>
+/// Replies for each field -2 if it doesn't exist, -1 if it has no expiration and 1 if the
+/// expiration was removed.
+pub fn hpersist_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, fields @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("hpersist".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    if fields_position("hpersist", fields)? != 0 {
+        return Err(Error::Syntax);
+    }
+    let expirations = state.hpersist(key, &parse_numfields(fields)?)?;
+    Ok(Data::Array(
+        expirations
+            .into_iter()
+            .map(|expiration| {
+                Data::Integer(match expiration {
+                    FieldExpiration::NoField => -2,
+                    FieldExpiration::Persistent => -1,
+                    FieldExpiration::At(_) => 1,
+                })
+            })
+            .collect(),
+    ))
+}
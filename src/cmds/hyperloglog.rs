@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::protocol::Data;
+use crate::storage::Db;
+
+/// Implement pfadd as described here <https://redis.io/docs/latest/commands/pfadd/>
+pub fn pfadd_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, elements @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("pfadd".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let elements = elements
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::Integer(i64::from(state.pfadd(key, &elements)?)))
+}
+
+/// Implement pfcount as described here <https://redis.io/docs/latest/commands/pfcount/>
+pub fn pfcount_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    if args.is_empty() {
+        return Err(Error::WrongNumberOfArgs("pfcount".to_string()));
+    }
+    let keys = args
+        .iter()
+        .map(<&str>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    let count = i64::try_from(state.pfcount(&keys)?).map_err(|_| Error::Overflow)?;
+    Ok(Data::Integer(count))
+}
+
+/// Implement pfmerge as described here <https://redis.io/docs/latest/commands/pfmerge/>
+pub fn pfmerge_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [destination, keys @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("pfmerge".to_string()));
+    };
+    let keys = keys
+        .iter()
+        .map(<&str>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    state.pfmerge(destination.try_into()?, &keys)?;
+    Ok(Data::ok_response())
+}
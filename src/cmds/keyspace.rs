@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::protocol::Data;
+use crate::storage::Db;
+
+/// Options shared by the SCAN family of commands
+/// <cursor> [MATCH pattern] [COUNT count] [TYPE type] [NOVALUES]
+/// TYPE is specific to SCAN and NOVALUES to HSCAN.
+pub(super) struct ScanArgs<'a> {
+    pub cursor: u64,
+    pub pattern: Option<&'a str>,
+    pub count: usize,
+    pub value_type: Option<&'a str>,
+    pub no_values: bool,
+}
+
+const DEFAULT_SCAN_COUNT: usize = 10;
+
+pub(super) fn parse_scan_args<'a>(cmd: &str, args: &'a [Data]) -> Result<ScanArgs<'a>> {
+    let [cursor, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let cursor: &str = cursor.try_into()?;
+    let cursor = cursor.parse().map_err(|_| Error::InvalidCursor)?;
+
+    let mut scan_args = ScanArgs {
+        cursor,
+        pattern: None,
+        count: DEFAULT_SCAN_COUNT,
+        value_type: None,
+        no_values: false,
+    };
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        let option = option.to_ascii_uppercase();
+        if option == "NOVALUES" && cmd == "hscan" {
+            scan_args.no_values = true;
+            continue;
+        }
+        let value: &str = options.next().ok_or(Error::Syntax)?.try_into()?;
+        match option.as_str() {
+            "MATCH" => scan_args.pattern = Some(value),
+            "COUNT" => {
+                let count: usize = value.parse()?;
+                if count < 1 {
+                    return Err(Error::Syntax);
+                }
+                scan_args.count = count;
+            }
+            "TYPE" if cmd == "scan" => scan_args.value_type = Some(value),
+            _ => return Err(Error::Syntax),
+        }
+    }
+    Ok(scan_args)
+}
+
+/// Cursor based iteration reply: the next cursor and the elements returned by this call
+pub(super) fn scan_response(cursor: u64, elements: Vec<Data>) -> Data {
+    Data::Array(vec![
+        Data::bulk_string(cursor.to_string()),
+        Data::Array(elements),
+    ])
+}
+
+/// Implement the scan command as described here <https://redis.io/docs/latest/commands/scan/>
+pub fn scan_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let scan_args = parse_scan_args("scan", args)?;
+    let (cursor, keys) = state.scan(
+        scan_args.cursor,
+        scan_args.count,
+        scan_args.pattern,
+        scan_args.value_type,
+    );
+    Ok(scan_response(
+        cursor,
+        keys.into_iter().map(Data::bulk_string).collect(),
+    ))
+}
+
+fn parse_keys(args: &[Data]) -> Result<Vec<&str>> {
+    args.iter().map(<&str>::try_from).collect()
+}
+
+/// Implement del and unlink, as described here <https://redis.io/docs/latest/commands/del/>
+/// values are small enough to be freed synchronously, so unlink is the same as del
+pub fn del_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    if args.is_empty() {
+        return Err(Error::WrongNumberOfArgs("del".to_string()));
+    }
+    let keys = parse_keys(args)?;
+    Ok(Data::from(state.del(&keys)))
+}
+
+/// Implement exists as described here <https://redis.io/docs/latest/commands/exists/>
+pub fn exists_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    if args.is_empty() {
+        return Err(Error::WrongNumberOfArgs("exists".to_string()));
+    }
+    let keys = parse_keys(args)?;
+    Ok(Data::from(state.exists(&keys)))
+}
+
+/// Implement type as described here <https://redis.io/docs/latest/commands/type/>
+pub fn type_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("type".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let value_type = state.value_type(key).unwrap_or("none");
+    Ok(Data::SimpleString(value_type.to_string()))
+}
+
+/// Implement rename and renamenx as described here <https://redis.io/docs/latest/commands/rename/>
+pub fn rename_execute(args: &[Data], state: &Arc<Db>, nx: bool) -> Result<Data> {
+    let [key, new_key] = args else {
+        let cmd = if nx { "renamenx" } else { "rename" };
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let new_key: &str = new_key.try_into()?;
+
+    let renamed = state.rename(key, new_key, nx)?;
+    if nx {
+        Ok(Data::Integer(i64::from(renamed)))
+    } else {
+        Ok(Data::ok_response())
+    }
+}
+
+/// Implement copy as described here <https://redis.io/docs/latest/commands/copy/>
+/// COPY source destination [DB destination-db] [REPLACE]
+pub fn copy_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [source, destination, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("copy".to_string()));
+    };
+    let source: &str = source.try_into()?;
+    let destination: &str = destination.try_into()?;
+
+    let mut replace = false;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        match option.to_ascii_uppercase().as_str() {
+            "REPLACE" => replace = true,
+            "DB" => {
+                // there is a single database
+                let db: &str = options.next().ok_or(Error::Syntax)?.try_into()?;
+                if db.parse::<i64>()? != 0 {
+                    return Err(Error::DbIndexOutOfRange);
+                }
+            }
+            _ => return Err(Error::Syntax),
+        }
+    }
+
+    let copied = state.copy(source, destination, replace);
+    Ok(Data::Integer(i64::from(copied)))
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use super::string::parse_integer;
+use crate::error::{Error, Result};
+use crate::protocol::Data;
+use crate::storage::{Db, ListEnd};
+
+fn parse_index(index: &Data) -> Result<i64> {
+    parse_integer(index.try_into()?)
+}
+
+/// LEFT or RIGHT, as used by LMOVE
+pub fn parse_list_end(end: &Data) -> Result<ListEnd> {
+    let end: &str = end.try_into()?;
+    match end.to_ascii_uppercase().as_str() {
+        "LEFT" => Ok(ListEnd::Left),
+        "RIGHT" => Ok(ListEnd::Right),
+        _ => Err(Error::Syntax),
+    }
+}
+
+fn bulk_or_null(element: Option<Vec<u8>>) -> Data {
+    element.map_or(Data::NullBuilkString, Data::BulkString)
+}
+
+/// Implement lpush and rpush as described here <https://redis.io/docs/latest/commands/lpush/>
+pub fn push_execute(cmd: &str, args: &[Data], state: &Arc<Db>, end: ListEnd) -> Result<Data> {
+    let [key, elements @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    if elements.is_empty() {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    }
+    let key: &str = key.try_into()?;
+    let elements = elements
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Data::from(state.push(key, &elements, end)?))
+}
+
+/// Implement lpop and rpop as described here <https://redis.io/docs/latest/commands/lpop/>
+/// Without count a single element is returned, otherwise an array.
+pub fn pop_execute(cmd: &str, args: &[Data], state: &Arc<Db>, end: ListEnd) -> Result<Data> {
+    let (key, count) = match args {
+        [key] => (key, None),
+        [key, count] => {
+            let count = usize::try_from(parse_index(count)?).map_err(|_| Error::NotPositive)?;
+            (key, Some(count))
+        }
+        _ => return Err(Error::WrongNumberOfArgs(cmd.to_string())),
+    };
+    let key: &str = key.try_into()?;
+
+    let popped = state.pop(key, end, count.unwrap_or(1))?;
+    Ok(match (popped, count) {
+        (None, None) => Data::NullBuilkString,
+        (None, Some(_)) => Data::NullArray,
+        (Some(popped), None) => bulk_or_null(popped.into_iter().next()),
+        (Some(popped), Some(_)) => Data::Array(popped.into_iter().map(Data::BulkString).collect()),
+    })
+}
+
+/// Implement lrange as described here <https://redis.io/docs/latest/commands/lrange/>
+pub fn lrange_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, start, stop] = args else {
+        return Err(Error::WrongNumberOfArgs("lrange".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let elements = state.lrange(key, parse_index(start)?, parse_index(stop)?)?;
+    Ok(Data::Array(
+        elements.into_iter().map(Data::BulkString).collect(),
+    ))
+}
+
+/// Implement llen as described here <https://redis.io/docs/latest/commands/llen/>
+pub fn llen_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("llen".to_string()));
+    };
+    Ok(Data::from(state.llen(key.try_into()?)?))
+}
+
+/// Implement lindex as described here <https://redis.io/docs/latest/commands/lindex/>
+pub fn lindex_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, index] = args else {
+        return Err(Error::WrongNumberOfArgs("lindex".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    Ok(bulk_or_null(state.lindex(key, parse_index(index)?)?))
+}
+
+/// Implement lset as described here <https://redis.io/docs/latest/commands/lset/>
+pub fn lset_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, index, element] = args else {
+        return Err(Error::WrongNumberOfArgs("lset".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    state.lset(key, parse_index(index)?, element.try_into()?)?;
+    Ok(Data::ok_response())
+}
+
+/// Implement lrem as described here <https://redis.io/docs/latest/commands/lrem/>
+pub fn lrem_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, count, element] = args else {
+        return Err(Error::WrongNumberOfArgs("lrem".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let removed = state.lrem(key, parse_index(count)?, element.try_into()?)?;
+    Ok(Data::from(removed))
+}
+
+/// Implement ltrim as described here <https://redis.io/docs/latest/commands/ltrim/>
+pub fn ltrim_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, start, stop] = args else {
+        return Err(Error::WrongNumberOfArgs("ltrim".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    state.ltrim(key, parse_index(start)?, parse_index(stop)?)?;
+    Ok(Data::ok_response())
+}
+
+/// Implement linsert as described here <https://redis.io/docs/latest/commands/linsert/>
+pub fn linsert_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, position, pivot, element] = args else {
+        return Err(Error::WrongNumberOfArgs("linsert".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let position: &str = position.try_into()?;
+    let before = match position.to_ascii_uppercase().as_str() {
+        "BEFORE" => true,
+        "AFTER" => false,
+        _ => return Err(Error::Syntax),
+    };
+    let len = state.linsert(key, before, pivot.try_into()?, element.try_into()?)?;
+    Ok(Data::Integer(len))
+}
+
+/// Implement lmove as described here <https://redis.io/docs/latest/commands/lmove/>
+pub fn lmove_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [source, destination, from, to] = args else {
+        return Err(Error::WrongNumberOfArgs("lmove".to_string()));
+    };
+    let source: &str = source.try_into()?;
+    let destination: &str = destination.try_into()?;
+    let (from, to) = (parse_list_end(from)?, parse_list_end(to)?);
+    Ok(bulk_or_null(state.lmove(source, destination, from, to)?))
+}
@@ -1,27 +1,320 @@
 use std::sync::Arc;
 
 use crate::error::Result;
-use crate::protocol::{Cmd, Data};
+use crate::protocol::{Cmd, Data, RespVersion};
 use crate::replication::master;
 use crate::storage::Db;
+use crate::storage::{ListEnd, SetOperation};
+use blocking::BlockingCmd;
+use expire::TimeUnit;
+use sorted_set::RangeKind;
 mod basic;
+mod bitmap;
+mod blocking;
+mod expire;
+mod geo;
+mod hash;
+mod hyperloglog;
+mod keyspace;
+mod list;
 mod replication;
+mod set;
 mod set_get;
+mod sorted_set;
+mod stream;
+mod string;
 
-pub fn execute(cmd: Cmd, state: &Arc<Db>) -> Result<Data> {
-    if cmd.is_write() && state.info().is_master() {
+/// State attached to a single client connection
+#[derive(Debug)]
+pub struct Session {
+    pub id: u64,
+    pub resp_version: RespVersion,
+}
+
+impl Session {
+    pub fn new(state: &Db) -> Self {
+        Self {
+            id: state.next_client_id(),
+            resp_version: RespVersion::default(),
+        }
+    }
+}
+
+pub fn execute(cmd: Cmd, state: &Arc<Db>, session: &mut Session) -> Result<Data> {
+    // only the writes applied by the master are propagated
+    let is_master = state.info().is_master();
+    let propagate = (cmd.is_write() && is_master).then(|| cmd.clone());
+    // the writes are applied and propagated in one step, including the writes propagated by
+    // the commands themselves, so the slaves apply the writes in the order of the master
+    let writes = cmd.is_write() || cmd.propagates_itself();
+    let ordered = (writes && is_master).then(|| state.order_writes());
+
+    let response = execute_cmd(cmd, state, session);
+
+    if !is_master {
+        // the slaves apply the deletes of the master, the keys they expire are not propagated
+        state.take_lazily_expired();
+        return response;
+    }
+    // the keys and fields expired by the command are deleted on the slaves before the command
+    // itself, a read is only ordered with the writes when it expired some
+    let ordered = ordered.or_else(|| state.has_lazily_expired().then(|| state.order_writes()));
+    if ordered.is_some() {
+        master::broadcast_expired(state.take_lazily_expired(), state);
+    }
+    let response = response?;
+    if let Some(cmd) = propagate {
         master::broadcast_cmd(&cmd, state);
     }
+    Ok(response)
+}
+
+/// Execute a blocking command, parking the client until it is served or times out.
+/// The commands served are propagated to the slaves by the blocking module itself.
+pub async fn execute_blocking(cmd: Cmd, state: &Arc<Db>, session: &Session) -> Result<Data> {
+    let blocking = BlockingCmd::from_cmd(&cmd, state)?;
+    blocking
+        .block(state, session.id, session.resp_version)
+        .await
+}
+
+/// Propagate a command to the slaves on behalf of a write that can't be replayed as is
+fn propagate(cmd: &Cmd, state: &Arc<Db>) {
+    if state.info().is_master() {
+        master::broadcast_expired(state.take_lazily_expired(), state);
+        master::broadcast_cmd(cmd, state);
+    }
+}
+
+// a single dispatch table, one arm per command
+#[allow(clippy::too_many_lines)]
+fn execute_cmd(cmd: Cmd, state: &Arc<Db>, session: &mut Session) -> Result<Data> {
     tracing::debug_span!("cmd_execute", cmd = ?cmd).in_scope(|| match cmd {
         Cmd::ConnectionClosed => Ok(Data::ConnectionClosed),
         Cmd::Ping => Ok(basic::ping_execute()),
         Cmd::Echo { args } => basic::echo_execute(&args),
         Cmd::Get { args } => set_get::get_execute(&args, state),
         Cmd::Set { args } => set_get::set_execute(&args, state),
+        Cmd::Setnx { args } => set_get::setnx_execute(&args, state),
+        Cmd::Setex { args } => set_get::setex_execute("setex", &args, state),
+        Cmd::Psetex { args } => set_get::setex_execute("psetex", &args, state),
+        Cmd::Getex { args } => set_get::getex_execute(&args, state),
+        Cmd::Getdel { args } => set_get::getdel_execute(&args, state),
+        Cmd::Incr { args } => string::incr_execute(&args, state),
+        Cmd::Decr { args } => string::decr_execute(&args, state),
+        Cmd::Incrby { args } => string::incrby_execute(&args, state),
+        Cmd::Decrby { args } => string::decrby_execute(&args, state),
+        Cmd::Incrbyfloat { args } => string::incrbyfloat_execute(&args, state),
+        Cmd::Append { args } => string::append_execute(&args, state),
+        Cmd::Strlen { args } => string::strlen_execute(&args, state),
+        Cmd::Getrange { args } => string::getrange_execute(&args, state),
+        Cmd::Setrange { args } => string::setrange_execute(&args, state),
+        Cmd::Mget { args } => string::mget_execute(&args, state),
+        Cmd::Mset { args } => string::mset_execute("mset", &args, state, false),
+        Cmd::Msetnx { args } => string::mset_execute("msetnx", &args, state, true),
+        Cmd::Lcs { args } => string::lcs_execute(&args, state),
+        Cmd::Setbit { args } => bitmap::setbit_execute(&args, state),
+        Cmd::Getbit { args } => bitmap::getbit_execute(&args, state),
+        Cmd::Bitcount { args } => bitmap::bitcount_execute(&args, state),
+        Cmd::Bitpos { args } => bitmap::bitpos_execute(&args, state),
+        Cmd::Bitop { args } => bitmap::bitop_execute(&args, state),
+        Cmd::Bitfield { args } => bitmap::bitfield_execute(&args, state),
+        Cmd::Pfadd { args } => hyperloglog::pfadd_execute(&args, state),
+        Cmd::Pfcount { args } => hyperloglog::pfcount_execute(&args, state),
+        Cmd::Pfmerge { args } => hyperloglog::pfmerge_execute(&args, state),
+        Cmd::Geoadd { args } => geo::geoadd_execute(&args, state),
+        Cmd::Geodist { args } => geo::geodist_execute(&args, state),
+        Cmd::Geopos { args } => geo::geopos_execute(&args, state),
+        Cmd::Geohash { args } => geo::geohash_execute(&args, state),
+        Cmd::Geosearch { args } => geo::geosearch_execute(&args, state),
+        Cmd::Geosearchstore { args } => geo::geosearchstore_execute(&args, state),
+        Cmd::Lpush { args } => list::push_execute("lpush", &args, state, ListEnd::Left),
+        Cmd::Rpush { args } => list::push_execute("rpush", &args, state, ListEnd::Right),
+        Cmd::Lpop { args } => list::pop_execute("lpop", &args, state, ListEnd::Left),
+        Cmd::Rpop { args } => list::pop_execute("rpop", &args, state, ListEnd::Right),
+        Cmd::Lrange { args } => list::lrange_execute(&args, state),
+        Cmd::Llen { args } => list::llen_execute(&args, state),
+        Cmd::Lindex { args } => list::lindex_execute(&args, state),
+        Cmd::Lset { args } => list::lset_execute(&args, state),
+        Cmd::Lrem { args } => list::lrem_execute(&args, state),
+        Cmd::Ltrim { args } => list::ltrim_execute(&args, state),
+        Cmd::Linsert { args } => list::linsert_execute(&args, state),
+        Cmd::Lmove { args } => list::lmove_execute(&args, state),
+        // the blocking commands only block when run by the clients, see execute_blocking
+        cmd @ (Cmd::Blpop { .. }
+        | Cmd::Brpop { .. }
+        | Cmd::Blmove { .. }
+        | Cmd::Bzpopmin { .. }
+        | Cmd::Bzpopmax { .. }) => {
+            BlockingCmd::from_cmd(&cmd, state)?.execute_now(state, session.resp_version)
+        }
+        Cmd::Hset { args } => hash::hset_execute(&args, state),
+        Cmd::Hget { args } => hash::hget_execute(&args, state),
+        Cmd::Hmget { args } => hash::hmget_execute(&args, state),
+        Cmd::Hdel { args } => hash::hdel_execute(&args, state),
+        Cmd::Hgetall { args } => hash::hgetall_execute(&args, state),
+        Cmd::Hincrby { args } => hash::hincrby_execute(&args, state),
+        Cmd::Hexists { args } => hash::hexists_execute(&args, state),
+        Cmd::Hkeys { args } => hash::hkeys_execute(&args, state),
+        Cmd::Hvals { args } => hash::hvals_execute(&args, state),
+        Cmd::Hlen { args } => hash::hlen_execute(&args, state),
+        Cmd::Hscan { args } => hash::hscan_execute(&args, state),
+        Cmd::Hexpire { args } => {
+            hash::hexpire_execute("hexpire", &args, state, TimeUnit::Seconds, false)
+        }
+        Cmd::Hpexpire { args } => {
+            hash::hexpire_execute("hpexpire", &args, state, TimeUnit::Milliseconds, false)
+        }
+        Cmd::Hexpireat { args } => {
+            hash::hexpire_execute("hexpireat", &args, state, TimeUnit::Seconds, true)
+        }
+        Cmd::Hpexpireat { args } => {
+            hash::hexpire_execute("hpexpireat", &args, state, TimeUnit::Milliseconds, true)
+        }
+        Cmd::Httl { args } => hash::httl_execute("httl", &args, state, TimeUnit::Seconds, false),
+        Cmd::Hpttl { args } => {
+            hash::httl_execute("hpttl", &args, state, TimeUnit::Milliseconds, false)
+        }
+        Cmd::Hexpiretime { args } => {
+            hash::httl_execute("hexpiretime", &args, state, TimeUnit::Seconds, true)
+        }
+        Cmd::Hpexpiretime { args } => {
+            hash::httl_execute("hpexpiretime", &args, state, TimeUnit::Milliseconds, true)
+        }
+        Cmd::Hpersist { args } => hash::hpersist_execute(&args, state),
+        Cmd::Sadd { args } => set::sadd_execute(&args, state),
+        Cmd::Srem { args } => set::srem_execute(&args, state),
+        Cmd::Smembers { args } => set::smembers_execute(&args, state),
+        Cmd::Sismember { args } => set::sismember_execute(&args, state),
+        Cmd::Smismember { args } => set::smismember_execute(&args, state),
+        Cmd::Scard { args } => set::scard_execute(&args, state),
+        Cmd::Sinter { args } => set::operation_execute("sinter", &args, state, SetOperation::Inter),
+        Cmd::Sunion { args } => set::operation_execute("sunion", &args, state, SetOperation::Union),
+        Cmd::Sdiff { args } => set::operation_execute("sdiff", &args, state, SetOperation::Diff),
+        Cmd::Sinterstore { args } => {
+            set::operation_store_execute("sinterstore", &args, state, SetOperation::Inter)
+        }
+        Cmd::Sunionstore { args } => {
+            set::operation_store_execute("sunionstore", &args, state, SetOperation::Union)
+        }
+        Cmd::Sdiffstore { args } => {
+            set::operation_store_execute("sdiffstore", &args, state, SetOperation::Diff)
+        }
+        Cmd::Sintercard { args } => set::sintercard_execute(&args, state),
+        Cmd::Srandmember { args } => set::srandmember_execute(&args, state),
+        Cmd::Spop { args } => set::spop_execute(&args, state),
+        Cmd::Sscan { args } => set::sscan_execute(&args, state),
+        Cmd::Zadd { args } => sorted_set::zadd_execute(&args, state),
+        Cmd::Zincrby { args } => sorted_set::zincrby_execute(&args, state),
+        Cmd::Zrem { args } => sorted_set::zrem_execute(&args, state),
+        Cmd::Zcard { args } => sorted_set::zcard_execute(&args, state),
+        Cmd::Zscore { args } => sorted_set::zscore_execute(&args, state),
+        Cmd::Zrank { args } => sorted_set::zrank_execute("zrank", &args, state, false),
+        Cmd::Zrevrank { args } => sorted_set::zrank_execute("zrevrank", &args, state, true),
+        Cmd::Zrange { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute("zrange", &args, state, version, RangeKind::Rank, false)
+        }
+        Cmd::Zrevrange { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute("zrevrange", &args, state, version, RangeKind::Rank, true)
+        }
+        Cmd::Zrangebyscore { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute(
+                "zrangebyscore",
+                &args,
+                state,
+                version,
+                RangeKind::Score,
+                false,
+            )
+        }
+        Cmd::Zrevrangebyscore { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute(
+                "zrevrangebyscore",
+                &args,
+                state,
+                version,
+                RangeKind::Score,
+                true,
+            )
+        }
+        Cmd::Zrangebylex { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute("zrangebylex", &args, state, version, RangeKind::Lex, false)
+        }
+        Cmd::Zrevrangebylex { args } => {
+            let version = session.resp_version;
+            sorted_set::zrange_execute(
+                "zrevrangebylex",
+                &args,
+                state,
+                version,
+                RangeKind::Lex,
+                true,
+            )
+        }
+        Cmd::Zunionstore { args } => {
+            sorted_set::zstore_execute("zunionstore", &args, state, SetOperation::Union)
+        }
+        Cmd::Zinterstore { args } => {
+            sorted_set::zstore_execute("zinterstore", &args, state, SetOperation::Inter)
+        }
+        Cmd::Zpopmin { args } => {
+            sorted_set::zpop_execute("zpopmin", &args, state, session.resp_version, false)
+        }
+        Cmd::Zpopmax { args } => {
+            sorted_set::zpop_execute("zpopmax", &args, state, session.resp_version, true)
+        }
+        Cmd::Zscan { args } => sorted_set::zscan_execute(&args, state),
+        Cmd::Xadd { args } => stream::xadd_execute(&args, state),
+        Cmd::Xlen { args } => stream::xlen_execute(&args, state),
+        Cmd::Xrange { args } => stream::xrange_execute(&args, state),
+        Cmd::Xtrim { args } => stream::xtrim_execute(&args, state),
+        Cmd::Xread { args } => stream::xread_execute(&args, state, session.resp_version),
+        Cmd::Xgroup { args } => stream::xgroup_execute(&args, state),
+        Cmd::Xreadgroup { args } => stream::xreadgroup_execute(&args, state, session.resp_version),
+        Cmd::Xack { args } => stream::xack_execute(&args, state),
+        Cmd::Xpending { args } => stream::xpending_execute(&args, state),
+        Cmd::Xclaim { args } => stream::xclaim_execute(&args, state),
+        Cmd::Xautoclaim { args } => stream::xautoclaim_execute(&args, state),
         Cmd::Config { args } => basic::config_execute(&args, state),
         Cmd::Command { args } => Ok(basic::command_execute(&args)),
         Cmd::Keys { args } => set_get::keys_execute(&args, state),
+        Cmd::Scan { args } => keyspace::scan_execute(&args, state),
+        Cmd::Del { args } | Cmd::Unlink { args } => keyspace::del_execute(&args, state),
+        Cmd::Exists { args } => keyspace::exists_execute(&args, state),
+        Cmd::Type { args } => keyspace::type_execute(&args, state),
+        Cmd::Rename { args } => keyspace::rename_execute(&args, state, false),
+        Cmd::Renamenx { args } => keyspace::rename_execute(&args, state, true),
+        Cmd::Copy { args } => keyspace::copy_execute(&args, state),
+        Cmd::Expire { args } => {
+            expire::expire_execute("expire", &args, state, TimeUnit::Seconds, false)
+        }
+        Cmd::Pexpire { args } => {
+            expire::expire_execute("pexpire", &args, state, TimeUnit::Milliseconds, false)
+        }
+        Cmd::Expireat { args } => {
+            expire::expire_execute("expireat", &args, state, TimeUnit::Seconds, true)
+        }
+        Cmd::Pexpireat { args } => {
+            expire::expire_execute("pexpireat", &args, state, TimeUnit::Milliseconds, true)
+        }
+        Cmd::Ttl { args } => expire::ttl_execute("ttl", &args, state, TimeUnit::Seconds, false),
+        Cmd::Pttl { args } => {
+            expire::ttl_execute("pttl", &args, state, TimeUnit::Milliseconds, false)
+        }
+        Cmd::Expiretime { args } => {
+            expire::ttl_execute("expiretime", &args, state, TimeUnit::Seconds, true)
+        }
+        Cmd::Pexpiretime { args } => {
+            expire::ttl_execute("pexpiretime", &args, state, TimeUnit::Milliseconds, true)
+        }
+        Cmd::Persist { args } => expire::persist_execute(&args, state),
         Cmd::Info { args } => basic::info_execute(&args, state),
+        Cmd::Hello { args } => basic::hello_execute(&args, state, session),
         Cmd::Replconf { args } => Ok(replication::replconf_execute(&args, state)),
         Cmd::Psync { args } => replication::psync_execute(&args, state),
     })
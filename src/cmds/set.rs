@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use super::keyspace::{parse_scan_args, scan_response};
+use super::string::parse_integer;
+use crate::error::{Error, Result};
+use crate::protocol::{Cmd, Data};
+use crate::storage::{Db, SetOperation};
+
+/// key member [member ...]
+fn parse_key_members<'a>(cmd: &str, args: &'a [Data]) -> Result<(&'a str, Vec<&'a [u8]>)> {
+    let [key, members @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    if members.is_empty() {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    }
+    let members = members
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok((key.try_into()?, members))
+}
+
+fn parse_keys<'a>(cmd: &str, keys: &'a [Data]) -> Result<Vec<&'a str>> {
+    if keys.is_empty() {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    }
+    keys.iter().map(<&str>::try_from).collect()
+}
+
+fn set_reply(members: Vec<Vec<u8>>) -> Data {
+    Data::Set(members.into_iter().map(Data::BulkString).collect())
+}
+
+/// Implement sadd as described here <https://redis.io/docs/latest/commands/sadd/>
+pub fn sadd_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, members) = parse_key_members("sadd", args)?;
+    Ok(Data::from(state.sadd(key, &members)?))
+}
+
+/// Implement srem as described here <https://redis.io/docs/latest/commands/srem/>
+pub fn srem_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, members) = parse_key_members("srem", args)?;
+    Ok(Data::from(state.srem(key, &members)?))
+}
+
+/// Implement smembers as described here <https://redis.io/docs/latest/commands/smembers/>
+pub fn smembers_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("smembers".to_string()));
+    };
+    Ok(set_reply(state.smembers(key.try_into()?)?))
+}
+
+/// Implement sismember as described here <https://redis.io/docs/latest/commands/sismember/>
+pub fn sismember_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, member] = args else {
+        return Err(Error::WrongNumberOfArgs("sismember".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let is_member = state.smismember(key, &[member.try_into()?])?;
+    Ok(Data::Integer(i64::from(is_member.first() == Some(&true))))
+}
+
+/// Implement smismember as described here <https://redis.io/docs/latest/commands/smismember/>
+pub fn smismember_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, members) = parse_key_members("smismember", args)?;
+    let is_member = state.smismember(key, &members)?;
+    Ok(Data::Array(
+        is_member
+            .into_iter()
+            .map(|is_member| Data::Integer(i64::from(is_member)))
+            .collect(),
+    ))
+}
+
+/// Implement scard as described here <https://redis.io/docs/latest/commands/scard/>
+pub fn scard_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("scard".to_string()));
+    };
+    Ok(Data::from(state.scard(key.try_into()?)?))
+}
+
+/// Implement sinter, sunion and sdiff as described here <https://redis.io/docs/latest/commands/sinter/>
+pub fn operation_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    op: SetOperation,
+) -> Result<Data> {
+    let keys = parse_keys(cmd, args)?;
+    Ok(set_reply(state.set_operation(&keys, op)?))
+}
+
+/// Implement sinterstore, sunionstore and sdiffstore as described here <https://redis.io/docs/latest/commands/sinterstore/>
+/// The destination is replaced by the result, so the command is replicated as is.
+pub fn operation_store_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    op: SetOperation,
+) -> Result<Data> {
+    let [destination, keys @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let destination: &str = destination.try_into()?;
+    let keys = parse_keys(cmd, keys)?;
+    Ok(Data::from(state.set_operation_store(
+        destination,
+        &keys,
+        op,
+    )?))
+}
+
+/// Implement sintercard as described here <https://redis.io/docs/latest/commands/sintercard/>
+/// SINTERCARD numkeys key [key ...] [LIMIT limit]
+pub fn sintercard_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [numkeys, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("sintercard".to_string()));
+    };
+    let numkeys = parse_integer(numkeys.try_into()?)?;
+    let numkeys = usize::try_from(numkeys)
+        .ok()
+        .filter(|numkeys| *numkeys > 0)
+        .ok_or(Error::NumKeysNotPositive)?;
+    if numkeys > args.len() {
+        return Err(Error::TooManyNumKeys);
+    }
+    let (keys, options) = args.split_at(numkeys);
+    let keys = parse_keys("sintercard", keys)?;
+
+    let limit = match options {
+        [] => 0,
+        [option, limit] => {
+            let option: &str = option.try_into()?;
+            if !option.eq_ignore_ascii_case("LIMIT") {
+                return Err(Error::Syntax);
+            }
+            let limit = parse_integer(limit.try_into()?)?;
+            usize::try_from(limit).map_err(|_| Error::NegativeLimit)?
+        }
+        _ => return Err(Error::Syntax),
+    };
+    Ok(Data::from(state.sintercard(&keys, limit)?))
+}
+
+/// Implement srandmember as described here <https://redis.io/docs/latest/commands/srandmember/>
+/// Without count a single member is returned, otherwise an array.
+pub fn srandmember_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, count) = match args {
+        [key] => (key, None),
+        [key, count] => (key, Some(parse_integer(count.try_into()?)?)),
+        _ => return Err(Error::WrongNumberOfArgs("srandmember".to_string())),
+    };
+    let key: &str = key.try_into()?;
+
+    let members = state.srandmember(key, count.unwrap_or(1))?;
+    Ok(match (members, count) {
+        (None, None) => Data::NullBuilkString,
+        (Some(members), None) => members
+            .into_iter()
+            .next()
+            .map_or(Data::NullBuilkString, Data::BulkString),
+        (members, Some(_)) => Data::Array(
+            members
+                .unwrap_or_default()
+                .into_iter()
+                .map(Data::BulkString)
+                .collect(),
+        ),
+    })
+}
+
+/// Implement spop as described here <https://redis.io/docs/latest/commands/spop/>
+/// The members are picked at random, so the pop is propagated as the SREM of the popped members.
+pub fn spop_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, count) = match args {
+        [key] => (key, None),
+        [key, count] => {
+            let count = parse_integer(count.try_into()?)?;
+            let count = usize::try_from(count).map_err(|_| Error::NotPositive)?;
+            (key, Some(count))
+        }
+        _ => return Err(Error::WrongNumberOfArgs("spop".to_string())),
+    };
+    let key: &str = key.try_into()?;
+
+    let popped = state.spop(key, count.unwrap_or(1))?.unwrap_or_default();
+    if !popped.is_empty() {
+        let mut args = vec![Data::bulk_string(key)];
+        args.extend(popped.iter().cloned().map(Data::BulkString));
+        super::propagate(&Cmd::Srem { args }, state);
+    }
+
+    Ok(match count {
+        None => popped
+            .into_iter()
+            .next()
+            .map_or(Data::NullBuilkString, Data::BulkString),
+        Some(_) => set_reply(popped),
+    })
+}
+
+/// Implement sscan as described here <https://redis.io/docs/latest/commands/sscan/>
+pub fn sscan_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("sscan".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let scan_args = parse_scan_args("sscan", args)?;
+    let (cursor, members) =
+        state.sscan(key, scan_args.cursor, scan_args.count, scan_args.pattern)?;
+    Ok(scan_response(
+        cursor,
+        members.into_iter().map(Data::BulkString).collect(),
+    ))
+}
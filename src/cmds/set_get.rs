@@ -1,66 +1,292 @@
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
 
+use super::expire::{expiration_ms, from_unix_time_ms, TimeUnit};
 use crate::error::{Error, Result};
-use crate::protocol::Data;
-use crate::storage::Db;
+use crate::protocol::{Cmd, Data};
+use crate::storage::{Db, SetCondition, SetExpiration};
 
-/// Implmement set command as descibed here <https://redis.io/docs/latest/commands/set/>
+/// Options of the SET command, they can be given in any order
+/// [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+#[derive(Debug, Default)]
+struct SetArgs {
+    condition: Option<SetCondition>,
+    get: bool,
+    expiration: SetExpiration,
+    // the expiration in ms since the epoch, as propagated to the slaves
+    expiration_ms: Option<i64>,
+}
+
+/// EX, PX, EXAT and PXAT options, shared by SET and GETEX. The time must be positive.
+fn parse_expiration(cmd: &str, option: &str, time: Option<&Data>) -> Result<i64> {
+    let (unit, absolute) = match option {
+        "EX" => (TimeUnit::Seconds, false),
+        "PX" => (TimeUnit::Milliseconds, false),
+        "EXAT" => (TimeUnit::Seconds, true),
+        "PXAT" => (TimeUnit::Milliseconds, true),
+        _ => return Err(Error::Syntax),
+    };
+    let time: &str = time.ok_or(Error::Syntax)?.try_into()?;
+    let time: i64 = time.parse()?;
+    if time <= 0 {
+        return Err(Error::InvalidExpireTime(cmd.to_string()));
+    }
+    expiration_ms(cmd, time, unit, absolute)
+}
+
+fn parse_set_args(options: &[Data]) -> Result<SetArgs> {
+    let mut set_args = SetArgs::default();
+    // the same option can be repeated, but not combined with a conflicting one
+    let mut expiration_option = None;
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        let option = option.to_ascii_uppercase();
+        match option.as_str() {
+            "NX" if set_args.condition != Some(SetCondition::Xx) => {
+                set_args.condition = Some(SetCondition::Nx);
+            }
+            "XX" if set_args.condition != Some(SetCondition::Nx) => {
+                set_args.condition = Some(SetCondition::Xx);
+            }
+            "GET" => set_args.get = true,
+            "KEEPTTL" | "EX" | "PX" | "EXAT" | "PXAT"
+                if expiration_option
+                    .as_ref()
+                    .is_none_or(|previous| *previous == option) =>
+            {
+                if option == "KEEPTTL" {
+                    set_args.expiration = SetExpiration::Keep;
+                } else {
+                    let at_ms = parse_expiration("set", &option, options.next())?;
+                    set_args.expiration = SetExpiration::At(from_unix_time_ms(at_ms));
+                    set_args.expiration_ms = Some(at_ms);
+                }
+                expiration_option = Some(option);
+            }
+            _ => return Err(Error::Syntax),
+        }
+    }
+    Ok(set_args)
+}
+
+/// Set the value and propagate it to the slaves, with an absolute expiration.
+/// Returns the previous value with the GET option, or the reply when the value isn't set.
+fn set_generic(
+    key: &str,
+    value: &[u8],
+    set_args: &SetArgs,
+    state: &Arc<Db>,
+) -> Result<(bool, Data)> {
+    let (set, previous) = state.set_with(
+        key,
+        value,
+        set_args.condition,
+        set_args.expiration,
+        set_args.get,
+    )?;
+
+    if set {
+        let mut args = vec![Data::bulk_string(key), Data::bulk_string(value)];
+        if let Some(at_ms) = set_args.expiration_ms {
+            args.extend([
+                Data::bulk_string("PXAT"),
+                Data::bulk_string(at_ms.to_string()),
+            ]);
+        } else if set_args.expiration == SetExpiration::Keep {
+            args.push(Data::bulk_string("KEEPTTL"));
+        }
+        super::propagate(&Cmd::Set { args }, state);
+    }
+
+    let previous = previous.map_or(Data::NullBuilkString, Data::BulkString);
+    Ok((set, previous))
+}
+
+/// Implement the set command as described here <https://redis.io/docs/latest/commands/set/>
 pub fn set_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
-    let [key, value, ..] = args else {
-        return Err(Error::Unsupported(format!("Unexpected set args {args:?}")));
+    let [key, value, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("set".to_string()));
     };
 
     let key: &str = key.try_into()?;
-    let value: &str = value.try_into()?;
+    let value: &[u8] = value.try_into()?;
+    let set_args = parse_set_args(options)?;
+
+    let (set, previous) = set_generic(key, value, &set_args, state)?;
+    Ok(match (set_args.get, set) {
+        (true, _) => previous,
+        (false, true) => Data::ok_response(),
+        (false, false) => Data::NullBuilkString,
+    })
+}
+
+/// Implement setnx as described here <https://redis.io/docs/latest/commands/setnx/>
+pub fn setnx_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, value] = args else {
+        return Err(Error::WrongNumberOfArgs("setnx".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let value: &[u8] = value.try_into()?;
 
-    let set_arts = args.get(2).zip(args.get(3));
-    let expiration = set_arts.map_or(Ok(None), parse_set_args)?;
+    let set_args = SetArgs {
+        condition: Some(SetCondition::Nx),
+        ..SetArgs::default()
+    };
+    let (set, _) = set_generic(key, value, &set_args, state)?;
+    Ok(Data::Integer(i64::from(set)))
+}
 
-    state.set(key, value, expiration);
+/// Implement setex and psetex as described here <https://redis.io/docs/latest/commands/setex/>
+pub fn setex_execute(cmd: &str, args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, time, value] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let value: &[u8] = value.try_into()?;
+
+    let option = if cmd == "setex" { "EX" } else { "PX" };
+    let at_ms = parse_expiration(cmd, option, Some(time))?;
+    let set_args = SetArgs {
+        expiration: SetExpiration::At(from_unix_time_ms(at_ms)),
+        expiration_ms: Some(at_ms),
+        ..SetArgs::default()
+    };
+    set_generic(key, value, &set_args, state)?;
     Ok(Data::ok_response())
 }
 
+/// Implement getex as described here <https://redis.io/docs/latest/commands/getex/>
+/// GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | PERSIST]
+pub fn getex_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("getex".to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let (expiration, propagated) = match options {
+        [] => (SetExpiration::Keep, None),
+        [option, time @ ..] => {
+            let option: &str = option.try_into()?;
+            let option = option.to_ascii_uppercase();
+            match (option.as_str(), time) {
+                ("PERSIST", []) => (
+                    SetExpiration::Clear,
+                    Some(Cmd::Persist {
+                        args: vec![Data::bulk_string(key)],
+                    }),
+                ),
+                (_, [_]) => {
+                    let at_ms = parse_expiration("getex", &option, time.first())?;
+                    let propagated = Cmd::Pexpireat {
+                        args: vec![Data::bulk_string(key), Data::bulk_string(at_ms.to_string())],
+                    };
+                    (
+                        SetExpiration::At(from_unix_time_ms(at_ms)),
+                        Some(propagated),
+                    )
+                }
+                _ => return Err(Error::Syntax),
+            }
+        }
+    };
+
+    let Some(value) = state.get_ex(key, expiration)? else {
+        return Ok(Data::NullBuilkString);
+    };
+    if let Some(cmd) = propagated {
+        super::propagate(&cmd, state);
+    }
+    Ok(Data::BulkString(value))
+}
+
+/// Implement getdel as described here <https://redis.io/docs/latest/commands/getdel/>
+pub fn getdel_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("getdel".to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let Some(value) = state.get_del(key)? else {
+        return Ok(Data::NullBuilkString);
+    };
+    super::propagate(
+        &Cmd::Del {
+            args: vec![Data::bulk_string(key)],
+        },
+        state,
+    );
+    Ok(Data::BulkString(value))
+}
+
 /// return the valu stored in the key
 /// if the key is missing, GET command should return "null build string"
 pub fn get_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
     let [key, ..] = args else {
-        return Err(Error::Unsupported(format!("Unexpected get args {args:?}")));
+        return Err(Error::WrongNumberOfArgs("get".to_string()));
     };
 
     let key: &str = key.try_into()?;
     Ok(state
-        .get(key)
+        .get(key)?
         .map_or(Data::NullBuilkString, Data::BulkString))
 }
 
 pub fn keys_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
     let [patern, ..] = args else {
-        return Err(Error::Unsupported(format!("Unexpected get args {args:?}")));
+        return Err(Error::WrongNumberOfArgs("keys".to_string()));
     };
     let patern: &str = patern.try_into()?;
     tracing::debug!("executing: keys {patern}");
     let keys = state.keys(patern);
     Ok(Data::Array(
-        keys.into_iter().map(Data::BulkString).collect(),
+        keys.into_iter().map(Data::bulk_string).collect(),
     ))
 }
 
-fn parse_set_args(args: (&Data, &Data)) -> Result<Option<SystemTime>> {
-    match args {
-        (Data::BulkString(ex_cmd), Data::BulkString(ms)) => {
-            if ex_cmd.to_ascii_uppercase() == "PX" {
-                let ms: u64 = ms.parse()?;
-                let expiration = SystemTime::now() + Duration::from_millis(ms);
-                Ok(Some(expiration))
-            } else {
-                Err(Error::Unsupported(format!(
-                    "unsupported set arts {ex_cmd:?}"
-                )))
-            }
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{get_execute, parse_set_args, SetCondition, SetExpiration};
+    use crate::protocol::Data;
+    use crate::storage::{Config, Db};
+
+    fn options(options: &[&str]) -> Vec<Data> {
+        options.iter().map(|o| Data::bulk_string(*o)).collect()
+    }
+
+    #[test]
+    fn parse_set_args_test() {
+        let set_args = parse_set_args(&options(&["get", "KEEPTTL", "xx"])).unwrap();
+        assert_eq!(set_args.condition, Some(SetCondition::Xx));
+        assert_eq!(set_args.expiration, SetExpiration::Keep);
+        assert!(set_args.get);
+
+        let set_args = parse_set_args(&options(&["PXAT", "1000", "NX"])).unwrap();
+        assert_eq!(set_args.expiration_ms, Some(1000));
+
+        for invalid in [
+            &["NX", "XX"][..],
+            &["EX", "10", "PX", "10"],
+            &["KEEPTTL", "EX", "10"],
+            &["EX"],
+            &["EX", "0"],
+            &["EX", "ten"],
+            &["FOO"],
+        ] {
+            assert!(parse_set_args(&options(invalid)).is_err(), "{invalid:?}");
         }
-        other => Err(Error::Unsupported(format!(
-            "unsupported set arts {other:?}"
-        ))),
+    }
+
+    #[test]
+    fn binary_key_test() {
+        let state = Arc::new(Db::new(Config::default()));
+        // the keys are strings, binary keys are not supported
+        let error = get_execute(&[Data::BulkString(vec![0xff, 0xfe])], &state).unwrap_err();
+        assert_eq!(
+            error.reply_message(),
+            "ERR keys and string arguments must be valid UTF-8, binary keys are not supported"
+        );
     }
 }
@@ -0,0 +1,381 @@
+use std::ops::Bound;
+use std::sync::Arc;
+
+use super::keyspace::{parse_scan_args, scan_response};
+use super::string::{parse_float, parse_integer};
+use crate::error::{Error, Result};
+use crate::protocol::{format_double, Data, RespVersion};
+use crate::storage::{
+    Aggregate, Db, LexBound, SetCondition, SetOperation, ZRange, ZRangeBy, ZaddOptions,
+};
+
+/// How the members of a range are selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeKind {
+    Rank,
+    Score,
+    Lex,
+}
+
+/// `score` or `(score` for an exclusive bound, as in ZRANGE BYSCORE
+fn parse_score_bound(bound: &Data) -> Result<Bound<f64>> {
+    let bound: &[u8] = bound.try_into()?;
+    let parse = |score| parse_float(score).map_err(|_| Error::MinMaxNotFloat);
+    match bound.strip_prefix(b"(") {
+        Some(score) => Ok(Bound::Excluded(parse(score)?)),
+        None => Ok(Bound::Included(parse(bound)?)),
+    }
+}
+
+/// `[member`, `(member`, `-` or `+`, as in ZRANGE BYLEX
+fn parse_lex_bound(bound: &Data) -> Result<LexBound> {
+    let bound: &[u8] = bound.try_into()?;
+    match bound {
+        b"-" => Ok(LexBound::Min),
+        b"+" => Ok(LexBound::Max),
+        [b'[', member @ ..] => Ok(LexBound::Included(member.to_vec())),
+        [b'(', member @ ..] => Ok(LexBound::Excluded(member.to_vec())),
+        _ => Err(Error::InvalidLexRange),
+    }
+}
+
+fn score_or_null(score: Option<f64>) -> Data {
+    score.map_or(Data::NullBuilkString, Data::Double)
+}
+
+/// Members of a range, with their scores as a flat array on RESP2 and as pairs on RESP3
+fn members_reply(entries: Vec<(Vec<u8>, f64)>, with_scores: bool, version: RespVersion) -> Data {
+    let entries = entries.into_iter();
+    Data::Array(match (with_scores, version) {
+        (false, _) => entries
+            .map(|(member, _)| Data::BulkString(member))
+            .collect(),
+        (true, RespVersion::Resp2) => entries
+            .flat_map(|(member, score)| [Data::BulkString(member), Data::Double(score)])
+            .collect(),
+        (true, RespVersion::Resp3) => entries
+            .map(|(member, score)| Data::Array(vec![Data::BulkString(member), Data::Double(score)]))
+            .collect(),
+    })
+}
+
+/// Implement zadd as described here <https://redis.io/docs/latest/commands/zadd/>
+/// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+pub fn zadd_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("zadd".to_string()));
+    };
+    if args.len() < 2 {
+        return Err(Error::WrongNumberOfArgs("zadd".to_string()));
+    }
+    let key: &str = key.try_into()?;
+
+    let mut options = ZaddOptions::default();
+    let (mut nx, mut xx, mut ch) = (false, false, false);
+    let mut position = 0;
+    for arg in args {
+        let Ok(option) = <&str>::try_from(arg) else {
+            break;
+        };
+        match option.to_ascii_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GT" => options.greater = true,
+            "LT" => options.less = true,
+            "CH" => ch = true,
+            "INCR" => options.incr = true,
+            _ => break,
+        }
+        position += 1;
+    }
+    if nx && xx {
+        return Err(Error::IncompatibleOptions("XX and NX".to_string()));
+    }
+    if (options.greater && options.less) || (nx && (options.greater || options.less)) {
+        return Err(Error::IncompatibleOptions("GT, LT, and/or NX".to_string()));
+    }
+    options.condition = match (nx, xx) {
+        (true, _) => Some(SetCondition::Nx),
+        (_, true) => Some(SetCondition::Xx),
+        _ => None,
+    };
+
+    let pairs = &args[position..];
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(Error::Syntax);
+    }
+    if options.incr && pairs.len() > 2 {
+        return Err(Error::IncrSinglePair);
+    }
+    let pairs = pairs
+        .chunks_exact(2)
+        .map(|pair| {
+            Ok((
+                parse_float((&pair[0]).try_into()?)?,
+                <&[u8]>::try_from(&pair[1])?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let outcome = state.zadd(key, &pairs, options)?;
+    Ok(if options.incr {
+        score_or_null(outcome.score)
+    } else if ch {
+        Data::from(outcome.changed)
+    } else {
+        Data::from(outcome.added)
+    })
+}
+
+/// Implement zincrby as described here <https://redis.io/docs/latest/commands/zincrby/>
+pub fn zincrby_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, increment, member] = args else {
+        return Err(Error::WrongNumberOfArgs("zincrby".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let increment = parse_float(increment.try_into()?)?;
+    let options = ZaddOptions {
+        incr: true,
+        ..ZaddOptions::default()
+    };
+    let outcome = state.zadd(key, &[(increment, member.try_into()?)], options)?;
+    Ok(score_or_null(outcome.score))
+}
+
+/// Implement zrem as described here <https://redis.io/docs/latest/commands/zrem/>
+pub fn zrem_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, members @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("zrem".to_string()));
+    };
+    if members.is_empty() {
+        return Err(Error::WrongNumberOfArgs("zrem".to_string()));
+    }
+    let key: &str = key.try_into()?;
+    let members = members
+        .iter()
+        .map(<&[u8]>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::from(state.zrem(key, &members)?))
+}
+
+/// Implement zcard as described here <https://redis.io/docs/latest/commands/zcard/>
+pub fn zcard_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("zcard".to_string()));
+    };
+    Ok(Data::from(state.zcard(key.try_into()?)?))
+}
+
+/// Implement zscore as described here <https://redis.io/docs/latest/commands/zscore/>
+pub fn zscore_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, member] = args else {
+        return Err(Error::WrongNumberOfArgs("zscore".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    Ok(score_or_null(state.zscore(key, member.try_into()?)?))
+}
+
+/// Implement zpopmin and zpopmax as described here <https://redis.io/docs/latest/commands/zpopmin/>
+/// Without count the member and its score are returned as a flat array.
+pub fn zpop_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    version: RespVersion,
+    max: bool,
+) -> Result<Data> {
+    let (key, count) = match args {
+        [key] => (key, None),
+        [key, count] => {
+            let count = parse_integer(count.try_into()?)?;
+            (
+                key,
+                Some(usize::try_from(count).map_err(|_| Error::NotPositive)?),
+            )
+        }
+        _ => return Err(Error::WrongNumberOfArgs(cmd.to_string())),
+    };
+    let popped = state.zpop(key.try_into()?, count.unwrap_or(1), max)?;
+    if count.is_some() {
+        return Ok(members_reply(popped, true, version));
+    }
+    Ok(Data::Array(
+        popped
+            .into_iter()
+            .flat_map(|(member, score)| [Data::BulkString(member), Data::Double(score)])
+            .collect(),
+    ))
+}
+
+/// Implement zrank and zrevrank as described here <https://redis.io/docs/latest/commands/zrank/>
+pub fn zrank_execute(cmd: &str, args: &[Data], state: &Arc<Db>, rev: bool) -> Result<Data> {
+    let (key, member, with_score) = match args {
+        [key, member] => (key, member, false),
+        [key, member, option] => {
+            let option: &str = option.try_into()?;
+            if !option.eq_ignore_ascii_case("WITHSCORE") {
+                return Err(Error::Syntax);
+            }
+            (key, member, true)
+        }
+        _ => return Err(Error::WrongNumberOfArgs(cmd.to_string())),
+    };
+    let key: &str = key.try_into()?;
+
+    let rank = state.zrank(key, member.try_into()?, rev)?;
+    Ok(match (rank, with_score) {
+        (None, false) => Data::NullBuilkString,
+        (None, true) => Data::NullArray,
+        (Some((rank, _)), false) => Data::from(rank),
+        (Some((rank, score)), true) => Data::Array(vec![Data::from(rank), Data::Double(score)]),
+    })
+}
+
+/// Implement zrange as described here <https://redis.io/docs/latest/commands/zrange/>
+/// and the zrevrange, zrangebyscore, zrevrangebyscore, zrangebylex and zrevrangebylex
+/// variants, which are ZRANGE with the BYSCORE, BYLEX and REV options implied.
+/// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+pub fn zrange_execute(
+    cmd: &str,
+    args: &[Data],
+    state: &Arc<Db>,
+    version: RespVersion,
+    kind: RangeKind,
+    rev: bool,
+) -> Result<Data> {
+    let [key, start, stop, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let (mut kind, mut rev) = (kind, rev);
+    let mut with_scores = false;
+    let mut limit = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        match option.to_ascii_uppercase().as_str() {
+            "WITHSCORES" => with_scores = true,
+            "LIMIT" => {
+                let offset = options.next().ok_or(Error::Syntax)?;
+                let count = options.next().ok_or(Error::Syntax)?;
+                limit = Some((
+                    parse_integer(offset.try_into()?)?,
+                    parse_integer(count.try_into()?)?,
+                ));
+            }
+            "BYSCORE" if cmd == "zrange" => kind = RangeKind::Score,
+            "BYLEX" if cmd == "zrange" => kind = RangeKind::Lex,
+            "REV" if cmd == "zrange" => rev = true,
+            _ => return Err(Error::Syntax),
+        }
+    }
+    if limit.is_some() && kind == RangeKind::Rank {
+        return Err(Error::RankLimit);
+    }
+    if with_scores && kind == RangeKind::Lex {
+        return Err(Error::LexWithScores);
+    }
+
+    // a negative offset selects nothing and a negative count everything
+    let limit = match limit {
+        Some((offset, _)) if offset < 0 => return Ok(Data::Array(Vec::new())),
+        Some((offset, count)) => Some((
+            usize::try_from(offset).unwrap_or(usize::MAX),
+            usize::try_from(count).unwrap_or(usize::MAX),
+        )),
+        None => None,
+    };
+    // the scores and members ranges are given from the highest to the lowest with REV
+    let (min, max) = if rev && kind != RangeKind::Rank {
+        (stop, start)
+    } else {
+        (start, stop)
+    };
+    let by = match kind {
+        RangeKind::Rank => ZRangeBy::Rank(
+            parse_integer(start.try_into()?)?,
+            parse_integer(stop.try_into()?)?,
+        ),
+        RangeKind::Score => ZRangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?),
+        RangeKind::Lex => ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+    };
+
+    let entries = state.zrange(key, &ZRange { by, rev, limit })?;
+    Ok(members_reply(entries, with_scores, version))
+}
+
+/// Implement zunionstore and zinterstore as described here <https://redis.io/docs/latest/commands/zunionstore/>
+/// ZUNIONSTORE destination numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE SUM | MIN | MAX]
+/// The destination is replaced by the result, so the command is replicated as is.
+pub fn zstore_execute(cmd: &str, args: &[Data], state: &Arc<Db>, op: SetOperation) -> Result<Data> {
+    let [destination, numkeys, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let destination: &str = destination.try_into()?;
+    let numkeys = usize::try_from(parse_integer(numkeys.try_into()?)?)
+        .ok()
+        .filter(|numkeys| *numkeys > 0)
+        .ok_or_else(|| Error::NoInputKeys(cmd.to_string()))?;
+    if numkeys > args.len() {
+        return Err(Error::Syntax);
+    }
+    let (keys, options) = args.split_at(numkeys);
+    let keys = keys
+        .iter()
+        .map(<&str>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut weights = Vec::new();
+    let mut aggregate = Aggregate::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        match option.to_ascii_uppercase().as_str() {
+            "WEIGHTS" => {
+                weights = options
+                    .by_ref()
+                    .take(numkeys)
+                    .map(|weight| {
+                        parse_float(weight.try_into()?).map_err(|_| Error::WeightNotFloat)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if weights.len() != numkeys {
+                    return Err(Error::Syntax);
+                }
+            }
+            "AGGREGATE" => {
+                let value: &str = options.next().ok_or(Error::Syntax)?.try_into()?;
+                aggregate = match value.to_ascii_uppercase().as_str() {
+                    "SUM" => Aggregate::Sum,
+                    "MIN" => Aggregate::Min,
+                    "MAX" => Aggregate::Max,
+                    _ => return Err(Error::Syntax),
+                };
+            }
+            _ => return Err(Error::Syntax),
+        }
+    }
+
+    let len = state.zstore(destination, &keys, &weights, aggregate, op)?;
+    Ok(Data::from(len))
+}
+
+/// Implement zscan as described here <https://redis.io/docs/latest/commands/zscan/>
+/// As in Redis the scores are replied as bulk strings whatever the protocol version.
+pub fn zscan_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("zscan".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let scan_args = parse_scan_args("zscan", args)?;
+    let (cursor, entries) =
+        state.zscan(key, scan_args.cursor, scan_args.count, scan_args.pattern)?;
+
+    let mut elements = Vec::with_capacity(entries.len() * 2);
+    for (member, score) in entries {
+        elements.push(Data::BulkString(member));
+        elements.push(Data::bulk_string(format_double(score)));
+    }
+    Ok(scan_response(cursor, elements))
+}
@@ -0,0 +1,762 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::expire::{from_unix_time_ms, unix_time_ms};
+use super::string::parse_integer;
+use crate::error::{Error, Result};
+use crate::protocol::{Cmd, Data, RespVersion};
+use crate::storage::{
+    ClaimOptions, Claimed, Db, GroupReadFrom, PendingFilter, StreamFields, StreamId, StreamTrim,
+    TrimThreshold, XaddId,
+};
+
+fn is_option(arg: &Data, option: &str) -> bool {
+    <&str>::try_from(arg).is_ok_and(|arg| arg.eq_ignore_ascii_case(option))
+}
+
+/// `ms-seq` or `ms`, in which case the sequence number is `missing_seq`
+fn parse_id(id: &Data, missing_seq: u64) -> Result<StreamId> {
+    let id: &str = id.try_into().map_err(|_| Error::InvalidStreamId)?;
+    let number = |n: &str| n.parse::<u64>().map_err(|_| Error::InvalidStreamId);
+    match id.split_once('-') {
+        Some((ms, seq)) => Ok(StreamId {
+            ms: number(ms)?,
+            seq: number(seq)?,
+        }),
+        None => Ok(StreamId {
+            ms: number(id)?,
+            seq: missing_seq,
+        }),
+    }
+}
+
+/// Start of a range, `-` for the smallest ID and `(id` to exclude the ID
+fn parse_range_start(start: &Data) -> Result<StreamId> {
+    match <&[u8]>::try_from(start)? {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_id(&Data::bulk_string(id), 0)?
+            .next()
+            .ok_or(Error::InvalidIntervalStart),
+        _ => parse_id(start, 0),
+    }
+}
+
+/// End of a range, `+` for the greatest ID and `(id` to exclude the ID
+fn parse_range_end(end: &Data) -> Result<StreamId> {
+    match <&[u8]>::try_from(end)? {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_id(&Data::bulk_string(id), u64::MAX)?
+            .prev()
+            .ok_or(Error::InvalidIntervalEnd),
+        _ => parse_id(end, u64::MAX),
+    }
+}
+
+/// COUNT of the read commands, a negative or zero count is no limit
+fn parse_count(count: Option<&Data>) -> Result<usize> {
+    let count = parse_integer(count.ok_or(Error::Syntax)?.try_into()?)?;
+    Ok(usize::try_from(count)
+        .ok()
+        .filter(|count| *count > 0)
+        .unwrap_or(usize::MAX))
+}
+
+/// `<MAXLEN | MINID> [= | ~] threshold [LIMIT count]`, returns the number of arguments used.
+/// As the exact trimming also satisfies the approximate one, `~` always trims exactly.
+fn parse_trim(args: &[Data]) -> Result<(StreamTrim, usize)> {
+    let [strategy, args @ ..] = args else {
+        return Err(Error::Syntax);
+    };
+    let mut position = 1;
+    let operator = args.first().map(<&[u8]>::try_from).transpose()?;
+    let approximate = operator == Some(b"~".as_slice());
+    let args = if matches!(operator, Some(b"~" | b"=")) {
+        position += 1;
+        &args[1..]
+    } else {
+        args
+    };
+    let [threshold, args @ ..] = args else {
+        return Err(Error::Syntax);
+    };
+    position += 1;
+
+    let threshold = if is_option(strategy, "MAXLEN") {
+        let len = parse_integer(threshold.try_into()?)?;
+        let len = usize::try_from(len).map_err(|_| Error::NegativeMaxLen)?;
+        TrimThreshold::MaxLen(len)
+    } else {
+        TrimThreshold::MinId(parse_id(threshold, 0)?)
+    };
+
+    let mut limit = None;
+    if let [option, count, ..] = args {
+        if is_option(option, "LIMIT") {
+            let count = parse_integer(count.try_into()?)?;
+            let count = usize::try_from(count).map_err(|_| Error::NegativeTrimLimit)?;
+            if !approximate {
+                return Err(Error::TrimLimitWithoutTilde);
+            }
+            limit = (count > 0).then_some(count);
+            position += 2;
+        }
+    }
+    Ok((StreamTrim { threshold, limit }, position))
+}
+
+fn id_reply(id: StreamId) -> Data {
+    Data::bulk_string(id.to_string())
+}
+
+/// An entry as its ID and its flattened fields, null fields for a deleted entry
+fn entry_reply(id: StreamId, fields: Option<StreamFields>) -> Data {
+    let fields = fields.map_or(Data::NullArray, |fields| {
+        Data::Array(
+            fields
+                .into_iter()
+                .flat_map(|(field, value)| [Data::BulkString(field), Data::BulkString(value)])
+                .collect(),
+        )
+    });
+    Data::Array(vec![id_reply(id), fields])
+}
+
+/// The entries read from each stream, a map on RESP3 and an array of pairs on RESP2
+fn streams_reply(streams: Vec<(String, Vec<Data>)>, version: RespVersion) -> Data {
+    if streams.is_empty() {
+        return Data::NullArray;
+    }
+    match version {
+        RespVersion::Resp2 => Data::Array(
+            streams
+                .into_iter()
+                .map(|(key, entries)| {
+                    Data::Array(vec![Data::bulk_string(key), Data::Array(entries)])
+                })
+                .collect(),
+        ),
+        RespVersion::Resp3 => Data::Map(
+            streams
+                .into_iter()
+                .map(|(key, entries)| (Data::bulk_string(key), Data::Array(entries)))
+                .collect(),
+        ),
+    }
+}
+
+/// `STREAMS key [key ...] id [id ...]`, the keys and their IDs
+fn parse_streams<'a>(cmd: &str, args: &'a [Data]) -> Result<Vec<(&'a str, &'a Data)>> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(Error::UnbalancedStreams(cmd.to_string()));
+    }
+    let (keys, ids) = args.split_at(args.len() / 2);
+    keys.iter()
+        .zip(ids)
+        .map(|(key, id)| Ok((<&str>::try_from(key)?, id)))
+        .collect()
+}
+
+/// Replicate the claim of an entry as Redis does, as a forced XCLAIM with the delivery time and
+/// count set by the master
+fn propagate_claim(
+    args: &[Data],
+    id: StreamId,
+    delivery_time: SystemTime,
+    delivery_count: u64,
+    state: &Arc<Db>,
+) {
+    let mut args = args.to_vec();
+    args.extend([
+        Data::bulk_string("0"),
+        id_reply(id),
+        Data::bulk_string("TIME"),
+        Data::bulk_string(unix_time_ms(delivery_time).to_string()),
+        Data::bulk_string("RETRYCOUNT"),
+        Data::bulk_string(delivery_count.to_string()),
+        Data::bulk_string("FORCE"),
+        Data::bulk_string("JUSTID"),
+    ]);
+    super::propagate(&Cmd::Xclaim { args }, state);
+}
+
+/// Replicate the changes of the pending entries list made by XCLAIM and XAUTOCLAIM
+fn propagate_claimed(
+    key: &Data,
+    group: &Data,
+    consumer: &Data,
+    claimed: &Claimed,
+    delivery_time: SystemTime,
+    state: &Arc<Db>,
+) {
+    let (key, group, consumer) = (key.clone(), group.clone(), consumer.clone());
+    if claimed.new_consumer {
+        let args = vec![
+            Data::bulk_string("CREATECONSUMER"),
+            key.clone(),
+            group.clone(),
+            consumer.clone(),
+        ];
+        super::propagate(&Cmd::Xgroup { args }, state);
+    }
+    for entry in &claimed.entries {
+        let args = [key.clone(), group.clone(), consumer.clone()];
+        propagate_claim(&args, entry.id, delivery_time, entry.delivery_count, state);
+    }
+    if !claimed.deleted.is_empty() {
+        let mut args = vec![key.clone(), group.clone()];
+        args.extend(claimed.deleted.iter().copied().map(id_reply));
+        super::propagate(&Cmd::Xack { args }, state);
+    }
+    if let Some(last_delivered) = claimed.last_delivered {
+        let args = vec![
+            Data::bulk_string("SETID"),
+            key,
+            group,
+            id_reply(last_delivered),
+        ];
+        super::propagate(&Cmd::Xgroup { args }, state);
+    }
+}
+
+fn claimed_reply(claimed: Claimed, just_id: bool) -> Data {
+    Data::Array(
+        claimed
+            .entries
+            .into_iter()
+            .map(|entry| {
+                if just_id {
+                    id_reply(entry.id)
+                } else {
+                    entry_reply(entry.id, Some(entry.fields))
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Implement xadd as described here <https://redis.io/docs/latest/commands/xadd/>
+/// XADD key [NOMKSTREAM] [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]] <* | id> field value [field value ...]
+/// The generated ID depends on the time, so the command is propagated with the ID of the entry.
+pub fn xadd_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xadd".to_string()));
+    };
+    let key: &str = key.try_into()?;
+
+    let mut make_stream = true;
+    let mut trim = None;
+    let mut position = 0;
+    while let Some(option) = options.get(position) {
+        if is_option(option, "NOMKSTREAM") {
+            make_stream = false;
+            position += 1;
+        } else if is_option(option, "MAXLEN") || is_option(option, "MINID") {
+            let (parsed, len) = parse_trim(&options[position..])?;
+            trim = Some(parsed);
+            position += len;
+        } else {
+            break;
+        }
+    }
+    let [id, pairs @ ..] = &options[position..] else {
+        return Err(Error::WrongNumberOfArgs("xadd".to_string()));
+    };
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(Error::WrongNumberOfArgs("xadd".to_string()));
+    }
+    let xadd_id = match <&[u8]>::try_from(id)? {
+        b"*" => XaddId::Auto,
+        [ms @ .., b'-', b'*'] => XaddId::AutoSeq(parse_id(&Data::bulk_string(ms), 0)?.ms),
+        _ => XaddId::Explicit(parse_id(id, 0)?),
+    };
+    let pairs = pairs
+        .chunks_exact(2)
+        .map(|pair| Ok((<&[u8]>::try_from(&pair[0])?, <&[u8]>::try_from(&pair[1])?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(id) = state.xadd(key, xadd_id, &pairs, make_stream, trim.as_ref())? else {
+        return Ok(Data::NullBuilkString);
+    };
+    let mut args = args.to_vec();
+    args[position + 1] = id_reply(id);
+    super::propagate(&Cmd::Xadd { args }, state);
+    Ok(id_reply(id))
+}
+
+/// Implement xlen as described here <https://redis.io/docs/latest/commands/xlen/>
+pub fn xlen_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("xlen".to_string()));
+    };
+    Ok(Data::from(state.xlen(key.try_into()?)?))
+}
+
+/// Implement xrange as described here <https://redis.io/docs/latest/commands/xrange/>
+/// XRANGE key start end [COUNT count]
+pub fn xrange_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, start, end, count) = match args {
+        [key, start, end] => (key, start, end, usize::MAX),
+        [key, start, end, option, count] if is_option(option, "COUNT") => {
+            let count = parse_integer(count.try_into()?)?;
+            (key, start, end, usize::try_from(count).unwrap_or(0))
+        }
+        [_, _, _, ..] => return Err(Error::Syntax),
+        _ => return Err(Error::WrongNumberOfArgs("xrange".to_string())),
+    };
+    let key: &str = key.try_into()?;
+    let entries = state.xrange(key, parse_range_start(start)?, parse_range_end(end)?, count)?;
+    Ok(Data::Array(
+        entries
+            .into_iter()
+            .map(|(id, fields)| entry_reply(id, Some(fields)))
+            .collect(),
+    ))
+}
+
+/// Implement xtrim as described here <https://redis.io/docs/latest/commands/xtrim/>
+/// XTRIM key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]
+pub fn xtrim_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xtrim".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let Some(strategy) = options.first() else {
+        return Err(Error::WrongNumberOfArgs("xtrim".to_string()));
+    };
+    if !is_option(strategy, "MAXLEN") && !is_option(strategy, "MINID") {
+        return Err(Error::Syntax);
+    }
+    let (trim, len) = parse_trim(options)?;
+    if len != options.len() {
+        return Err(Error::Syntax);
+    }
+    Ok(Data::from(state.xtrim(key, &trim)?))
+}
+
+/// Arguments of XREAD, `$` is resolved to the last ID of the stream when parsed
+#[derive(Debug)]
+pub struct XreadArgs {
+    pub streams: Vec<(String, StreamId)>,
+    pub count: usize,
+    /// BLOCK timeout in milliseconds, 0 blocks forever
+    pub block: Option<u64>,
+}
+
+/// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+pub fn parse_xread(args: &[Data], state: &Arc<Db>) -> Result<XreadArgs> {
+    let mut count = usize::MAX;
+    let mut block = None;
+    let mut position = 0;
+    loop {
+        let Some(option) = args.get(position) else {
+            return Err(Error::Syntax);
+        };
+        if is_option(option, "COUNT") {
+            count = parse_count(args.get(position + 1))?;
+            position += 2;
+        } else if is_option(option, "BLOCK") {
+            let timeout = args.get(position + 1).ok_or(Error::Syntax)?;
+            let timeout = parse_integer(timeout.try_into()?)?;
+            let timeout = u64::try_from(timeout).map_err(|_| Error::NegativeTimeout)?;
+            block = Some(timeout);
+            position += 2;
+        } else if is_option(option, "STREAMS") {
+            position += 1;
+            break;
+        } else {
+            return Err(Error::Syntax);
+        }
+    }
+    let streams = parse_streams("xread", &args[position..])?
+        .into_iter()
+        .map(|(key, id)| match <&[u8]>::try_from(id)? {
+            b"$" => Ok((key.to_string(), state.stream_last_id(key)?)),
+            _ => Ok((key.to_string(), parse_id(id, 0)?)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(XreadArgs {
+        streams,
+        count,
+        block,
+    })
+}
+
+/// The entries after the ID of each stream, None if there are none
+pub fn xread(xread: &XreadArgs, state: &Arc<Db>, version: RespVersion) -> Result<Option<Data>> {
+    let streams: Vec<(&str, StreamId)> = xread
+        .streams
+        .iter()
+        .map(|(key, id)| (key.as_str(), *id))
+        .collect();
+    let read = state.xread(&streams, xread.count)?;
+    if read.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(streams_reply(
+        read.into_iter()
+            .map(|(key, entries)| {
+                let entries = entries
+                    .into_iter()
+                    .map(|(id, fields)| entry_reply(id, Some(fields)))
+                    .collect();
+                (key, entries)
+            })
+            .collect(),
+        version,
+    )))
+}
+
+/// Implement xread as described here <https://redis.io/docs/latest/commands/xread/>
+/// Here BLOCK doesn't block, the blocking reads of the clients are served by the blocking module.
+pub fn xread_execute(args: &[Data], state: &Arc<Db>, version: RespVersion) -> Result<Data> {
+    let xread_args = parse_xread(args, state)?;
+    Ok(xread(&xread_args, state, version)?.unwrap_or(Data::NullArray))
+}
+
+/// Implement xgroup as described here <https://redis.io/docs/latest/commands/xgroup/>
+/// with the CREATE, SETID, DESTROY, CREATECONSUMER and DELCONSUMER subcommands.
+/// `$` is resolved against the stream, which is the same on the slaves, so the command is
+/// replicated as is.
+pub fn xgroup_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [sub_cmd, key, group, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xgroup".to_string()));
+    };
+    let sub_cmd: &str = sub_cmd.try_into()?;
+    let key: &str = key.try_into()?;
+    let group: &[u8] = group.try_into()?;
+    let parse_group_id = |id: &Data| match <&[u8]>::try_from(id)? {
+        b"$" => Ok(None),
+        _ => parse_id(id, 0).map(Some),
+    };
+
+    match (sub_cmd.to_ascii_uppercase().as_str(), args) {
+        ("CREATE", [id, options @ ..]) => {
+            let make_stream = match options {
+                [] => false,
+                [option] if is_option(option, "MKSTREAM") => true,
+                _ => return Err(Error::Syntax),
+            };
+            state.xgroup_create(key, group, parse_group_id(id)?, make_stream)?;
+            Ok(Data::SimpleString("OK".to_string()))
+        }
+        ("SETID", [id]) => {
+            state.xgroup_setid(key, group, parse_group_id(id)?)?;
+            Ok(Data::SimpleString("OK".to_string()))
+        }
+        ("DESTROY", []) => {
+            let destroyed = state.xgroup_destroy(key, group)?;
+            Ok(Data::Integer(i64::from(destroyed)))
+        }
+        ("CREATECONSUMER", [consumer]) => {
+            let consumer: &[u8] = consumer.try_into()?;
+            let created = state.xgroup_createconsumer(key, group, consumer)?;
+            Ok(Data::Integer(i64::from(created)))
+        }
+        ("DELCONSUMER", [consumer]) => {
+            let consumer: &[u8] = consumer.try_into()?;
+            Ok(Data::from(state.xgroup_delconsumer(key, group, consumer)?))
+        }
+        ("CREATE" | "SETID" | "DESTROY" | "CREATECONSUMER" | "DELCONSUMER", _) => Err(
+            Error::WrongNumberOfArgs(format!("xgroup|{}", sub_cmd.to_lowercase())),
+        ),
+        _ => Err(Error::UnknownXgroupSubcommand(sub_cmd.to_string())),
+    }
+}
+
+/// Implement xreadgroup as described here <https://redis.io/docs/latest/commands/xreadgroup/>
+/// XREADGROUP GROUP group consumer [COUNT count] [NOACK] STREAMS key [key ...] id [id ...]
+/// The deliveries are replicated as XCLAIM of each entry and the XGROUP SETID of the group.
+pub fn xreadgroup_execute(args: &[Data], state: &Arc<Db>, version: RespVersion) -> Result<Data> {
+    let [option, group_arg, consumer_arg, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xreadgroup".to_string()));
+    };
+    if !is_option(option, "GROUP") {
+        return Err(Error::Syntax);
+    }
+    let group: &[u8] = group_arg.try_into()?;
+    let consumer: &[u8] = consumer_arg.try_into()?;
+
+    let mut count = usize::MAX;
+    let mut no_ack = false;
+    let mut position = 0;
+    loop {
+        let Some(option) = args.get(position) else {
+            return Err(Error::Syntax);
+        };
+        if is_option(option, "COUNT") {
+            count = parse_count(args.get(position + 1))?;
+            position += 2;
+        } else if is_option(option, "NOACK") {
+            no_ack = true;
+            position += 1;
+        } else if is_option(option, "STREAMS") {
+            position += 1;
+            break;
+        } else {
+            return Err(Error::Syntax);
+        }
+    }
+    let streams = parse_streams("xreadgroup", &args[position..])?
+        .into_iter()
+        .map(|(key, id)| match <&[u8]>::try_from(id)? {
+            b">" => Ok((key, GroupReadFrom::New)),
+            _ => Ok((key, GroupReadFrom::History(parse_id(id, 0)?))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let now = SystemTime::now();
+    let reads = state.xreadgroup(group, consumer, &streams, count, no_ack, now)?;
+    let mut reply = Vec::with_capacity(reads.len());
+    for (read, (_, from)) in reads.into_iter().zip(&streams) {
+        let key = Data::bulk_string(read.key.as_str());
+        let claim_args = [key.clone(), group_arg.clone(), consumer_arg.clone()];
+        if read.new_consumer {
+            let mut args = vec![Data::bulk_string("CREATECONSUMER")];
+            args.extend(claim_args.iter().cloned());
+            super::propagate(&Cmd::Xgroup { args }, state);
+        }
+        if read.delivered {
+            if !no_ack {
+                for (id, _) in &read.entries {
+                    propagate_claim(&claim_args, *id, now, 1, state);
+                }
+            }
+            if let Some((last_delivered, _)) = read.entries.last() {
+                let args = vec![
+                    Data::bulk_string("SETID"),
+                    key,
+                    group_arg.clone(),
+                    id_reply(*last_delivered),
+                ];
+                super::propagate(&Cmd::Xgroup { args }, state);
+            }
+        }
+        // the streams without new entries are left out, the history is always returned
+        if read.entries.is_empty() && *from == GroupReadFrom::New {
+            continue;
+        }
+        let entries = read
+            .entries
+            .into_iter()
+            .map(|(id, fields)| entry_reply(id, fields))
+            .collect();
+        reply.push((read.key, entries));
+    }
+    Ok(streams_reply(reply, version))
+}
+
+/// Implement xack as described here <https://redis.io/docs/latest/commands/xack/>
+pub fn xack_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, group, ids @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xack".to_string()));
+    };
+    if ids.is_empty() {
+        return Err(Error::WrongNumberOfArgs("xack".to_string()));
+    }
+    let key: &str = key.try_into()?;
+    let ids = ids
+        .iter()
+        .map(|id| parse_id(id, 0))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::from(state.xack(key, group.try_into()?, &ids)?))
+}
+
+/// Implement xpending as described here <https://redis.io/docs/latest/commands/xpending/>
+/// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+pub fn xpending_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, group, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xpending".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let group: &[u8] = group.try_into()?;
+
+    if args.is_empty() {
+        let summary = state.xpending_summary(key, group)?;
+        let (first, last) = summary.range.map_or(
+            (Data::NullBuilkString, Data::NullBuilkString),
+            |(first, last)| (id_reply(first), id_reply(last)),
+        );
+        let consumers = if summary.consumers.is_empty() {
+            Data::NullArray
+        } else {
+            Data::Array(
+                summary
+                    .consumers
+                    .into_iter()
+                    .map(|(consumer, count)| {
+                        Data::Array(vec![
+                            Data::BulkString(consumer),
+                            Data::bulk_string(count.to_string()),
+                        ])
+                    })
+                    .collect(),
+            )
+        };
+        return Ok(Data::Array(vec![
+            Data::from(summary.count),
+            first,
+            last,
+            consumers,
+        ]));
+    }
+
+    let (min_idle, args) = match args {
+        [option, min_idle, args @ ..] if is_option(option, "IDLE") => {
+            let min_idle = parse_integer(min_idle.try_into()?)?;
+            (u64::try_from(min_idle).unwrap_or(0), args)
+        }
+        _ => (0, args),
+    };
+    let (start, end, count, consumer) = match args {
+        [start, end, count] => (start, end, count, None),
+        [start, end, count, consumer] => (start, end, count, Some(consumer)),
+        _ => return Err(Error::Syntax),
+    };
+    let count = parse_integer(count.try_into()?)?;
+    let filter = PendingFilter {
+        start: parse_range_start(start)?,
+        end: parse_range_end(end)?,
+        count: usize::try_from(count).unwrap_or(0),
+        consumer: consumer
+            .map(|consumer| <&[u8]>::try_from(consumer).map(<[u8]>::to_vec))
+            .transpose()?,
+        min_idle: Duration::from_millis(min_idle),
+    };
+
+    let pending = state.xpending(key, group, &filter)?;
+    Ok(Data::Array(
+        pending
+            .into_iter()
+            .map(|pending| {
+                Data::Array(vec![
+                    id_reply(pending.id),
+                    Data::BulkString(pending.consumer),
+                    Data::Integer(i64::try_from(pending.idle.as_millis()).unwrap_or(i64::MAX)),
+                    Data::Integer(i64::try_from(pending.delivery_count).unwrap_or(i64::MAX)),
+                ])
+            })
+            .collect(),
+    ))
+}
+
+/// Minimum idle time of the claim commands in milliseconds, negative times are 0
+fn parse_min_idle(min_idle: &Data) -> Result<Duration> {
+    let min_idle = parse_integer(min_idle.try_into()?)?;
+    Ok(Duration::from_millis(u64::try_from(min_idle).unwrap_or(0)))
+}
+
+/// Implement xclaim as described here <https://redis.io/docs/latest/commands/xclaim/>
+/// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+/// [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+pub fn xclaim_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key_arg, group_arg, consumer_arg, min_idle, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xclaim".to_string()));
+    };
+    let key: &str = key_arg.try_into()?;
+    let group: &[u8] = group_arg.try_into()?;
+    let consumer: &[u8] = consumer_arg.try_into()?;
+
+    // the IDs come first, the options start at the first argument that isn't an ID
+    let ids: Vec<StreamId> = args.iter().map_while(|id| parse_id(id, 0).ok()).collect();
+    if ids.is_empty() {
+        return Err(Error::WrongNumberOfArgs("xclaim".to_string()));
+    }
+    let now = SystemTime::now();
+    let mut options = ClaimOptions {
+        min_idle: parse_min_idle(min_idle)?,
+        delivery_time: now,
+        retry_count: None,
+        force: false,
+        just_id: false,
+        last_id: None,
+    };
+    let mut args = args[ids.len()..].iter();
+    while let Some(option) = args.next() {
+        let option: &str = option.try_into()?;
+        let mut value =
+            || -> Result<i64> { parse_integer(args.next().ok_or(Error::Syntax)?.try_into()?) };
+        match option.to_ascii_uppercase().as_str() {
+            "IDLE" => {
+                let idle = Duration::from_millis(u64::try_from(value()?).unwrap_or(0));
+                options.delivery_time = now.checked_sub(idle).unwrap_or(SystemTime::UNIX_EPOCH);
+            }
+            "TIME" => options.delivery_time = from_unix_time_ms(value()?),
+            "RETRYCOUNT" => {
+                options.retry_count =
+                    Some(u64::try_from(value()?).map_err(|_| Error::NotPositive)?);
+            }
+            "FORCE" => options.force = true,
+            "JUSTID" => options.just_id = true,
+            "LASTID" => options.last_id = Some(parse_id(args.next().ok_or(Error::Syntax)?, 0)?),
+            _ => return Err(Error::Syntax),
+        }
+    }
+
+    let claimed = state.xclaim(key, group, consumer, &ids, &options)?;
+    propagate_claimed(
+        key_arg,
+        group_arg,
+        consumer_arg,
+        &claimed,
+        options.delivery_time,
+        state,
+    );
+    Ok(claimed_reply(claimed, options.just_id))
+}
+
+/// Implement xautoclaim as described here <https://redis.io/docs/latest/commands/xautoclaim/>
+/// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+pub fn xautoclaim_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key_arg, group_arg, consumer_arg, min_idle, start, args @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("xautoclaim".to_string()));
+    };
+    let key: &str = key_arg.try_into()?;
+    let group: &[u8] = group_arg.try_into()?;
+    let consumer: &[u8] = consumer_arg.try_into()?;
+    let start = parse_range_start(start)?;
+
+    let mut count = 100;
+    let mut just_id = false;
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let option: &str = option.try_into()?;
+        match option.to_ascii_uppercase().as_str() {
+            "COUNT" => {
+                let value = parse_integer(args.next().ok_or(Error::Syntax)?.try_into()?)?;
+                count = usize::try_from(value)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or(Error::CountNotPositive)?;
+            }
+            "JUSTID" => just_id = true,
+            _ => return Err(Error::Syntax),
+        }
+    }
+    let options = ClaimOptions {
+        min_idle: parse_min_idle(min_idle)?,
+        delivery_time: SystemTime::now(),
+        retry_count: None,
+        force: false,
+        just_id,
+        last_id: None,
+    };
+
+    let claimed = state.xautoclaim(key, group, consumer, start, count, &options)?;
+    propagate_claimed(
+        key_arg,
+        group_arg,
+        consumer_arg,
+        &claimed,
+        options.delivery_time,
+        state,
+    );
+    let next = id_reply(claimed.next);
+    let deleted = Data::Array(claimed.deleted.iter().copied().map(id_reply).collect());
+    Ok(Data::Array(vec![
+        next,
+        claimed_reply(claimed, just_id),
+        deleted,
+    ]))
+}
@@ -0,0 +1,402 @@
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::protocol::{format_double, Cmd, Data};
+use crate::storage::{clamp_range, Db};
+
+/// Integers as Redis `string2ll` parses them: no sign other than `-`, no spaces, no leading zeros
+pub fn parse_integer(value: &[u8]) -> Result<i64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| {
+            let integer: i64 = value.parse().ok()?;
+            (integer.to_string() == value).then_some(integer)
+        })
+        .ok_or(Error::NotAnInteger)
+}
+
+/// Floats as Redis `string2ld` parses them, NaN is rejected
+pub fn parse_float(value: &[u8]) -> Result<f64> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or(Error::NotAFloat)
+}
+
+/// Add the increment to the integer stored at key, a missing key counts as 0
+fn incr_by(key: &str, increment: i64, state: &Arc<Db>) -> Result<Data> {
+    state
+        .update_string(key, |value, exists| {
+            let current = if exists { parse_integer(value)? } else { 0 };
+            let updated = current.checked_add(increment).ok_or(Error::Overflow)?;
+            *value = updated.to_string().into_bytes();
+            Ok(updated)
+        })
+        .map(Data::Integer)
+}
+
+fn parse_key_increment<'a>(cmd: &str, args: &'a [Data]) -> Result<(&'a str, i64)> {
+    let [key, increment] = args else {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let increment: &[u8] = increment.try_into()?;
+    Ok((key, parse_integer(increment)?))
+}
+
+/// Implement incr as described here <https://redis.io/docs/latest/commands/incr/>
+pub fn incr_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("incr".to_string()));
+    };
+    incr_by(key.try_into()?, 1, state)
+}
+
+/// Implement decr as described here <https://redis.io/docs/latest/commands/decr/>
+pub fn decr_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("decr".to_string()));
+    };
+    incr_by(key.try_into()?, -1, state)
+}
+
+/// Implement incrby as described here <https://redis.io/docs/latest/commands/incrby/>
+pub fn incrby_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, increment) = parse_key_increment("incrby", args)?;
+    incr_by(key, increment, state)
+}
+
+/// Implement decrby as described here <https://redis.io/docs/latest/commands/decrby/>
+pub fn decrby_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let (key, decrement) = parse_key_increment("decrby", args)?;
+    incr_by(key, decrement.checked_neg().ok_or(Error::Overflow)?, state)
+}
+
+/// Implement incrbyfloat as described here <https://redis.io/docs/latest/commands/incrbyfloat/>
+/// The result is propagated as a SET with KEEPTTL, so the slaves store the same value.
+pub fn incrbyfloat_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, increment] = args else {
+        return Err(Error::WrongNumberOfArgs("incrbyfloat".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let increment = parse_float(increment.try_into()?)?;
+
+    let updated = state.update_string(key, |value, exists| {
+        let current = if exists { parse_float(value)? } else { 0.0 };
+        let updated = current + increment;
+        if !updated.is_finite() {
+            return Err(Error::NanOrInfinity);
+        }
+        *value = format_double(updated).into_bytes();
+        Ok(value.clone())
+    })?;
+
+    let args = vec![
+        Data::bulk_string(key),
+        Data::bulk_string(updated.clone()),
+        Data::bulk_string("KEEPTTL"),
+    ];
+    super::propagate(&Cmd::Set { args }, state);
+    Ok(Data::BulkString(updated))
+}
+
+/// Largest string value, as Redis `proto-max-bulk-len`
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// Implement append as described here <https://redis.io/docs/latest/commands/append/>
+pub fn append_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, suffix] = args else {
+        return Err(Error::WrongNumberOfArgs("append".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let suffix: &[u8] = suffix.try_into()?;
+
+    state
+        .update_string(key, |value, _| {
+            if value.len() + suffix.len() > MAX_STRING_LEN {
+                return Err(Error::StringTooLong);
+            }
+            value.extend_from_slice(suffix);
+            Ok(value.len())
+        })
+        .map(Data::from)
+}
+
+/// Implement strlen as described here <https://redis.io/docs/latest/commands/strlen/>
+pub fn strlen_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key] = args else {
+        return Err(Error::WrongNumberOfArgs("strlen".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    Ok(Data::from(
+        state.read_string(key, <[u8]>::len)?.unwrap_or(0),
+    ))
+}
+
+/// Implement getrange as described here <https://redis.io/docs/latest/commands/getrange/>
+pub fn getrange_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, start, end] = args else {
+        return Err(Error::WrongNumberOfArgs("getrange".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let start = parse_integer(start.try_into()?)?;
+    let end = parse_integer(end.try_into()?)?;
+
+    let range = state.read_string(key, |value| {
+        clamp_range(value.len(), start, end)
+            .map_or_else(Vec::new, |(start, end)| value[start..=end].to_vec())
+    });
+    Ok(Data::BulkString(range?.unwrap_or_default()))
+}
+
+/// Implement setrange as described here <https://redis.io/docs/latest/commands/setrange/>
+/// The string is padded with zero bytes if the offset is after its end.
+pub fn setrange_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [key, offset, patch] = args else {
+        return Err(Error::WrongNumberOfArgs("setrange".to_string()));
+    };
+    let key: &str = key.try_into()?;
+    let offset = parse_integer(offset.try_into()?)?;
+    let offset = usize::try_from(offset).map_err(|_| Error::OffsetOutOfRange)?;
+    let patch: &[u8] = patch.try_into()?;
+
+    // nothing to write, a missing key isn't created
+    if patch.is_empty() {
+        return Ok(Data::from(
+            state.read_string(key, <[u8]>::len)?.unwrap_or(0),
+        ));
+    }
+    if offset + patch.len() > MAX_STRING_LEN {
+        return Err(Error::StringTooLong);
+    }
+
+    state
+        .update_string(key, |value, _| {
+            let end = offset + patch.len();
+            if value.len() < end {
+                value.resize(end, 0);
+            }
+            value[offset..end].copy_from_slice(patch);
+            Ok(value.len())
+        })
+        .map(Data::from)
+}
+
+/// Implement mget as described here <https://redis.io/docs/latest/commands/mget/>
+pub fn mget_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    if args.is_empty() {
+        return Err(Error::WrongNumberOfArgs("mget".to_string()));
+    }
+    let keys = args
+        .iter()
+        .map(<&str>::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Data::Array(
+        state
+            .mget(&keys)
+            .into_iter()
+            .map(|value| value.map_or(Data::NullBuilkString, Data::BulkString))
+            .collect(),
+    ))
+}
+
+/// Implement mset and msetnx as described here <https://redis.io/docs/latest/commands/mset/>
+/// All the keys are set at once, the command is propagated as is so the slaves apply it at once too.
+pub fn mset_execute(cmd: &str, args: &[Data], state: &Arc<Db>, nx: bool) -> Result<Data> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(Error::WrongNumberOfArgs(cmd.to_string()));
+    }
+    let pairs = args
+        .chunks_exact(2)
+        .map(|pair| Ok((<&str>::try_from(&pair[0])?, <&[u8]>::try_from(&pair[1])?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let set = state.mset(&pairs, nx);
+    Ok(if nx {
+        Data::Integer(i64::from(set))
+    } else {
+        Data::ok_response()
+    })
+}
+
+/// Options of the LCS command
+#[derive(Debug, Default)]
+struct LcsArgs {
+    len: bool,
+    idx: bool,
+    min_match_len: usize,
+    with_match_len: bool,
+}
+
+fn parse_lcs_args(options: &[Data]) -> Result<LcsArgs> {
+    let mut lcs_args = LcsArgs::default();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let option: &str = option.try_into()?;
+        match option.to_ascii_uppercase().as_str() {
+            "LEN" => lcs_args.len = true,
+            "IDX" => lcs_args.idx = true,
+            "WITHMATCHLEN" => lcs_args.with_match_len = true,
+            "MINMATCHLEN" => {
+                let min: &[u8] = options.next().ok_or(Error::Syntax)?.try_into()?;
+                // a negative length doesn't filter any match
+                lcs_args.min_match_len = usize::try_from(parse_integer(min)?).unwrap_or(0);
+            }
+            _ => return Err(Error::Syntax),
+        }
+    }
+    if lcs_args.len && lcs_args.idx {
+        return Err(Error::Unsupported(
+            "If you want both the length and indexes, please just use IDX.".to_string(),
+        ));
+    }
+    Ok(lcs_args)
+}
+
+/// A common range of the two strings, inclusive bounds in the first and the second string
+type LcsMatch = ((usize, usize), (usize, usize));
+
+/// Longest common subsequence with the dynamic programming algorithm, as Redis does.
+/// Returns the subsequence and the matching ranges, from the end of the strings.
+fn lcs(a: &[u8], b: &[u8]) -> (Vec<u8>, Vec<LcsMatch>) {
+    // table[i][j] is the length of the LCS of a[..i] and b[..j]
+    let width = b.len() + 1;
+    let mut table = vec![0u32; (a.len() + 1) * width];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i * width + j] = if a[i - 1] == b[j - 1] {
+                table[(i - 1) * width + j - 1] + 1
+            } else {
+                table[(i - 1) * width + j].max(table[i * width + j - 1])
+            };
+        }
+    }
+
+    let mut subsequence = Vec::new();
+    let mut matches = Vec::new();
+    // the range being extended backward: start in a, start in b and its length
+    let mut current: Option<(usize, usize, usize)> = None;
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] {
+            subsequence.push(a[i - 1]);
+            current = match current {
+                Some((a_start, b_start, len)) if a_start == i && b_start == j => {
+                    Some((i - 1, j - 1, len + 1))
+                }
+                Some(range) => {
+                    matches.push(range);
+                    Some((i - 1, j - 1, 1))
+                }
+                None => Some((i - 1, j - 1, 1)),
+            };
+            i -= 1;
+            j -= 1;
+        } else {
+            if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+            matches.extend(current.take());
+        }
+    }
+    matches.extend(current);
+    subsequence.reverse();
+
+    let matches = matches
+        .into_iter()
+        .map(|(a_start, b_start, len)| ((a_start, a_start + len - 1), (b_start, b_start + len - 1)))
+        .collect();
+    (subsequence, matches)
+}
+
+/// Implement lcs as described here <https://redis.io/docs/latest/commands/lcs/>
+/// LCS key1 key2 [LEN] [IDX] [MINMATCHLEN min-match-len] [WITHMATCHLEN]
+pub fn lcs_execute(args: &[Data], state: &Arc<Db>) -> Result<Data> {
+    let [first, second, options @ ..] = args else {
+        return Err(Error::WrongNumberOfArgs("lcs".to_string()));
+    };
+    let keys = [<&str>::try_from(first)?, <&str>::try_from(second)?];
+    let flags = parse_lcs_args(options)?;
+
+    let [a, b] = <[_; 2]>::try_from(state.get_strings(&keys)?).unwrap_or_default();
+    let (a, b) = (a.unwrap_or_default(), b.unwrap_or_default());
+    if (a.len() + 1)
+        .saturating_mul(b.len() + 1)
+        .saturating_mul(size_of::<u32>())
+        > MAX_STRING_LEN
+    {
+        return Err(Error::Unsupported(
+            "Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len".to_string(),
+        ));
+    }
+
+    let (subsequence, matches) = lcs(&a, &b);
+    if flags.len {
+        return Ok(Data::from(subsequence.len()));
+    }
+    if !flags.idx {
+        return Ok(Data::BulkString(subsequence));
+    }
+
+    let range = |(start, end): (usize, usize)| Data::Array(vec![start.into(), end.into()]);
+    let matches = matches
+        .into_iter()
+        .filter(|((start, end), _)| end - start + 1 >= flags.min_match_len)
+        .map(|(a_range, b_range)| {
+            let mut item = vec![range(a_range), range(b_range)];
+            if flags.with_match_len {
+                item.push(Data::from(a_range.1 - a_range.0 + 1));
+            }
+            Data::Array(item)
+        })
+        .collect();
+    Ok(Data::Map(vec![
+        (Data::bulk_string("matches"), Data::Array(matches)),
+        (Data::bulk_string("len"), Data::from(subsequence.len())),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lcs, parse_float, parse_integer};
+
+    #[test]
+    fn parse_integer_test() {
+        assert_eq!(parse_integer(b"-42").unwrap(), -42);
+        assert_eq!(parse_integer(b"0").unwrap(), 0);
+        assert_eq!(parse_integer(b"9223372036854775807").unwrap(), i64::MAX);
+        for invalid in [
+            &b"+1"[..],
+            b"01",
+            b"-0",
+            b" 1",
+            b"",
+            b"1.5",
+            b"9223372036854775808",
+        ] {
+            assert!(parse_integer(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn parse_float_test() {
+        assert!((parse_float(b"10.5").unwrap() - 10.5).abs() < f64::EPSILON);
+        assert!((parse_float(b"5.0e3").unwrap() - 5000.0).abs() < f64::EPSILON);
+        assert!(parse_float(b"nan").is_err());
+        assert!(parse_float(b"abc").is_err());
+    }
+
+    #[test]
+    fn lcs_test() {
+        // example of the Redis documentation
+        let (subsequence, matches) = lcs(b"ohmytext", b"mynewtext");
+        assert_eq!(subsequence, b"mytext");
+        assert_eq!(matches, vec![((4, 7), (5, 8)), ((2, 3), (0, 1))]);
+
+        let (subsequence, matches) = lcs(b"", b"abc");
+        assert!(subsequence.is_empty() && matches.is_empty());
+    }
+}
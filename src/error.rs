@@ -6,8 +6,78 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug, From, Display)]
 pub enum Error {
     InvalidResp,
+    ExpectedBulk(char),
     ArgsMissing(String),
     InvalidRdb(String),
+    UnknownCommand(String),
+    WrongNumberOfArgs(String),
+    Replication(String),
+    Syntax,
+    NoProto,
+    InvalidCursor,
+    NoSuchKey,
+    DbIndexOutOfRange,
+    InvalidExpireTime(String),
+    IncompatibleOptions(String),
+    NotAnInteger,
+    NotAFloat,
+    Overflow,
+    NanOrInfinity,
+    OffsetOutOfRange,
+    StringTooLong,
+    WrongType,
+    IndexOutOfRange,
+    NotPositive,
+    ValueOutOfRange,
+    HashValueNotInteger,
+    NumFields,
+    ScoreNan,
+    MinMaxNotFloat,
+    InvalidLexRange,
+    InvalidStreamId,
+    StreamIdTooSmall,
+    NoGroup(String),
+    BusyGroup,
+    BitOffset,
+    BitValue,
+    BitfieldType,
+    InvalidHll,
+    CorruptedHll,
+    ReadOnly,
+    NumKeysNotPositive,
+    TooManyNumKeys,
+    NegativeLimit,
+    IncrSinglePair,
+    RankLimit,
+    LexWithScores,
+    NoInputKeys(String),
+    WeightNotFloat,
+    InvalidIntervalStart,
+    InvalidIntervalEnd,
+    NegativeMaxLen,
+    NegativeTrimLimit,
+    TrimLimitWithoutTilde,
+    UnbalancedStreams(String),
+    UnknownXgroupSubcommand(String),
+    CountNotPositive,
+    StreamIdExhausted,
+    StreamIdZero,
+    XgroupKeyRequired,
+    TimeoutNotFloat,
+    NegativeTimeout,
+    BitArgument,
+    BitopNotSingleKey,
+    InvalidOverflow,
+    InvalidGeoUnit,
+    InvalidLonLat(String),
+    NeedNumeric(String),
+    NegativeRadius,
+    NegativeBoxSize,
+    GeoSearchFrom,
+    GeoSearchShape,
+    AnyWithoutCount,
+    UndecodableMember,
+    NotUtf8,
 
     // Externals
     #[from]
@@ -24,6 +94,234 @@ pub enum Error {
     P2pSwarmError(libp2p::swarm::DialError),
 }
 
+impl Error {
+    /// Redis error replies start with an upper case prefix identifying the kind of error
+    /// <https://redis.io/docs/latest/develop/reference/protocol-spec/#simple-errors>
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Error::InvalidResp
+            | Error::ExpectedBulk(_)
+            | Error::ArgsMissing(_)
+            | Error::InvalidRdb(_)
+            | Error::UnknownCommand(_)
+            | Error::WrongNumberOfArgs(_)
+            | Error::Replication(_)
+            | Error::IO(_)
+            | Error::Parser(_)
+            | Error::Unsupported(_)
+            | Error::P2pError(_)
+            | Error::P2pTransportError(_)
+            | Error::Syntax
+            | Error::InvalidCursor
+            | Error::NoSuchKey
+            | Error::DbIndexOutOfRange
+            | Error::InvalidExpireTime(_)
+            | Error::IncompatibleOptions(_)
+            | Error::NotAnInteger
+            | Error::NotAFloat
+            | Error::Overflow
+            | Error::NanOrInfinity
+            | Error::OffsetOutOfRange
+            | Error::StringTooLong
+            | Error::IndexOutOfRange
+            | Error::NotPositive
+            | Error::ValueOutOfRange
+            | Error::HashValueNotInteger
+            | Error::NumFields
+            | Error::ScoreNan
+            | Error::MinMaxNotFloat
+            | Error::InvalidLexRange
+            | Error::InvalidStreamId
+            | Error::StreamIdTooSmall
+            | Error::BitOffset
+            | Error::BitValue
+            | Error::BitfieldType
+            | Error::NumKeysNotPositive
+            | Error::TooManyNumKeys
+            | Error::NegativeLimit
+            | Error::IncrSinglePair
+            | Error::RankLimit
+            | Error::LexWithScores
+            | Error::NoInputKeys(_)
+            | Error::WeightNotFloat
+            | Error::InvalidIntervalStart
+            | Error::InvalidIntervalEnd
+            | Error::NegativeMaxLen
+            | Error::NegativeTrimLimit
+            | Error::TrimLimitWithoutTilde
+            | Error::UnbalancedStreams(_)
+            | Error::UnknownXgroupSubcommand(_)
+            | Error::CountNotPositive
+            | Error::StreamIdExhausted
+            | Error::StreamIdZero
+            | Error::XgroupKeyRequired
+            | Error::TimeoutNotFloat
+            | Error::NegativeTimeout
+            | Error::BitArgument
+            | Error::BitopNotSingleKey
+            | Error::InvalidOverflow
+            | Error::InvalidGeoUnit
+            | Error::InvalidLonLat(_)
+            | Error::NeedNumeric(_)
+            | Error::NegativeRadius
+            | Error::NegativeBoxSize
+            | Error::GeoSearchFrom
+            | Error::GeoSearchShape
+            | Error::AnyWithoutCount
+            | Error::UndecodableMember
+            | Error::NotUtf8
+            | Error::P2pSwarmError(_) => "ERR",
+            Error::NoProto => "NOPROTO",
+            Error::WrongType | Error::InvalidHll => "WRONGTYPE",
+            Error::NoGroup(_) => "NOGROUP",
+            Error::BusyGroup => "BUSYGROUP",
+            Error::CorruptedHll => "INVALIDOBJ",
+            Error::ReadOnly => "READONLY",
+        }
+    }
+
+    /// The error message sent back to the client, as a Redis server would reply
+    // a single message table, one arm per error
+    #[allow(clippy::too_many_lines)]
+    pub fn reply_message(&self) -> String {
+        let message = match self {
+            Error::InvalidResp => "Protocol error".to_string(),
+            Error::ExpectedBulk(kind) => format!("Protocol error: expected '$', got '{kind}'"),
+            Error::UnknownCommand(cmd) => format!("unknown command '{cmd}'"),
+            Error::WrongNumberOfArgs(cmd) => {
+                format!(
+                    "wrong number of arguments for '{}' command",
+                    cmd.to_lowercase()
+                )
+            }
+            Error::Parser(_) | Error::NotAnInteger => {
+                "value is not an integer or out of range".to_string()
+            }
+            Error::NotAFloat => "value is not a valid float".to_string(),
+            Error::Overflow => "increment or decrement would overflow".to_string(),
+            Error::NanOrInfinity => "increment would produce NaN or Infinity".to_string(),
+            Error::OffsetOutOfRange => "offset is out of range".to_string(),
+            Error::WrongType => {
+                "Operation against a key holding the wrong kind of value".to_string()
+            }
+            Error::IndexOutOfRange => "index out of range".to_string(),
+            Error::NotPositive => "value is out of range, must be positive".to_string(),
+            Error::ValueOutOfRange => "value is out of range".to_string(),
+            Error::HashValueNotInteger => "hash value is not an integer".to_string(),
+            Error::ScoreNan => "resulting score is not a number (NaN)".to_string(),
+            Error::MinMaxNotFloat => "min or max is not a float".to_string(),
+            Error::InvalidLexRange => "min or max not valid string range item".to_string(),
+            Error::InvalidStreamId => {
+                "Invalid stream ID specified as stream command argument".to_string()
+            }
+            Error::StreamIdTooSmall => {
+                "The ID specified in XADD is equal or smaller than the target stream top item"
+                    .to_string()
+            }
+            Error::NoGroup(message) => message.clone(),
+            Error::BusyGroup => "Consumer Group name already exists".to_string(),
+            Error::BitOffset => "bit offset is not an integer or out of range".to_string(),
+            Error::BitValue => "bit is not an integer or out of range".to_string(),
+            Error::InvalidHll => "Key is not a valid HyperLogLog string value.".to_string(),
+            Error::CorruptedHll => "Corrupted HLL object detected".to_string(),
+            Error::ReadOnly => "You can't write against a read only replica.".to_string(),
+            Error::BitfieldType => {
+                "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                    .to_string()
+            }
+            Error::NumFields => {
+                "The `numfields` parameter must match the number of arguments".to_string()
+            }
+            Error::StringTooLong => {
+                "string exceeds maximum allowed size (proto-max-bulk-len)".to_string()
+            }
+            Error::Syntax => "syntax error".to_string(),
+            Error::NoProto => "unsupported protocol version".to_string(),
+            Error::InvalidCursor => "invalid cursor".to_string(),
+            Error::NoSuchKey => "no such key".to_string(),
+            Error::DbIndexOutOfRange => "DB index is out of range".to_string(),
+            Error::InvalidExpireTime(cmd) => {
+                format!("invalid expire time in '{}' command", cmd.to_lowercase())
+            }
+            Error::IncompatibleOptions(options) => {
+                format!("{options} options at the same time are not compatible")
+            }
+            Error::NumKeysNotPositive => "numkeys should be greater than 0".to_string(),
+            Error::TooManyNumKeys => {
+                "Number of keys can't be greater than number of args".to_string()
+            }
+            Error::NegativeLimit => "LIMIT can't be negative".to_string(),
+            Error::IncrSinglePair => {
+                "INCR option supports a single increment-element pair".to_string()
+            }
+            Error::RankLimit => {
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .to_string()
+            }
+            Error::LexWithScores => {
+                "syntax error, WITHSCORES not supported in combination with BYLEX".to_string()
+            }
+            Error::NoInputKeys(cmd) => {
+                format!("at least 1 input key is needed for '{cmd}' command")
+            }
+            Error::WeightNotFloat => "weight value is not a float".to_string(),
+            Error::InvalidIntervalStart => "invalid start ID for the interval".to_string(),
+            Error::InvalidIntervalEnd => "invalid end ID for the interval".to_string(),
+            Error::NegativeMaxLen => "The MAXLEN argument must be >= 0.".to_string(),
+            Error::NegativeTrimLimit => "The LIMIT argument must be >= 0.".to_string(),
+            Error::TrimLimitWithoutTilde => {
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string()
+            }
+            Error::UnbalancedStreams(cmd) => {
+                format!("Unbalanced '{cmd}' list of streams: for each stream key an ID or '$' must be specified.")
+            }
+            Error::UnknownXgroupSubcommand(sub_cmd) => {
+                format!("unknown subcommand '{sub_cmd}'. Try XGROUP HELP.")
+            }
+            Error::CountNotPositive => "COUNT must be > 0".to_string(),
+            Error::StreamIdExhausted => {
+                "The stream has exhausted the last possible ID, unable to add more items"
+                    .to_string()
+            }
+            Error::StreamIdZero => "The ID specified in XADD must be greater than 0-0".to_string(),
+            Error::XgroupKeyRequired => {
+                "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically."
+                    .to_string()
+            }
+            Error::TimeoutNotFloat => "timeout is not a float or out of range".to_string(),
+            Error::NegativeTimeout => "timeout is negative".to_string(),
+            Error::BitArgument => "The bit argument must be 1 or 0.".to_string(),
+            Error::BitopNotSingleKey => {
+                "BITOP NOT must be called with a single source key.".to_string()
+            }
+            Error::InvalidOverflow => "Invalid OVERFLOW type specified".to_string(),
+            Error::InvalidGeoUnit => {
+                "unsupported unit provided. please use M, KM, FT, MI".to_string()
+            }
+            Error::InvalidLonLat(pair) => format!("invalid longitude,latitude pair {pair}"),
+            Error::NeedNumeric(name) => format!("need numeric {name}"),
+            Error::NegativeRadius => "radius cannot be negative".to_string(),
+            Error::NegativeBoxSize => "height or width cannot be negative".to_string(),
+            Error::GeoSearchFrom => {
+                "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH".to_string()
+            }
+            Error::GeoSearchShape => {
+                "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH".to_string()
+            }
+            Error::AnyWithoutCount => "the ANY argument requires COUNT argument".to_string(),
+            Error::UndecodableMember => "could not decode requested zset member".to_string(),
+            Error::NotUtf8 => {
+                "keys and string arguments must be valid UTF-8, binary keys are not supported"
+                    .to_string()
+            }
+            other => other.to_string(),
+        };
+        // error replies can't contain new lines
+        let message = message.replace(['\r', '\n'], " ");
+        format!("{} {message}", self.prefix())
+    }
+}
+
 impl From<FromUtf8Error> for Error {
     fn from(e: FromUtf8Error) -> Self {
         Error::InvalidRdb(format!("Invalid utf8 - {e}"))
@@ -1,15 +1,17 @@
 #![deny(clippy::pedantic)] // up front pain and suffering for the greater good :)
 
 use std::error::Error;
-use std::io::{BufReader, BufWriter, Write};
-use std::net::TcpListener;
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
-use protocol::Data;
+use futures::{FutureExt, SinkExt, StreamExt};
+use protocol::{Cmd, Data, RespCodec};
 use replication::{master, slave};
 use storage::{Config, Db};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::MissedTickBehavior;
+use tokio_util::codec::Framed;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::EnvFilter;
 
@@ -34,6 +36,9 @@ struct Args {
     port: Option<u16>,
     #[arg(long)]
     replicaof: Option<String>,
+    /// Frequency of the background tasks such as the active expiration
+    #[arg(long)]
+    hz: Option<u32>,
     #[arg(long)]
     remote_p2p_peer: Option<String>,
 }
@@ -56,10 +61,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
     ipfs::start_swam_loop(&args.remote_p2p_peer);
 
     tracing::info!("Redis server starting at {}!", config.port);
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", config.port));
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", config.port)).await;
 
     match listener {
-        Ok(mut tcp_listener) => start_loop(&config, &mut tcp_listener),
+        Ok(tcp_listener) => start_loop(&config, &tcp_listener).await,
         Err(e) => Err(error::Error::IO(e)),
     }?;
 
@@ -67,51 +72,142 @@ async fn main() -> Result<(), Box<dyn Error>> {
 }
 
 /// main Redis loop, listening for incomming command request
-fn start_loop(conf: &Config, tcp_listener: &mut TcpListener) -> error::Result<()> {
+/// each client is served by its own task until the server is stopped.
+async fn start_loop(conf: &Config, tcp_listener: &TcpListener) -> error::Result<()> {
     let mut db = Rdb::from(conf);
     let database = db.load()?;
     let state = Arc::new(database);
 
-    // Starts replication if the node is a slave
-    slave::start_replication(Arc::clone(&state))?;
+    // Starts replication if the node is a slave, the replication link is still blocking
+    let replication_state = Arc::clone(&state);
+    tokio::task::spawn_blocking(move || slave::start_replication(replication_state))
+        .await
+        .map_err(|e| error::Error::Replication(format!("replication task failed {e}")))??;
+
+    tokio::spawn(active_expire_loop(Arc::clone(&state)));
 
     loop {
-        let state: Arc<Db> = Arc::clone(&state);
-        if let Ok((client_stream, _)) = tcp_listener.accept() {
-            tracing::debug!("New client connected!");
-            std::thread::spawn(move || {
-                if let Err(e) = process_client_requets(client_stream, &state) {
-                    tracing::warn!("Error processing request {e:?}");
-                }
-                tracing::debug!("Thread completed");
-            });
+        tokio::select! {
+            accepted = tcp_listener.accept() => {
+                let (client_stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("Unable to accept client {e:?}");
+                        continue;
+                    }
+                };
+                tracing::debug!("New client connected!");
+                let state: Arc<Db> = Arc::clone(&state);
+                tokio::spawn(async move {
+                    if let Err(e) = process_client_requets(client_stream, &state).await {
+                        tracing::warn!("Error processing request {e:?}");
+                    }
+                    tracing::debug!("Client task completed");
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("Shutting down Redis server");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Remove the expired keys in the background, `hz` times per second.
+/// Only the master expires keys and hash fields actively, the deletes are propagated to the slaves.
+async fn active_expire_loop(state: Arc<Db>) {
+    let period = Duration::from_millis(1000 / u64::from(state.config.hz.max(1)));
+    // as in Redis, a cycle can use up to 25% of the time between two cycles
+    let time_limit = period / 4;
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        if !state.info().is_master() {
+            continue;
         }
+        let _ordered = state.order_writes();
+        let expired = state.active_expire_cycle(time_limit);
+        master::broadcast_expired(expired, &state);
     }
 }
 
 /// Process the incoming request from a single Redis client.
-fn process_client_requets(stream: TcpStream, state: &Arc<Db>) -> error::Result<()> {
-    let mut reader = BufReader::new(stream.try_clone().unwrap());
-    let writer = Arc::new(Mutex::new(BufWriter::new(stream)));
+/// Command failures are sent back to the client as error replies, only protocol errors close the connection.
+async fn process_client_requets(stream: TcpStream, state: &Arc<Db>) -> error::Result<()> {
+    let mut framed = Framed::new(stream, RespCodec::default());
+    let mut session = cmds::Session::new(state);
 
     loop {
-        match Data::parse_cmd(&mut reader) {
-            Err(err) => println!("Unable to parse cmd: {err:?}"),
-            Ok(cmd) => {
-                let response = cmds::execute(cmd, state)?;
-                tracing::debug!("process_stream response: {response:?}");
-                if matches!(response, Data::ConnectionClosed) {
-                    tracing::debug!("Client disconnected");
-                    return Ok(());
+        // pipelined requests are answered with a single flush, once all the buffered requests are processed
+        let request = if let Some(request) = framed.next().now_or_never() {
+            request
+        } else {
+            framed.flush().await?;
+            framed.next().await
+        };
+        let Some(request) = request else {
+            break;
+        };
+
+        let response = match request.and_then(Cmd::from_args) {
+            Ok(cmd) if cmd.is_blocking() => {
+                // the replies already buffered are sent before the client is parked
+                framed.flush().await?;
+                let response = tokio::select! {
+                    response = cmds::execute_blocking(cmd, state, &session) => response,
+                    () = client_closed(framed.get_ref()) => break,
+                };
+                response.unwrap_or_else(|err| {
+                    tracing::debug!("Error executing cmd: {err:?}");
+                    Data::error_response(&err)
+                })
+            }
+            Ok(cmd) => cmds::execute(cmd, state, &mut session).unwrap_or_else(|err| {
+                tracing::debug!("Error executing cmd: {err:?}");
+                Data::error_response(&err)
+            }),
+            // the whole command was read, the client can keep sending commands
+            Err(err @ error::Error::UnknownCommand(_)) => Data::error_response(&err),
+            Err(err) => {
+                tracing::debug!("Unable to parse cmd: {err:?}");
+                if matches!(
+                    err,
+                    error::Error::InvalidResp | error::Error::ExpectedBulk(_)
+                ) {
+                    framed.send(Data::error_response(&err)).await?;
                 }
+                return Err(err);
+            }
+        };
+        tracing::debug!("process_stream response: {response:?}");
+        if matches!(response, Data::ConnectionClosed) {
+            framed.flush().await?;
+            break;
+        }
 
-                // if the client is doing a handshake
-                master::register_slave(&response, &writer, state);
+        let is_full_resync = matches!(response, Data::FullResyncBinaryConent(_, _));
+        framed.codec_mut().resp_version = session.resp_version;
+        framed.feed(response).await?;
 
-                let mut writer = writer.lock().unwrap();
-                response.write_resp(&mut writer)?;
-                writer.flush()?;
-            }
+        // if the client is doing a handshake, the connection is now used to feed the slave
+        if is_full_resync {
+            framed.flush().await?;
+            return master::serve_slave(framed, state).await;
         }
     }
+
+    tracing::debug!("Client disconnected");
+    Ok(())
+}
+
+/// Resolve when the client closes the connection, the requests it pipelines while it is blocked
+/// are left to be read once it is served
+async fn client_closed(stream: &TcpStream) {
+    let mut buf = [0; 1];
+    match stream.peek(&mut buf).await {
+        Ok(0) | Err(_) => {}
+        Ok(_) => std::future::pending().await,
+    }
 }
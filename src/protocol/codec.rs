@@ -0,0 +1,211 @@
+use bytes::{Buf, BufMut, BytesMut};
+use std::io::BufReader;
+use tokio_util::codec::{Decoder, Encoder};
+
+use super::parser::{MAX_BULK_LEN, MAX_INLINE_LEN};
+use super::{Data, RespVersion};
+use crate::error::{Error, Result};
+
+/// Frame the client requests and encode the responses over an async stream.
+/// Requests are decoded as the list of arguments, responses are encoded with the negotiated protocol version.
+#[derive(Debug, Default)]
+pub struct RespCodec {
+    pub resp_version: RespVersion,
+    scan: FrameScan,
+}
+
+/// Progress of the scan of the next request, kept between the reads so the headers are scanned
+/// once and the request is only parsed when all its bytes are buffered
+#[derive(Debug, Default)]
+struct FrameScan {
+    /// End of the arguments already scanned
+    scanned: usize,
+    /// Arguments left to scan
+    pending: usize,
+    /// Length the buffer must reach before the scan can go further
+    needed: usize,
+}
+
+/// Position after the \n ending the line that starts at `start`
+fn line_end(src: &[u8], start: usize) -> Option<usize> {
+    src[start..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map(|end| start + end + 1)
+}
+
+/// The length or number of elements of a header line such as `$5\r\n`
+fn header_length(line: &[u8]) -> Result<i64> {
+    let length = line
+        .strip_suffix(b"\r\n")
+        .and_then(|line| std::str::from_utf8(&line[1..]).ok())
+        .and_then(|length| length.parse().ok());
+    length.ok_or(Error::InvalidResp)
+}
+
+impl FrameScan {
+    /// Length of the request at the start of the buffer, None until it is complete.
+    /// Only the framing is checked, the request is validated when it is parsed.
+    fn frame_len(&mut self, src: &[u8]) -> Result<Option<usize>> {
+        if src.len() < self.needed {
+            return Ok(None);
+        }
+        if src[0] != b'*' {
+            // inline commands end with the line, the parser rejects the lines too long
+            if let Some(end) = line_end(src, self.scanned) {
+                return Ok(Some(end));
+            }
+            if src.len() as u64 >= MAX_INLINE_LEN {
+                return Ok(Some(src.len()));
+            }
+            self.scanned = src.len();
+            self.needed = src.len() + 1;
+            return Ok(None);
+        }
+
+        loop {
+            let start = self.scanned;
+            let Some(&kind) = src.get(start) else {
+                self.needed = start + 1;
+                return Ok(None);
+            };
+            let Some(mut end) = line_end(src, start) else {
+                if (src.len() - start) as u64 > MAX_INLINE_LEN {
+                    return Err(Error::InvalidResp);
+                }
+                self.needed = src.len() + 1;
+                return Ok(None);
+            };
+            if start == 0 {
+                // the number of arguments, the null array has none
+                let arguments = header_length(&src[..end])?.max(0);
+                self.pending = usize::try_from(arguments).map_err(|_| Error::InvalidResp)?;
+            } else if kind == b'$' {
+                let length = header_length(&src[start..end])?;
+                if length >= 0 {
+                    let length = u64::try_from(length)
+                        .ok()
+                        .filter(|length| *length <= MAX_BULK_LEN)
+                        .and_then(|length| usize::try_from(length).ok())
+                        .ok_or(Error::InvalidResp)?;
+                    // the data and its \r\n trailer
+                    end += length + 2;
+                    if src.len() < end {
+                        self.needed = end;
+                        return Ok(None);
+                    }
+                }
+                self.pending -= 1;
+            } else {
+                // as in Redis the arguments can only be bulk strings, nested aggregates included
+                return Err(Error::ExpectedBulk(kind as char));
+            }
+            self.scanned = end;
+            if self.pending == 0 {
+                return Ok(Some(end));
+            }
+        }
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = Vec<Data>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Vec<Data>>> {
+        loop {
+            if src.is_empty() {
+                return Ok(None);
+            }
+            let Some(len) = self.scan.frame_len(src)? else {
+                src.reserve(self.scan.needed - src.len());
+                return Ok(None);
+            };
+            self.scan = FrameScan::default();
+
+            // The request is complete, it is parsed once with the parser shared with the
+            // blocking readers. Empty requests are skipped.
+            let request = Data::parse_request(&mut BufReader::new(&src[..len]))?;
+            src.advance(len);
+            if request.is_some() {
+                return Ok(request);
+            }
+        }
+    }
+}
+
+impl Encoder<Data> for RespCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Data, dst: &mut BytesMut) -> Result<()> {
+        item.write_resp(&mut dst.writer(), self.resp_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RespCodec;
+    use crate::error::Error;
+    use crate::protocol::Data;
+    use bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    #[test]
+    fn decode_partial_requests_test() {
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::from("*2\r\n$4\r\nECHO\r\n$5\r\nhel");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+
+        buffer.extend_from_slice(b"lo\r\nPING\r\n*1\r\n");
+        let request = codec.decode(&mut buffer).unwrap();
+        assert_eq!(
+            request,
+            Some(vec![Data::bulk_string("ECHO"), Data::bulk_string("hello")])
+        );
+        let request = codec.decode(&mut buffer).unwrap();
+        assert_eq!(request, Some(vec![Data::bulk_string("PING")]));
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        assert_eq!(&buffer[..], b"*1\r\n");
+    }
+
+    #[test]
+    fn decode_large_request_test() {
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::from("\r\n*2\r\n$3\r\nGET\r\n$100000\r\n");
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        // the empty line is skipped and the request is buffered up to its end
+        assert!(buffer.capacity() >= 22 + 100_000 + 2);
+
+        let key = vec![b'k'; 100_000];
+        for chunk in key.chunks(1000) {
+            buffer.extend_from_slice(chunk);
+            assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        }
+        buffer.extend_from_slice(b"\r\n");
+        let request = codec.decode(&mut buffer).unwrap();
+        assert_eq!(
+            request,
+            Some(vec![Data::bulk_string("GET"), Data::BulkString(key)])
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decode_nested_request_test() {
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::from("*2\r\n$4\r\nECHO\r\n:1\r\n");
+        let error = codec.decode(&mut buffer).unwrap_err();
+        assert_eq!(
+            error.reply_message(),
+            "ERR Protocol error: expected '$', got ':'"
+        );
+
+        // arrays nested deep enough to overflow the stack when parsed are rejected up front
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::from("*1\r\n".repeat(1_000_000).as_str());
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(Error::ExpectedBulk('*'))
+        ));
+    }
+}
@@ -1,23 +1,96 @@
 use crate::error::Error;
 use crate::error::Result;
+mod codec;
 mod parser;
 mod write;
 
+pub use codec::RespCodec;
+
 /// resp supprted as described here <https://redis.io/docs/latest/develop/reference/protocol-spec/#resp-protocol-description>
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Data {
     ConnectionClosed,
     SimpleString(String),
-    BulkString(String),
+    BulkString(Vec<u8>),
     NullBuilkString,
+    Error(String),
+    Integer(i64),
     Array(Vec<Data>),
+    NullArray,
+    // RESP3 types, see <https://github.com/redis/redis-specifications/blob/master/protocol/RESP3.md>
+    Null,
+    Boolean(bool),
+    Double(f64),
+    BigNumber(String),
+    VerbatimString(String, Vec<u8>),
+    Map(Vec<(Data, Data)>),
+    Set(Vec<Data>),
+    Push(Vec<Data>),
+    Attribute(Vec<(Data, Data)>, Box<Data>),
     FullResyncBinaryConent(Box<Data>, Vec<u8>),
 }
 
+/// Protocol version used by a connection, negotiated with the HELLO command
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum RespVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl RespVersion {
+    pub fn from_protover(protover: i64) -> Option<RespVersion> {
+        match protover {
+            2 => Some(RespVersion::Resp2),
+            3 => Some(RespVersion::Resp3),
+            _ => None,
+        }
+    }
+
+    pub fn protover(self) -> i64 {
+        match self {
+            RespVersion::Resp2 => 2,
+            RespVersion::Resp3 => 3,
+        }
+    }
+}
+
+/// Doubles are formatted as Redis does, with `inf`, `-inf` and `nan` for the special values
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "inf"
+        } else {
+            "-inf"
+        }
+        .to_string()
+    } else {
+        value.to_string()
+    }
+}
+
 impl Data {
     pub fn ok_response() -> Data {
         Data::SimpleString(String::from("OK"))
     }
+
+    pub fn error_response(error: &Error) -> Data {
+        Data::Error(error.reply_message())
+    }
+
+    /// Bulk strings are binary safe, any byte sequence can be stored
+    pub fn bulk_string(value: impl Into<Vec<u8>>) -> Data {
+        Data::BulkString(value.into())
+    }
+}
+
+/// Integer reply for counts and lengths
+impl From<usize> for Data {
+    fn from(value: usize) -> Self {
+        Data::Integer(i64::try_from(value).unwrap_or(i64::MAX))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -27,10 +100,143 @@ pub enum Cmd {
     Echo { args: Vec<Data> },
     Set { args: Vec<Data> },
     Get { args: Vec<Data> },
+    Setnx { args: Vec<Data> },
+    Setex { args: Vec<Data> },
+    Psetex { args: Vec<Data> },
+    Getex { args: Vec<Data> },
+    Getdel { args: Vec<Data> },
+    Incr { args: Vec<Data> },
+    Decr { args: Vec<Data> },
+    Incrby { args: Vec<Data> },
+    Decrby { args: Vec<Data> },
+    Incrbyfloat { args: Vec<Data> },
+    Append { args: Vec<Data> },
+    Strlen { args: Vec<Data> },
+    Getrange { args: Vec<Data> },
+    Setrange { args: Vec<Data> },
+    Mget { args: Vec<Data> },
+    Mset { args: Vec<Data> },
+    Msetnx { args: Vec<Data> },
+    Lcs { args: Vec<Data> },
+    Setbit { args: Vec<Data> },
+    Getbit { args: Vec<Data> },
+    Bitcount { args: Vec<Data> },
+    Bitpos { args: Vec<Data> },
+    Bitop { args: Vec<Data> },
+    Bitfield { args: Vec<Data> },
+    Pfadd { args: Vec<Data> },
+    Pfcount { args: Vec<Data> },
+    Pfmerge { args: Vec<Data> },
+    Geoadd { args: Vec<Data> },
+    Geodist { args: Vec<Data> },
+    Geopos { args: Vec<Data> },
+    Geohash { args: Vec<Data> },
+    Geosearch { args: Vec<Data> },
+    Geosearchstore { args: Vec<Data> },
+    Lpush { args: Vec<Data> },
+    Rpush { args: Vec<Data> },
+    Lpop { args: Vec<Data> },
+    Rpop { args: Vec<Data> },
+    Lrange { args: Vec<Data> },
+    Llen { args: Vec<Data> },
+    Lindex { args: Vec<Data> },
+    Lset { args: Vec<Data> },
+    Lrem { args: Vec<Data> },
+    Ltrim { args: Vec<Data> },
+    Linsert { args: Vec<Data> },
+    Lmove { args: Vec<Data> },
+    Blpop { args: Vec<Data> },
+    Brpop { args: Vec<Data> },
+    Blmove { args: Vec<Data> },
+    Hset { args: Vec<Data> },
+    Hget { args: Vec<Data> },
+    Hmget { args: Vec<Data> },
+    Hdel { args: Vec<Data> },
+    Hgetall { args: Vec<Data> },
+    Hincrby { args: Vec<Data> },
+    Hexists { args: Vec<Data> },
+    Hkeys { args: Vec<Data> },
+    Hvals { args: Vec<Data> },
+    Hlen { args: Vec<Data> },
+    Hscan { args: Vec<Data> },
+    Hexpire { args: Vec<Data> },
+    Hpexpire { args: Vec<Data> },
+    Hexpireat { args: Vec<Data> },
+    Hpexpireat { args: Vec<Data> },
+    Httl { args: Vec<Data> },
+    Hpttl { args: Vec<Data> },
+    Hexpiretime { args: Vec<Data> },
+    Hpexpiretime { args: Vec<Data> },
+    Hpersist { args: Vec<Data> },
+    Sadd { args: Vec<Data> },
+    Srem { args: Vec<Data> },
+    Smembers { args: Vec<Data> },
+    Sismember { args: Vec<Data> },
+    Smismember { args: Vec<Data> },
+    Scard { args: Vec<Data> },
+    Sinter { args: Vec<Data> },
+    Sunion { args: Vec<Data> },
+    Sdiff { args: Vec<Data> },
+    Sinterstore { args: Vec<Data> },
+    Sunionstore { args: Vec<Data> },
+    Sdiffstore { args: Vec<Data> },
+    Sintercard { args: Vec<Data> },
+    Srandmember { args: Vec<Data> },
+    Spop { args: Vec<Data> },
+    Sscan { args: Vec<Data> },
+    Zadd { args: Vec<Data> },
+    Zincrby { args: Vec<Data> },
+    Zrem { args: Vec<Data> },
+    Zcard { args: Vec<Data> },
+    Zscore { args: Vec<Data> },
+    Zrank { args: Vec<Data> },
+    Zrevrank { args: Vec<Data> },
+    Zrange { args: Vec<Data> },
+    Zrevrange { args: Vec<Data> },
+    Zrangebyscore { args: Vec<Data> },
+    Zrevrangebyscore { args: Vec<Data> },
+    Zrangebylex { args: Vec<Data> },
+    Zrevrangebylex { args: Vec<Data> },
+    Zunionstore { args: Vec<Data> },
+    Zinterstore { args: Vec<Data> },
+    Zpopmin { args: Vec<Data> },
+    Zpopmax { args: Vec<Data> },
+    Zscan { args: Vec<Data> },
+    Bzpopmin { args: Vec<Data> },
+    Bzpopmax { args: Vec<Data> },
+    Xadd { args: Vec<Data> },
+    Xlen { args: Vec<Data> },
+    Xrange { args: Vec<Data> },
+    Xtrim { args: Vec<Data> },
+    Xread { args: Vec<Data> },
+    Xgroup { args: Vec<Data> },
+    Xreadgroup { args: Vec<Data> },
+    Xack { args: Vec<Data> },
+    Xpending { args: Vec<Data> },
+    Xclaim { args: Vec<Data> },
+    Xautoclaim { args: Vec<Data> },
     Config { args: Vec<Data> },
     Command { args: Vec<Data> },
     Keys { args: Vec<Data> },
+    Scan { args: Vec<Data> },
+    Del { args: Vec<Data> },
+    Unlink { args: Vec<Data> },
+    Exists { args: Vec<Data> },
+    Type { args: Vec<Data> },
+    Rename { args: Vec<Data> },
+    Renamenx { args: Vec<Data> },
+    Copy { args: Vec<Data> },
+    Expire { args: Vec<Data> },
+    Pexpire { args: Vec<Data> },
+    Expireat { args: Vec<Data> },
+    Pexpireat { args: Vec<Data> },
+    Ttl { args: Vec<Data> },
+    Pttl { args: Vec<Data> },
+    Expiretime { args: Vec<Data> },
+    Pexpiretime { args: Vec<Data> },
+    Persist { args: Vec<Data> },
     Info { args: Vec<Data> },
+    Hello { args: Vec<Data> },
     // Replication related commands
     Replconf { args: Vec<Data> },
     Psync { args: Vec<Data> },
@@ -41,7 +247,9 @@ impl<'a> TryFrom<&'a Data> for &'a str {
 
     fn try_from(value: &'a Data) -> core::result::Result<Self, Self::Error> {
         match value {
-            Data::BulkString(s) | Data::SimpleString(s) => Ok(s.as_str()),
+            Data::SimpleString(s) => Ok(s.as_str()),
+            // the keys are strings, binary keys are rejected here
+            Data::BulkString(bytes) => std::str::from_utf8(bytes).map_err(|_| Error::NotUtf8),
             invalid => Err(Error::Unsupported(format!(
                 "Unable to extract string from unsupported {invalid:?}"
             ))),
@@ -49,52 +257,354 @@ impl<'a> TryFrom<&'a Data> for &'a str {
     }
 }
 
+impl<'a> TryFrom<&'a Data> for &'a [u8] {
+    type Error = Error;
+
+    fn try_from(value: &'a Data) -> core::result::Result<Self, Self::Error> {
+        match value {
+            Data::BulkString(bytes) => Ok(bytes.as_slice()),
+            Data::SimpleString(s) => Ok(s.as_bytes()),
+            invalid => Err(Error::Unsupported(format!(
+                "Unable to extract bytes from unsupported {invalid:?}"
+            ))),
+        }
+    }
+}
+
 impl Cmd {
+    /// Build the command from the request arguments, the first one is the command name
+    pub fn from_args(mut args: Vec<Data>) -> Result<Cmd> {
+        if args.is_empty() {
+            return Err(Error::InvalidResp);
+        }
+        let Data::BulkString(cmd) = args.remove(0) else {
+            return Err(Error::InvalidResp);
+        };
+        let cmd = std::str::from_utf8(&cmd).map_err(|_| Error::InvalidResp)?;
+
+        Cmd::from_str_args(cmd, args)
+    }
+
+    // a single lookup table, one arm per command
+    #[allow(clippy::too_many_lines)]
     pub fn from_str_args(cmd_str: &str, args: Vec<Data>) -> Result<Cmd> {
         match cmd_str.to_ascii_uppercase().as_str() {
             "ECHO" => Ok(Cmd::Echo { args }),
             "PING" => Ok(Cmd::Ping),
             "SET" => Ok(Cmd::Set { args }),
             "GET" => Ok(Cmd::Get { args }),
+            "SETNX" => Ok(Cmd::Setnx { args }),
+            "SETEX" => Ok(Cmd::Setex { args }),
+            "PSETEX" => Ok(Cmd::Psetex { args }),
+            "GETEX" => Ok(Cmd::Getex { args }),
+            "GETDEL" => Ok(Cmd::Getdel { args }),
+            "INCR" => Ok(Cmd::Incr { args }),
+            "DECR" => Ok(Cmd::Decr { args }),
+            "INCRBY" => Ok(Cmd::Incrby { args }),
+            "DECRBY" => Ok(Cmd::Decrby { args }),
+            "INCRBYFLOAT" => Ok(Cmd::Incrbyfloat { args }),
+            "APPEND" => Ok(Cmd::Append { args }),
+            "STRLEN" => Ok(Cmd::Strlen { args }),
+            "GETRANGE" => Ok(Cmd::Getrange { args }),
+            "SETRANGE" => Ok(Cmd::Setrange { args }),
+            "MGET" => Ok(Cmd::Mget { args }),
+            "MSET" => Ok(Cmd::Mset { args }),
+            "MSETNX" => Ok(Cmd::Msetnx { args }),
+            "LCS" => Ok(Cmd::Lcs { args }),
+            "SETBIT" => Ok(Cmd::Setbit { args }),
+            "GETBIT" => Ok(Cmd::Getbit { args }),
+            "BITCOUNT" => Ok(Cmd::Bitcount { args }),
+            "BITPOS" => Ok(Cmd::Bitpos { args }),
+            "BITOP" => Ok(Cmd::Bitop { args }),
+            "BITFIELD" => Ok(Cmd::Bitfield { args }),
+            "PFADD" => Ok(Cmd::Pfadd { args }),
+            "PFCOUNT" => Ok(Cmd::Pfcount { args }),
+            "PFMERGE" => Ok(Cmd::Pfmerge { args }),
+            "GEOADD" => Ok(Cmd::Geoadd { args }),
+            "GEODIST" => Ok(Cmd::Geodist { args }),
+            "GEOPOS" => Ok(Cmd::Geopos { args }),
+            "GEOHASH" => Ok(Cmd::Geohash { args }),
+            "GEOSEARCH" => Ok(Cmd::Geosearch { args }),
+            "GEOSEARCHSTORE" => Ok(Cmd::Geosearchstore { args }),
+            "LPUSH" => Ok(Cmd::Lpush { args }),
+            "RPUSH" => Ok(Cmd::Rpush { args }),
+            "LPOP" => Ok(Cmd::Lpop { args }),
+            "RPOP" => Ok(Cmd::Rpop { args }),
+            "LRANGE" => Ok(Cmd::Lrange { args }),
+            "LLEN" => Ok(Cmd::Llen { args }),
+            "LINDEX" => Ok(Cmd::Lindex { args }),
+            "LSET" => Ok(Cmd::Lset { args }),
+            "LREM" => Ok(Cmd::Lrem { args }),
+            "LTRIM" => Ok(Cmd::Ltrim { args }),
+            "LINSERT" => Ok(Cmd::Linsert { args }),
+            "LMOVE" => Ok(Cmd::Lmove { args }),
+            "BLPOP" => Ok(Cmd::Blpop { args }),
+            "BRPOP" => Ok(Cmd::Brpop { args }),
+            "BLMOVE" => Ok(Cmd::Blmove { args }),
+            "HSET" => Ok(Cmd::Hset { args }),
+            "HGET" => Ok(Cmd::Hget { args }),
+            "HMGET" => Ok(Cmd::Hmget { args }),
+            "HDEL" => Ok(Cmd::Hdel { args }),
+            "HGETALL" => Ok(Cmd::Hgetall { args }),
+            "HINCRBY" => Ok(Cmd::Hincrby { args }),
+            "HEXISTS" => Ok(Cmd::Hexists { args }),
+            "HKEYS" => Ok(Cmd::Hkeys { args }),
+            "HVALS" => Ok(Cmd::Hvals { args }),
+            "HLEN" => Ok(Cmd::Hlen { args }),
+            "HSCAN" => Ok(Cmd::Hscan { args }),
+            "HEXPIRE" => Ok(Cmd::Hexpire { args }),
+            "HPEXPIRE" => Ok(Cmd::Hpexpire { args }),
+            "HEXPIREAT" => Ok(Cmd::Hexpireat { args }),
+            "HPEXPIREAT" => Ok(Cmd::Hpexpireat { args }),
+            "HTTL" => Ok(Cmd::Httl { args }),
+            "HPTTL" => Ok(Cmd::Hpttl { args }),
+            "HEXPIRETIME" => Ok(Cmd::Hexpiretime { args }),
+            "HPEXPIRETIME" => Ok(Cmd::Hpexpiretime { args }),
+            "HPERSIST" => Ok(Cmd::Hpersist { args }),
+            "SADD" => Ok(Cmd::Sadd { args }),
+            "SREM" => Ok(Cmd::Srem { args }),
+            "SMEMBERS" => Ok(Cmd::Smembers { args }),
+            "SISMEMBER" => Ok(Cmd::Sismember { args }),
+            "SMISMEMBER" => Ok(Cmd::Smismember { args }),
+            "SCARD" => Ok(Cmd::Scard { args }),
+            "SINTER" => Ok(Cmd::Sinter { args }),
+            "SUNION" => Ok(Cmd::Sunion { args }),
+            "SDIFF" => Ok(Cmd::Sdiff { args }),
+            "SINTERSTORE" => Ok(Cmd::Sinterstore { args }),
+            "SUNIONSTORE" => Ok(Cmd::Sunionstore { args }),
+            "SDIFFSTORE" => Ok(Cmd::Sdiffstore { args }),
+            "SINTERCARD" => Ok(Cmd::Sintercard { args }),
+            "SRANDMEMBER" => Ok(Cmd::Srandmember { args }),
+            "SPOP" => Ok(Cmd::Spop { args }),
+            "SSCAN" => Ok(Cmd::Sscan { args }),
+            "ZADD" => Ok(Cmd::Zadd { args }),
+            "ZINCRBY" => Ok(Cmd::Zincrby { args }),
+            "ZREM" => Ok(Cmd::Zrem { args }),
+            "ZCARD" => Ok(Cmd::Zcard { args }),
+            "ZSCORE" => Ok(Cmd::Zscore { args }),
+            "ZRANK" => Ok(Cmd::Zrank { args }),
+            "ZREVRANK" => Ok(Cmd::Zrevrank { args }),
+            "ZRANGE" => Ok(Cmd::Zrange { args }),
+            "ZREVRANGE" => Ok(Cmd::Zrevrange { args }),
+            "ZRANGEBYSCORE" => Ok(Cmd::Zrangebyscore { args }),
+            "ZREVRANGEBYSCORE" => Ok(Cmd::Zrevrangebyscore { args }),
+            "ZRANGEBYLEX" => Ok(Cmd::Zrangebylex { args }),
+            "ZREVRANGEBYLEX" => Ok(Cmd::Zrevrangebylex { args }),
+            "ZUNIONSTORE" => Ok(Cmd::Zunionstore { args }),
+            "ZINTERSTORE" => Ok(Cmd::Zinterstore { args }),
+            "ZPOPMIN" => Ok(Cmd::Zpopmin { args }),
+            "ZPOPMAX" => Ok(Cmd::Zpopmax { args }),
+            "ZSCAN" => Ok(Cmd::Zscan { args }),
+            "BZPOPMIN" => Ok(Cmd::Bzpopmin { args }),
+            "BZPOPMAX" => Ok(Cmd::Bzpopmax { args }),
+            "XADD" => Ok(Cmd::Xadd { args }),
+            "XLEN" => Ok(Cmd::Xlen { args }),
+            "XRANGE" => Ok(Cmd::Xrange { args }),
+            "XTRIM" => Ok(Cmd::Xtrim { args }),
+            "XREAD" => Ok(Cmd::Xread { args }),
+            "XGROUP" => Ok(Cmd::Xgroup { args }),
+            "XREADGROUP" => Ok(Cmd::Xreadgroup { args }),
+            "XACK" => Ok(Cmd::Xack { args }),
+            "XPENDING" => Ok(Cmd::Xpending { args }),
+            "XCLAIM" => Ok(Cmd::Xclaim { args }),
+            "XAUTOCLAIM" => Ok(Cmd::Xautoclaim { args }),
             "CONFIG" => Ok(Cmd::Config { args }),
             "COMMAND" => Ok(Cmd::Command { args }),
             "KEYS" => Ok(Cmd::Keys { args }),
+            "SCAN" => Ok(Cmd::Scan { args }),
+            "DEL" => Ok(Cmd::Del { args }),
+            "UNLINK" => Ok(Cmd::Unlink { args }),
+            "EXISTS" => Ok(Cmd::Exists { args }),
+            "TYPE" => Ok(Cmd::Type { args }),
+            "RENAME" => Ok(Cmd::Rename { args }),
+            "RENAMENX" => Ok(Cmd::Renamenx { args }),
+            "COPY" => Ok(Cmd::Copy { args }),
+            "EXPIRE" => Ok(Cmd::Expire { args }),
+            "PEXPIRE" => Ok(Cmd::Pexpire { args }),
+            "EXPIREAT" => Ok(Cmd::Expireat { args }),
+            "PEXPIREAT" => Ok(Cmd::Pexpireat { args }),
+            "TTL" => Ok(Cmd::Ttl { args }),
+            "PTTL" => Ok(Cmd::Pttl { args }),
+            "EXPIRETIME" => Ok(Cmd::Expiretime { args }),
+            "PEXPIRETIME" => Ok(Cmd::Pexpiretime { args }),
+            "PERSIST" => Ok(Cmd::Persist { args }),
             "INFO" => Ok(Cmd::Info { args }),
+            "HELLO" => Ok(Cmd::Hello { args }),
             "REPLCONF" => Ok(Cmd::Replconf { args }),
             "PSYNC" => Ok(Cmd::Psync { args }),
-            c => Err(Error::Unsupported(c.to_string())),
+            _ => Err(Error::UnknownCommand(cmd_str.to_string())),
         }
     }
 
+    /// Encode the command as a request, used to send the commands to the master or the slaves
     pub fn to_data(&self) -> Result<Data> {
-        match self {
-            Cmd::Ping => Ok(Data::Array(vec![Data::BulkString("PING".to_string())])),
-            Cmd::Replconf { args } => {
-                let mut data = vec![Data::BulkString("REPLCONF".to_string())];
-
-                args.iter().for_each(|i| data.push(i.clone()));
-                Ok(Data::Array(data))
+        let (name, args): (&str, &[Data]) = match self {
+            Cmd::Ping => ("PING", &[]),
+            Cmd::Replconf { args } => ("REPLCONF", args),
+            Cmd::Psync { args } => ("PSYNC", args),
+            Cmd::Set { args } => ("SET", args),
+            Cmd::Del { args } => ("DEL", args),
+            Cmd::Unlink { args } => ("UNLINK", args),
+            Cmd::Rename { args } => ("RENAME", args),
+            Cmd::Renamenx { args } => ("RENAMENX", args),
+            Cmd::Copy { args } => ("COPY", args),
+            Cmd::Pexpireat { args } => ("PEXPIREAT", args),
+            Cmd::Persist { args } => ("PERSIST", args),
+            Cmd::Incr { args } => ("INCR", args),
+            Cmd::Decr { args } => ("DECR", args),
+            Cmd::Incrby { args } => ("INCRBY", args),
+            Cmd::Decrby { args } => ("DECRBY", args),
+            Cmd::Append { args } => ("APPEND", args),
+            Cmd::Setrange { args } => ("SETRANGE", args),
+            Cmd::Mset { args } => ("MSET", args),
+            Cmd::Msetnx { args } => ("MSETNX", args),
+            Cmd::Setbit { args } => ("SETBIT", args),
+            Cmd::Bitop { args } => ("BITOP", args),
+            Cmd::Bitfield { args } => ("BITFIELD", args),
+            Cmd::Pfadd { args } => ("PFADD", args),
+            Cmd::Pfmerge { args } => ("PFMERGE", args),
+            Cmd::Geoadd { args } => ("GEOADD", args),
+            Cmd::Geosearchstore { args } => ("GEOSEARCHSTORE", args),
+            Cmd::Lpush { args } => ("LPUSH", args),
+            Cmd::Rpush { args } => ("RPUSH", args),
+            Cmd::Lpop { args } => ("LPOP", args),
+            Cmd::Rpop { args } => ("RPOP", args),
+            Cmd::Lset { args } => ("LSET", args),
+            Cmd::Lrem { args } => ("LREM", args),
+            Cmd::Ltrim { args } => ("LTRIM", args),
+            Cmd::Linsert { args } => ("LINSERT", args),
+            Cmd::Lmove { args } => ("LMOVE", args),
+            Cmd::Hset { args } => ("HSET", args),
+            Cmd::Hdel { args } => ("HDEL", args),
+            Cmd::Hincrby { args } => ("HINCRBY", args),
+            Cmd::Hpexpireat { args } => ("HPEXPIREAT", args),
+            Cmd::Hpersist { args } => ("HPERSIST", args),
+            Cmd::Sadd { args } => ("SADD", args),
+            Cmd::Srem { args } => ("SREM", args),
+            Cmd::Sinterstore { args } => ("SINTERSTORE", args),
+            Cmd::Sunionstore { args } => ("SUNIONSTORE", args),
+            Cmd::Sdiffstore { args } => ("SDIFFSTORE", args),
+            Cmd::Zadd { args } => ("ZADD", args),
+            Cmd::Zincrby { args } => ("ZINCRBY", args),
+            Cmd::Zrem { args } => ("ZREM", args),
+            Cmd::Zunionstore { args } => ("ZUNIONSTORE", args),
+            Cmd::Zinterstore { args } => ("ZINTERSTORE", args),
+            Cmd::Zpopmin { args } => ("ZPOPMIN", args),
+            Cmd::Zpopmax { args } => ("ZPOPMAX", args),
+            Cmd::Xadd { args } => ("XADD", args),
+            Cmd::Xtrim { args } => ("XTRIM", args),
+            Cmd::Xgroup { args } => ("XGROUP", args),
+            Cmd::Xack { args } => ("XACK", args),
+            Cmd::Xclaim { args } => ("XCLAIM", args),
+            other => {
+                return Err(Error::Unsupported(format!(
+                    "Invalid command {other:?} to encode"
+                )))
             }
+        };
 
-            Cmd::Psync { args } => {
-                let mut data = vec![Data::BulkString("PSYNC".to_string())];
+        let mut data = vec![Data::bulk_string(name)];
+        data.extend(args.iter().cloned());
+        Ok(Data::Array(data))
+    }
 
-                args.iter().for_each(|a| data.push(a.clone()));
-                Ok(Data::Array(data))
-            }
+    /// Write commands are propagated to the slaves as is.
+    /// Writes depending on the time or on a condition, like the SET and EXPIRE families,
+    /// propagate a deterministic equivalent themselves and are not listed here.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Cmd::Del { .. }
+                | Cmd::Unlink { .. }
+                | Cmd::Rename { .. }
+                | Cmd::Renamenx { .. }
+                | Cmd::Copy { .. }
+                | Cmd::Persist { .. }
+                | Cmd::Incr { .. }
+                | Cmd::Decr { .. }
+                | Cmd::Incrby { .. }
+                | Cmd::Decrby { .. }
+                | Cmd::Append { .. }
+                | Cmd::Setrange { .. }
+                | Cmd::Mset { .. }
+                | Cmd::Msetnx { .. }
+                | Cmd::Setbit { .. }
+                | Cmd::Bitop { .. }
+                | Cmd::Bitfield { .. }
+                | Cmd::Pfadd { .. }
+                | Cmd::Pfmerge { .. }
+                | Cmd::Geoadd { .. }
+                | Cmd::Geosearchstore { .. }
+                | Cmd::Lpush { .. }
+                | Cmd::Rpush { .. }
+                | Cmd::Lpop { .. }
+                | Cmd::Rpop { .. }
+                | Cmd::Lset { .. }
+                | Cmd::Lrem { .. }
+                | Cmd::Ltrim { .. }
+                | Cmd::Linsert { .. }
+                | Cmd::Lmove { .. }
+                | Cmd::Hset { .. }
+                | Cmd::Hdel { .. }
+                | Cmd::Hincrby { .. }
+                | Cmd::Hpersist { .. }
+                | Cmd::Sadd { .. }
+                | Cmd::Srem { .. }
+                | Cmd::Sinterstore { .. }
+                | Cmd::Sunionstore { .. }
+                | Cmd::Sdiffstore { .. }
+                | Cmd::Zadd { .. }
+                | Cmd::Zincrby { .. }
+                | Cmd::Zrem { .. }
+                | Cmd::Zunionstore { .. }
+                | Cmd::Zinterstore { .. }
+                | Cmd::Zpopmin { .. }
+                | Cmd::Zpopmax { .. }
+                | Cmd::Xtrim { .. }
+                | Cmd::Xgroup { .. }
+                | Cmd::Xack { .. }
+        )
+    }
 
-            Cmd::Set { args } => {
-                let mut data = vec![Data::BulkString("SET".to_string())];
-                args.iter().for_each(|a| data.push(a.clone()));
-                Ok(Data::Array(data))
-            }
-            other => Err(Error::Unsupported(format!(
-                "Invalid command {other:?} to encode"
-            ))),
-        }
+    /// Writes propagating a deterministic equivalent themselves, see [`Cmd::is_write`]
+    pub fn propagates_itself(&self) -> bool {
+        matches!(
+            self,
+            Cmd::Set { .. }
+                | Cmd::Setnx { .. }
+                | Cmd::Setex { .. }
+                | Cmd::Psetex { .. }
+                | Cmd::Getex { .. }
+                | Cmd::Getdel { .. }
+                | Cmd::Incrbyfloat { .. }
+                | Cmd::Expire { .. }
+                | Cmd::Pexpire { .. }
+                | Cmd::Expireat { .. }
+                | Cmd::Pexpireat { .. }
+                | Cmd::Hexpire { .. }
+                | Cmd::Hpexpire { .. }
+                | Cmd::Hexpireat { .. }
+                | Cmd::Hpexpireat { .. }
+                | Cmd::Spop { .. }
+                | Cmd::Xadd { .. }
+                | Cmd::Xreadgroup { .. }
+                | Cmd::Xclaim { .. }
+                | Cmd::Xautoclaim { .. }
+        )
     }
 
-    pub fn is_write(&self) -> bool {
-        matches!(self, Cmd::Set { .. })
+    /// Blocking commands park the client until they can be served or their timeout fires.
+    /// XREAD only blocks with the BLOCK option.
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            Cmd::Blpop { .. }
+                | Cmd::Brpop { .. }
+                | Cmd::Blmove { .. }
+                | Cmd::Bzpopmin { .. }
+                | Cmd::Bzpopmax { .. }
+        ) || matches!(self, Cmd::Xread { args } if args.iter().any(|arg| {
+            <&str>::try_from(arg).is_ok_and(|arg| arg.eq_ignore_ascii_case("BLOCK"))
+        }))
     }
 }
@@ -7,35 +7,43 @@ use super::Data;
 impl Data {
     /// Request commands are either an array (start with *) or an inline command
     pub fn parse_cmd<T: Read>(stream: &mut BufReader<T>) -> Result<Cmd> {
-        let mut first_byte = [0; 1];
-        if let Err(error) = stream.read_exact(&mut first_byte) {
-            if matches!(error.kind(), io::ErrorKind::UnexpectedEof) {
-                return Ok(Cmd::ConnectionClosed);
-            }
+        match Data::parse_request(stream)? {
+            Some(args) => Cmd::from_args(args),
+            None => Ok(Cmd::ConnectionClosed),
         }
+    }
 
-        let Some(first_character) = first_byte.first() else {
-            return Err(Error::InvalidResp);
-        };
+    /// Read the arguments of the next request, None if the stream is closed
+    pub fn parse_request<T: Read>(stream: &mut BufReader<T>) -> Result<Option<Vec<Data>>> {
+        loop {
+            // peek the first byte, inline commands need it as part of the line
+            let first_character = match stream.fill_buf() {
+                Ok([]) => return Ok(None),
+                Ok(buffer) => buffer[0],
+                Err(error) => return Err(Error::IO(error)),
+            };
 
-        let span = tracing::debug_span!("parse_cmd", first_character = %*first_character as char);
+            let span =
+                tracing::debug_span!("parse_request", first_character = %first_character as char);
 
-        span.in_scope(|| {
-            match first_character {
-                b'*' => {
-                    let Data::Array(mut array_data) = parse_array(stream)? else {
-                        return Err(Error::InvalidResp);
-                    };
+            let args = span.in_scope(|| -> Result<Vec<Data>> {
+                if first_character == b'*' {
+                    stream.consume(1);
+                    let array_data = parse_arguments(stream)?;
                     tracing::debug!("Array data: {array_data:?}");
-                    to_cmd(&mut array_data)
-                }
-                other => {
-                    tracing::debug!("unsupported data {other:?}");
-                    // TODO implement inline commands, for now we consider it to be a PING
-                    Ok(Cmd::Ping)
+                    return Ok(array_data);
                 }
+
+                let inline_data = parse_inline(stream)?;
+                tracing::debug!("Inline data: {inline_data:?}");
+                Ok(inline_data)
+            })?;
+
+            // empty lines are ignored
+            if !args.is_empty() {
+                return Ok(Some(args));
             }
-        })
+        }
     }
 
     pub fn parse<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
@@ -49,32 +57,41 @@ impl Data {
 
         tracing::info_span!("parse", first_byte=%*first_byte as char).in_scope(
             || match first_byte {
-                b'+' => Ok(parse_simple_string(stream)),
+                b'+' => parse_simple_string(stream),
+                b'-' => parse_simple_error(stream),
+                b':' => parse_integer(stream),
                 b'*' => parse_array(stream),
                 b'$' => parse_bulk_string(stream),
+                b'_' => parse_null(stream),
+                b'#' => parse_boolean(stream),
+                b',' => parse_double(stream),
+                b'(' => parse_big_number(stream),
+                b'=' => parse_verbatim_string(stream),
+                b'%' => Ok(Data::Map(parse_pairs(stream)?)),
+                b'~' => Ok(Data::Set(parse_elements(stream)?)),
+                b'>' => Ok(Data::Push(parse_elements(stream)?)),
+                b'|' => {
+                    let attributes = parse_pairs(stream)?;
+                    Ok(Data::Attribute(attributes, Box::new(Data::parse(stream)?)))
+                }
                 _ => Err(Error::InvalidResp),
             },
         )
     }
 }
 
-fn to_cmd(args: &mut Vec<Data>) -> Result<Cmd> {
-    let Data::BulkString(cmd) = args.remove(0) else {
-        return Err(Error::InvalidResp);
-    };
-
-    let args = std::mem::take(args);
-
-    Cmd::from_str_args(&cmd, args)
-}
-
 /// RESP arrays are encoded follow:
 /// *<number-of-elements>\r\n<element-1>...<element-n>
+/// The null array is encoded as *-1\r\n
 fn parse_array<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
-    let line = read_str_line(stream);
-    let num_elements: usize = line.parse()?;
+    let num_elements: i64 = read_length(stream)?;
+    if num_elements == -1 {
+        return Ok(Data::NullArray);
+    }
+    let num_elements = usize::try_from(num_elements).map_err(|_| Error::InvalidResp)?;
 
-    let mut array: Vec<Data> = Vec::with_capacity(num_elements);
+    // the capacity is bounded, the number of elements is sent by the client
+    let mut array: Vec<Data> = Vec::with_capacity(num_elements.min(1024));
 
     for _ in 0..num_elements {
         array.push(Data::parse(stream)?);
@@ -83,36 +100,302 @@ fn parse_array<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
     Ok(Data::Array(array))
 }
 
+/// The arguments of a request array, as in Redis they can only be bulk strings. Nested
+/// aggregates are rejected so a request can't nest arrays as deep as it wants.
+fn parse_arguments<T: Read>(stream: &mut BufReader<T>) -> Result<Vec<Data>> {
+    let num_arguments: i64 = read_length(stream)?;
+    let num_arguments = usize::try_from(num_arguments).map_err(|_| Error::InvalidResp)?;
+
+    let mut arguments: Vec<Data> = Vec::with_capacity(num_arguments.min(1024));
+    for _ in 0..num_arguments {
+        let mut kind = [0; 1];
+        stream.read_exact(&mut kind)?;
+        if kind[0] != b'$' {
+            return Err(Error::ExpectedBulk(kind[0] as char));
+        }
+        arguments.push(parse_bulk_string(stream)?);
+    }
+    Ok(arguments)
+}
+
 /// Read a line expluding the end caracters \r\n
-fn read_str_line<T: Read>(stream: &mut BufReader<T>) -> String {
-    let mut line = String::new();
-    let _ = stream.read_line(&mut line);
-    line[..(line.len().saturating_sub(2))].to_owned()
+/// A line without the \n terminator means the stream ended before the line was complete.
+fn read_line<T: Read>(stream: &mut BufReader<T>) -> Result<Vec<u8>> {
+    let mut line = Vec::new();
+    stream.read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\n") {
+        return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+    }
+    if !line.ends_with(b"\r\n") {
+        return Err(Error::InvalidResp);
+    }
+    line.truncate(line.len() - 2);
+    Ok(line)
+}
+
+fn read_str_line<T: Read>(stream: &mut BufReader<T>) -> Result<String> {
+    String::from_utf8(read_line(stream)?).map_err(|_| Error::InvalidResp)
+}
+
+/// Read the length prefix of arrays and bulk strings
+fn read_length<T, N>(stream: &mut BufReader<T>) -> Result<N>
+where
+    T: Read,
+    N: std::str::FromStr,
+{
+    read_str_line(stream)?
+        .parse()
+        .map_err(|_| Error::InvalidResp)
+}
+
+/// Max size of an inline command, same as `PROTO_INLINE_MAX_SIZE` in Redis
+pub(super) const MAX_INLINE_LEN: u64 = 64 * 1024;
+
+/// Inline commands are space separated arguments terminated by a new line, as sent by telnet:
+/// SET foo "hello world"\r\n
+fn parse_inline<T: Read>(stream: &mut BufReader<T>) -> Result<Vec<Data>> {
+    let mut line = Vec::new();
+    stream
+        .by_ref()
+        .take(MAX_INLINE_LEN)
+        .read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\n") {
+        if line.len() as u64 == MAX_INLINE_LEN {
+            return Err(Error::InvalidResp);
+        }
+        return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+    }
+    line.pop();
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+
+    let args = split_args(&line).ok_or(Error::InvalidResp)?;
+    Ok(args.into_iter().map(Data::BulkString).collect())
+}
+
+/// Split the line in arguments as Redis `sdssplitargs` does:
+///  - arguments are separated by spaces
+///  - "double quoted" arguments support escapes like \n, \r, \t, \b, \a, \\, \" and \xHH
+///  - 'single quoted' arguments only support the \' escape
+///
+/// Returns None if the quotes are unbalanced or a closing quote is not followed by a space.
+fn split_args(line: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut chars = line.iter().copied().peekable();
+
+    loop {
+        while chars.next_if(u8::is_ascii_whitespace).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Some(args);
+        };
+
+        let mut arg = Vec::new();
+        match first {
+            b'"' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        b'\\' => {
+                            let escaped = chars.next()?;
+                            match escaped {
+                                b'x' => {
+                                    let hex = [*chars.peek()?, chars.clone().nth(1)?];
+                                    // from_str_radix alone would accept a leading +
+                                    let byte = hex
+                                        .iter()
+                                        .all(u8::is_ascii_hexdigit)
+                                        .then(|| std::str::from_utf8(&hex).ok())
+                                        .flatten()
+                                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                                    if let Some(byte) = byte {
+                                        chars.nth(1);
+                                        arg.push(byte);
+                                    } else {
+                                        arg.push(b'x');
+                                    }
+                                }
+                                b'n' => arg.push(b'\n'),
+                                b'r' => arg.push(b'\r'),
+                                b't' => arg.push(b'\t'),
+                                b'b' => arg.push(0x08),
+                                b'a' => arg.push(0x07),
+                                other => arg.push(other),
+                            }
+                        }
+                        b'"' => break,
+                        other => arg.push(other),
+                    }
+                }
+                // closing quote must be followed by a space or nothing
+                if chars.peek().is_some_and(|c| !c.is_ascii_whitespace()) {
+                    return None;
+                }
+            }
+            b'\'' => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        b'\\' if chars.peek() == Some(&b'\'') => {
+                            chars.next();
+                            arg.push(b'\'');
+                        }
+                        b'\'' => break,
+                        other => arg.push(other),
+                    }
+                }
+                if chars.peek().is_some_and(|c| !c.is_ascii_whitespace()) {
+                    return None;
+                }
+            }
+            _ => {
+                while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace()) {
+                    arg.push(c);
+                }
+            }
+        }
+        args.push(arg);
+    }
 }
 
+/// Max size of a bulk string, same as the default `proto-max-bulk-len` in Redis
+pub(super) const MAX_BULK_LEN: u64 = 512 * 1024 * 1024;
+
 /// Resp Bulk String are encoded as follow:
 /// $<length>\r\n<data>\r\n
 /// examles:
 ///  - hello: $5\r\nhello\r\n
 ///  - '':    $0\r\n\r\n
+///  - null:  $-1\r\n
+///
+/// The data is binary and may contain \r\n, so exactly <length> bytes are read.
 fn parse_bulk_string<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
-    let size_line = read_str_line(stream);
-    let size: usize = size_line.parse()?;
+    let size: i64 = read_length(stream)?;
+    if size == -1 {
+        return Ok(Data::NullBuilkString);
+    }
+    Ok(Data::BulkString(read_blob(stream, size)?))
+}
+
+/// Read exactly <size> bytes followed by the \r\n trailer
+fn read_blob<T: Read>(stream: &mut BufReader<T>, size: i64) -> Result<Vec<u8>> {
+    let size = u64::try_from(size).map_err(|_| Error::InvalidResp)?;
+    if size > MAX_BULK_LEN {
+        return Err(Error::InvalidResp);
+    }
+
+    let mut data = Vec::new();
+    stream.by_ref().take(size).read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        return Err(Error::IO(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    let mut trailer = [0; 2];
+    stream.read_exact(&mut trailer)?;
+    if &trailer != b"\r\n" {
+        return Err(Error::InvalidResp);
+    }
 
-    let data_line = read_str_line(stream);
-    tracing::info!("build_string[size:{size}, data: {data_line}]");
-    Ok(Data::BulkString(data_line[..size].to_string()))
+    tracing::debug!("build_string[size:{size}]");
+    Ok(data)
 }
 
-fn parse_simple_string<T: Read>(stream: &mut BufReader<T>) -> Data {
-    let line: String = read_str_line(stream);
-    Data::SimpleString(line)
+fn parse_simple_string<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let line: String = read_str_line(stream)?;
+    Ok(Data::SimpleString(line))
+}
+
+/// Resp Simple Errors are encoded as follow:
+/// -<prefix> <message>\r\n
+fn parse_simple_error<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let line: String = read_str_line(stream)?;
+    Ok(Data::Error(line))
+}
+
+/// Resp Integers are encoded as follow:
+/// :[<+|->]<value>\r\n
+fn parse_integer<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let value: i64 = read_length(stream)?;
+    Ok(Data::Integer(value))
+}
+
+/// RESP3 Null is encoded as _\r\n
+fn parse_null<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    if !read_line(stream)?.is_empty() {
+        return Err(Error::InvalidResp);
+    }
+    Ok(Data::Null)
+}
+
+/// RESP3 Booleans are encoded as #<t|f>\r\n
+fn parse_boolean<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    match read_line(stream)?.as_slice() {
+        b"t" => Ok(Data::Boolean(true)),
+        b"f" => Ok(Data::Boolean(false)),
+        _ => Err(Error::InvalidResp),
+    }
+}
+
+/// RESP3 Doubles are encoded as ,[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n
+/// or inf, -inf and nan
+fn parse_double<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let value: f64 = read_length(stream)?;
+    Ok(Data::Double(value))
+}
+
+/// RESP3 Big numbers are encoded as ([+|-]<number>\r\n
+fn parse_big_number<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let line = read_str_line(stream)?;
+    let digits = line.strip_prefix(['+', '-']).unwrap_or(&line);
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(Error::InvalidResp);
+    }
+    Ok(Data::BigNumber(line))
+}
+
+/// RESP3 Verbatim strings are encoded as =<length>\r\n<encoding>:<data>\r\n
+/// where encoding is exactly three bytes
+fn parse_verbatim_string<T: Read>(stream: &mut BufReader<T>) -> Result<Data> {
+    let size: i64 = read_length(stream)?;
+    let mut data = read_blob(stream, size)?;
+    if data.len() < 4 || data[3] != b':' {
+        return Err(Error::InvalidResp);
+    }
+    let value = data.split_off(4);
+    let format = String::from_utf8(data[..3].to_vec()).map_err(|_| Error::InvalidResp)?;
+    Ok(Data::VerbatimString(format, value))
+}
+
+/// RESP3 Sets and Pushes are encoded as arrays
+/// <type><number-of-elements>\r\n<element-1>...<element-n>
+fn parse_elements<T: Read>(stream: &mut BufReader<T>) -> Result<Vec<Data>> {
+    let num_elements: usize = read_length(stream)?;
+    let mut elements: Vec<Data> = Vec::with_capacity(num_elements.min(1024));
+    for _ in 0..num_elements {
+        elements.push(Data::parse(stream)?);
+    }
+    Ok(elements)
+}
+
+/// RESP3 Maps and Attributes are encoded as
+/// <type><number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
+fn parse_pairs<T: Read>(stream: &mut BufReader<T>) -> Result<Vec<(Data, Data)>> {
+    let num_entries: usize = read_length(stream)?;
+    let mut entries = Vec::with_capacity(num_entries.min(1024));
+    for _ in 0..num_entries {
+        let key = Data::parse(stream)?;
+        let value = Data::parse(stream)?;
+        entries.push((key, value));
+    }
+    Ok(entries)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol::Data;
-    use std::io::{BufReader, Cursor};
+    use super::split_args;
+    use crate::error::Error;
+    use crate::protocol::{Cmd, Data, RespVersion};
+    use std::io::{BufReader, BufWriter, Cursor};
 
     fn build_reader(resp_txt: &str) -> BufReader<Cursor<&[u8]>> {
         let data = resp_txt.as_bytes();
@@ -123,7 +406,7 @@ mod tests {
     #[test]
     fn bulk_decode_test() {
         let result: Data = Data::parse(&mut build_reader("$5\r\nhello\r\n")).unwrap();
-        assert_eq!(result, Data::BulkString("hello".to_string()));
+        assert_eq!(result, Data::bulk_string("hello"));
     }
 
     #[test]
@@ -131,10 +414,186 @@ mod tests {
         let result =
             Data::parse(&mut build_reader("*2\r\n$5\r\nhello\r\n$5\r\nworld\r\n")).unwrap();
 
-        let expected = Data::Array(vec![
-            Data::BulkString("hello".to_string()),
-            Data::BulkString("world".to_string()),
-        ]);
+        let expected = Data::Array(vec![Data::bulk_string("hello"), Data::bulk_string("world")]);
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn bulk_decode_binary_test() {
+        let data: &[u8] = b"$4\r\n\x00\xff\xfe\x01\r\n";
+        let result = Data::parse(&mut BufReader::new(data)).unwrap();
+        assert_eq!(result, Data::bulk_string(vec![0x00, 0xff, 0xfe, 0x01]));
+    }
+
+    #[test]
+    fn bulk_decode_embedded_crlf_test() {
+        let mut reader = build_reader("$12\r\nhello\r\nworld\r\n+OK\r\n");
+        let result = Data::parse(&mut reader).unwrap();
+        assert_eq!(result, Data::bulk_string("hello\r\nworld"));
+        // the stream is still aligned on the next value
+        let next = Data::parse(&mut reader).unwrap();
+        assert_eq!(next, Data::SimpleString("OK".to_string()));
+    }
+
+    #[test]
+    fn bulk_decode_empty_test() {
+        let result = Data::parse(&mut build_reader("$0\r\n\r\n")).unwrap();
+        assert_eq!(result, Data::bulk_string(""));
+    }
+
+    #[test]
+    fn bulk_decode_null_test() {
+        let result = Data::parse(&mut build_reader("$-1\r\n")).unwrap();
+        assert_eq!(result, Data::NullBuilkString);
+    }
+
+    #[test]
+    fn bulk_decode_truncated_test() {
+        for truncated in ["$5\r\nhel", "$5\r\nhello", "$5\r\nhello\r", "$5", "$"] {
+            let result = Data::parse(&mut build_reader(truncated));
+            assert!(
+                matches!(&result, Err(Error::IO(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof),
+                "{truncated:?} => {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bulk_decode_invalid_framing_test() {
+        for invalid in [
+            "$5\r\nhelloXX",
+            "$3\r\nhello\r\n",
+            "$abc\r\n",
+            "$-2\r\n",
+            "$5\nhello\r\n",
+        ] {
+            let result = Data::parse(&mut build_reader(invalid));
+            assert!(
+                matches!(result, Err(Error::InvalidResp)),
+                "{invalid:?} => {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn integer_decode_test() {
+        let result = Data::parse(&mut build_reader(":1000\r\n")).unwrap();
+        assert_eq!(result, Data::Integer(1000));
+        let result = Data::parse(&mut build_reader(":-42\r\n")).unwrap();
+        assert_eq!(result, Data::Integer(-42));
+        let result = Data::parse(&mut build_reader(":+7\r\n")).unwrap();
+        assert_eq!(result, Data::Integer(7));
+        assert!(Data::parse(&mut build_reader(":12a\r\n")).is_err());
+    }
+
+    #[test]
+    fn error_decode_test() {
+        let result = Data::parse(&mut build_reader("-ERR unknown command 'foo'\r\n")).unwrap();
+        assert_eq!(result, Data::Error("ERR unknown command 'foo'".to_string()));
+    }
+
+    #[test]
+    fn null_array_decode_test() {
+        let result = Data::parse(&mut build_reader("*-1\r\n")).unwrap();
+        assert_eq!(result, Data::NullArray);
+        let result = Data::parse(&mut build_reader("*0\r\n")).unwrap();
+        assert_eq!(result, Data::Array(vec![]));
+    }
+
+    fn encode(data: &Data, version: RespVersion) -> String {
+        let mut writer = BufWriter::new(Vec::new());
+        data.write_resp(&mut writer, version).unwrap();
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resp3_round_trip_test() {
+        let data = Data::Attribute(
+            vec![(Data::SimpleString("ttl".to_string()), Data::Integer(3600))],
+            Box::new(Data::Map(vec![
+                (Data::bulk_string("double"), Data::Double(1.5)),
+                (Data::bulk_string("inf"), Data::Double(f64::NEG_INFINITY)),
+                (Data::bulk_string("bool"), Data::Boolean(true)),
+                (Data::bulk_string("null"), Data::Null),
+                (
+                    Data::bulk_string("big"),
+                    Data::BigNumber("3492890328409238509324850943850943825024385".to_string()),
+                ),
+                (
+                    Data::bulk_string("txt"),
+                    Data::VerbatimString("txt".to_string(), b"Some string".to_vec()),
+                ),
+                (
+                    Data::bulk_string("set"),
+                    Data::Set(vec![Data::Integer(1), Data::Integer(2)]),
+                ),
+                (
+                    Data::bulk_string("push"),
+                    Data::Push(vec![Data::bulk_string("message")]),
+                ),
+            ])),
+        );
+        let encoded = encode(&data, RespVersion::Resp3);
+        assert!(encoded.starts_with("|1\r\n+ttl\r\n:3600\r\n%8\r\n"));
+        let result = Data::parse(&mut build_reader(&encoded)).unwrap();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn resp2_downgrade_test() {
+        let data = Data::Map(vec![
+            (Data::bulk_string("a"), Data::Boolean(false)),
+            (Data::bulk_string("b"), Data::Double(2.5)),
+            (Data::bulk_string("c"), Data::Null),
+            (
+                Data::bulk_string("d"),
+                Data::VerbatimString("txt".to_string(), b"hi".to_vec()),
+            ),
+        ]);
+        assert_eq!(
+            encode(&data, RespVersion::Resp2),
+            "*8\r\n$1\r\na\r\n:0\r\n$1\r\nb\r\n$3\r\n2.5\r\n$1\r\nc\r\n$-1\r\n$1\r\nd\r\n$2\r\nhi\r\n"
+        );
+    }
+
+    #[test]
+    fn inline_cmd_test() {
+        let mut reader = build_reader("SET foo bar\r\n\r\nGET foo\n*1\r\n$4\r\nPING\r\n");
+        assert!(
+            matches!(Data::parse_cmd(&mut reader), Ok(Cmd::Set { args }) if args == vec![Data::bulk_string("foo"), Data::bulk_string("bar")])
+        );
+        // the empty line is skipped, lines can end with \n only
+        assert!(
+            matches!(Data::parse_cmd(&mut reader), Ok(Cmd::Get { args }) if args == vec![Data::bulk_string("foo")])
+        );
+        assert!(matches!(Data::parse_cmd(&mut reader), Ok(Cmd::Ping)));
+        assert!(matches!(
+            Data::parse_cmd(&mut reader),
+            Ok(Cmd::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn request_nested_array_test() {
+        // the arguments of a request are bulk strings only
+        let mut reader = build_reader("*2\r\n$4\r\nECHO\r\n*1\r\n$2\r\nhi\r\n");
+        assert!(matches!(
+            Data::parse_request(&mut reader),
+            Err(Error::ExpectedBulk('*'))
+        ));
+    }
+
+    #[test]
+    fn inline_split_args_test() {
+        let args = split_args(br#"  set  "hello \"world\"\n" 'it\'s' "\x41\x4a\xzz" '' "#).unwrap();
+        let expected: Vec<&[u8]> = vec![b"set", b"hello \"world\"\n", b"it's", b"AJxzz", b""];
+        assert_eq!(args, expected);
+        let args = split_args(br#""\x+1\x-1""#).unwrap();
+        assert_eq!(args, vec![b"x+1x-1".to_vec()]);
+
+        assert_eq!(split_args(b"   "), Some(vec![]));
+        assert_eq!(split_args(br#"set "unbalanced"#), None);
+        assert_eq!(split_args(br#"set "a"b"#), None);
+        assert_eq!(split_args(b"set 'unbalanced"), None);
+    }
 }
@@ -1,35 +1,55 @@
-use super::Data;
+use super::{format_double, Data, RespVersion};
 use crate::error::Result;
-use std::io::{BufWriter, Write};
+use std::io::Write;
 
 impl Data {
-    pub fn write_resp<T: Write>(&self, writer: &mut BufWriter<T>) -> Result<()> {
+    /// Encode the data for a client using the given protocol version,
+    /// RESP3 only types are downgraded to their RESP2 equivalent for RESP2 clients.
+    /// The writer is not flushed, so pipelined responses can be sent at once.
+    pub fn write_resp<T: Write>(&self, writer: &mut T, version: RespVersion) -> Result<()> {
         match self {
             Data::SimpleString(value) => {
                 write!(writer, "+{value}\r\n")?;
-                writer.flush()?;
+                Ok(())
+            }
+            Data::Error(message) => {
+                write!(writer, "-{message}\r\n")?;
+                Ok(())
+            }
+            Data::Integer(value) => {
+                write!(writer, ":{value}\r\n")?;
                 Ok(())
             }
             Data::BulkString(value) => {
-                write!(writer, "${}\r\n{value}\r\n", value.len())?;
-                writer.flush()?;
+                write!(writer, "${}\r\n", value.len())?;
+                writer.write_all(value)?;
+                writer.write_all(b"\r\n")?;
                 Ok(())
             }
-            Data::Array(values) => {
-                write!(writer, "*{}\r\n", values.len())?;
-                values.iter().try_for_each(|item| item.write_resp(writer))?;
-                writer.flush()?;
+            Data::Array(values) => write_aggregate(writer, '*', values, version),
+            Data::NullArray if version == RespVersion::Resp3 => write_null(writer),
+            Data::NullArray => {
+                write!(writer, "*-1\r\n")?;
                 Ok(())
             }
             Data::ConnectionClosed => Ok(()),
-            Data::NullBuilkString => {
+            Data::NullBuilkString | Data::Null if version == RespVersion::Resp3 => {
+                write_null(writer)
+            }
+            Data::NullBuilkString | Data::Null => {
                 write!(writer, "$-1\r\n")?;
-                writer.flush()?;
                 Ok(())
             }
+            Data::Boolean(_)
+            | Data::Double(_)
+            | Data::BigNumber(_)
+            | Data::VerbatimString(_, _)
+            | Data::Map(_)
+            | Data::Set(_)
+            | Data::Push(_)
+            | Data::Attribute(_, _) => write_resp3_type(self, writer, version),
             Data::FullResyncBinaryConent(response, data) => {
-                response.write_resp(writer)?;
-                writer.flush()?;
+                response.write_resp(writer, version)?;
                 write!(writer, "${}\r\n", data.len())?;
                 writer.write_all(data)?;
                 Ok(())
@@ -37,3 +57,96 @@ impl Data {
         }
     }
 }
+
+/// Types introduced by RESP3, downgraded to the closest RESP2 type for RESP2 clients
+fn write_resp3_type<T: Write>(data: &Data, writer: &mut T, version: RespVersion) -> Result<()> {
+    match data {
+        Data::Boolean(value) => match version {
+            RespVersion::Resp3 => {
+                write!(writer, "#{}\r\n", if *value { 't' } else { 'f' })?;
+                Ok(())
+            }
+            RespVersion::Resp2 => Data::Integer(i64::from(*value)).write_resp(writer, version),
+        },
+        Data::Double(value) => match version {
+            RespVersion::Resp3 => {
+                write!(writer, ",{}\r\n", format_double(*value))?;
+                Ok(())
+            }
+            RespVersion::Resp2 => {
+                Data::bulk_string(format_double(*value)).write_resp(writer, version)
+            }
+        },
+        Data::BigNumber(value) => match version {
+            RespVersion::Resp3 => {
+                write!(writer, "({value}\r\n")?;
+                Ok(())
+            }
+            RespVersion::Resp2 => Data::bulk_string(value.as_str()).write_resp(writer, version),
+        },
+        Data::VerbatimString(format, value) => match version {
+            RespVersion::Resp3 => {
+                write!(writer, "={}\r\n{format}:", value.len() + 4)?;
+                writer.write_all(value)?;
+                writer.write_all(b"\r\n")?;
+                Ok(())
+            }
+            RespVersion::Resp2 => Data::BulkString(value.clone()).write_resp(writer, version),
+        },
+        Data::Map(entries) => match version {
+            RespVersion::Resp3 => {
+                write!(writer, "%{}\r\n", entries.len())?;
+                write_pairs(writer, entries, version)
+            }
+            RespVersion::Resp2 => {
+                write!(writer, "*{}\r\n", entries.len() * 2)?;
+                write_pairs(writer, entries, version)
+            }
+        },
+        Data::Set(values) => write_aggregate(writer, '~', values, version),
+        Data::Push(values) => write_aggregate(writer, '>', values, version),
+        Data::Attribute(attributes, data) => {
+            if version == RespVersion::Resp3 {
+                write!(writer, "|{}\r\n", attributes.len())?;
+                write_pairs(writer, attributes, version)?;
+            }
+            data.write_resp(writer, version)
+        }
+        other => other.write_resp(writer, version),
+    }
+}
+
+/// Aggregate types are all written as arrays on RESP2
+fn write_aggregate<T: Write>(
+    writer: &mut T,
+    resp3_type: char,
+    values: &[Data],
+    version: RespVersion,
+) -> Result<()> {
+    let marker = match version {
+        RespVersion::Resp3 => resp3_type,
+        RespVersion::Resp2 => '*',
+    };
+    write!(writer, "{marker}{}\r\n", values.len())?;
+    values
+        .iter()
+        .try_for_each(|item| item.write_resp(writer, version))?;
+    Ok(())
+}
+
+fn write_pairs<T: Write>(
+    writer: &mut T,
+    entries: &[(Data, Data)],
+    version: RespVersion,
+) -> Result<()> {
+    entries.iter().try_for_each(|(key, value)| {
+        key.write_resp(writer, version)?;
+        value.write_resp(writer, version)
+    })?;
+    Ok(())
+}
+
+fn write_null<T: Write>(writer: &mut T) -> Result<()> {
+    write!(writer, "_\r\n")?;
+    Ok(())
+}
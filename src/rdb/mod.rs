@@ -1,4 +1,4 @@
-use parser::read_string;
+use parser::{read_bytes, read_key};
 
 use crate::{
     error::{Error, Result},
@@ -168,9 +168,9 @@ fn load_key_value_without_expire<R: Read>(
     reader: &mut R,
     in_memory_db: &storage::Db,
 ) -> Result<()> {
-    let key = read_string(reader)?;
-    let value = read_string(reader)?;
-    tracing::debug!("rdb load {key}={value}");
+    let key = read_key(reader)?;
+    let value = read_bytes(reader)?;
+    tracing::debug!("rdb load {key}={value:?}");
     in_memory_db.set(&key, &value, None);
     Ok(())
 }
@@ -184,7 +184,7 @@ fn load_key_value_expire<R: Read>(
         OP_CODEC_EXPIRE_SEC_0XFD => {
             let mut n32: [u8; 4] = [0x0; 4];
             reader.read_exact(&mut n32)?;
-            Ok(u64::from(u32::from_le_bytes(n32) * 1000))
+            Ok(u64::from(u32::from_le_bytes(n32)) * 1000)
         }
         OP_CODEC_EXPIRE_MS_0XFC => {
             let mut n64: [u8; 8] = [0x0; 8];
@@ -206,10 +206,10 @@ fn load_key_value_expire<R: Read>(
         )));
     }
 
-    let key = read_string(reader)?;
-    let value = read_string(reader)?;
+    let key = read_key(reader)?;
+    let value = read_bytes(reader)?;
     let expiration = Some(u64_to_instant(expiration_ms));
-    tracing::debug!("rdb load {key}={value} {expiration:?}");
+    tracing::debug!("rdb load {key}={value:?} {expiration:?}");
     in_memory_db.set(&key, &value, expiration);
     Ok(())
 }
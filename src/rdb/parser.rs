@@ -5,26 +5,138 @@ pub fn read_string<R>(reader: &mut R) -> Result<String>
 where
     R: Read + ?Sized,
 {
-    let (size, is_string) = read_lenth_encoding(reader)?;
-    let mut s: Vec<u8> = vec![0x0; size];
-    reader.read_exact(&mut s)?;
+    let s = String::from_utf8(read_bytes(reader)?)?;
+    tracing::debug!("to read_string {} bytes ({s})", s.len());
+    Ok(s)
+}
+
+/// Read a key, unlike the values the keys must be valid UTF-8 as the keyspace doesn't support
+/// binary keys
+pub fn read_key<R>(reader: &mut R) -> Result<String>
+where
+    R: Read + ?Sized,
+{
+    String::from_utf8(read_bytes(reader)?).map_err(|e| {
+        Error::InvalidRdb(format!(
+            "binary keys are not supported, the key {:?} is not valid UTF-8",
+            e.as_bytes()
+        ))
+    })
+}
 
+/// Read a string encoded value as raw bytes, strings are binary safe.
+/// as described here <https://rdb.fnordig.de/file_format.html#string-encoding>
+pub fn read_bytes<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: Read + ?Sized,
+{
+    let (size, is_string) = read_lenth_encoding(reader)?;
     if is_string {
-        let s = String::from_utf8(s)?;
-        tracing::debug!("to read_string {size} bytes ({s})");
-        Ok(s)
-    } else if size == 1 {
-        // It's a 8 bit integer
-        Ok(s[0].to_string()) // It's an integers as String encoded
-    } else if size == 4 {
-        // It's a 32 bit integer
-        let num = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
-        Ok(num.to_string()) // It's an integers as String encoded
-    } else {
-        Err(Error::Unsupported(format!(
-            "Unsupporte integer with size {size}"
-        )))
+        let mut s: Vec<u8> = vec![0x0; string_len(size)?];
+        reader.read_exact(&mut s)?;
+        return Ok(s);
+    }
+
+    // Integers are signed and encoded as little-endian
+    match size {
+        1 => {
+            let mut n = [0x0; 1];
+            reader.read_exact(&mut n)?;
+            Ok(i8::from_le_bytes(n).to_string().into_bytes())
+        }
+        2 => {
+            let mut n = [0x0; 2];
+            reader.read_exact(&mut n)?;
+            Ok(i16::from_le_bytes(n).to_string().into_bytes())
+        }
+        4 => {
+            let mut n = [0x0; 4];
+            reader.read_exact(&mut n)?;
+            Ok(i32::from_le_bytes(n).to_string().into_bytes())
+        }
+        LZF_COMPRESSED => read_lzf_string(reader),
+        other => Err(Error::Unsupported(format!(
+            "Unsupporte integer with size {other}"
+        ))),
+    }
+}
+
+/// Marker returned by `read_lenth_encoding` for LZF compressed strings.
+const LZF_COMPRESSED: usize = 0;
+
+/// Max length of a string, same as the default `proto-max-bulk-len` in Redis
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// The lengths are read from the file, they are checked before allocating the strings
+fn string_len(len: usize) -> Result<usize> {
+    if len > MAX_STRING_LEN {
+        return Err(Error::InvalidRdb(format!(
+            "String length {len} exceeds the maximum of {MAX_STRING_LEN} bytes"
+        )));
+    }
+    Ok(len)
+}
+
+/// Compressed strings are encoded as follow:
+/// <compressed len><uncompressed len><lzf compressed data>
+fn read_lzf_string<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: Read + ?Sized,
+{
+    let (compressed_len, _) = read_lenth_encoding(reader)?;
+    let (len, _) = read_lenth_encoding(reader)?;
+    let mut compressed: Vec<u8> = vec![0x0; string_len(compressed_len)?];
+    reader.read_exact(&mut compressed)?;
+    lzf_decompress(&compressed, len)
+}
+
+/// Decompress the LZF format used by Redis (liblzf).
+/// Each chunk starts with a control byte:
+///  - `000LLLLL`: a literal run of L+1 bytes follows
+///  - `LLLooooo oooooooo`: a back reference of L+2 bytes (L=7 means an extra length byte follows)
+///
+/// The output must be exactly `len` bytes, the decompression stops as soon as it would be longer.
+fn lzf_decompress(input: &[u8], len: usize) -> Result<Vec<u8>> {
+    let invalid = || Error::InvalidRdb("Invalid lzf compressed string".to_string());
+    let mut output: Vec<u8> = Vec::with_capacity(string_len(len)?);
+    let mut i = 0;
+
+    while i < input.len() {
+        let ctrl = usize::from(input[i]);
+        i += 1;
+
+        if ctrl < 32 {
+            let run = ctrl + 1;
+            let literal = input.get(i..i + run).ok_or_else(invalid)?;
+            if output.len() + run > len {
+                return Err(invalid());
+            }
+            output.extend_from_slice(literal);
+            i += run;
+        } else {
+            let mut run = ctrl >> 5;
+            if run == 7 {
+                run += usize::from(*input.get(i).ok_or_else(invalid)?);
+                i += 1;
+            }
+            let offset = ((ctrl & 0x1f) << 8) + usize::from(*input.get(i).ok_or_else(invalid)?) + 1;
+            i += 1;
+
+            let start = output.len().checked_sub(offset).ok_or_else(invalid)?;
+            if output.len() + run + 2 > len {
+                return Err(invalid());
+            }
+            // the reference can overlap with the bytes being written
+            for pos in start..start + run + 2 {
+                output.push(output[pos]);
+            }
+        }
     }
+
+    if output.len() != len {
+        return Err(invalid());
+    }
+    Ok(output)
 }
 
 /// Read a size encoding
@@ -63,8 +175,8 @@ where
 
         reader.read_exact(&mut byte)?;
         let size = (remaining_6_bits) | u16::from(byte[0]);
-        println!("size: {size:016b}");
-        return Ok((size as usize, true));
+        tracing::debug!("size: {size:016b}");
+        Ok((size as usize, true))
 
         // If the first two bits are 0b10:
         // Ignore the remaining 6 bits of the first byte.
@@ -106,6 +218,7 @@ where
             } // 0 indicates that an 8 bit integer follows
             0b00_00_00_01 => Ok((2, false)), // 16 bit integer
             0b00_00_00_10 => Ok((4, false)), //32 bit integer
+            0b00_00_00_11 => Ok((LZF_COMPRESSED, false)), // LZF compressed string
             _ => Err(Error::InvalidResp),
         }
     } else {
@@ -141,3 +254,42 @@ where
 //         BufReader::new(cursor)
 //     }
 // }
+
+#[cfg(test)]
+mod lzf_tests {
+    use super::lzf_decompress;
+
+    #[test]
+    fn test_lzf_back_reference() {
+        // literal "abc" followed by a 6 bytes back reference with offset 3
+        let compressed = [0x02, b'a', b'b', b'c', 0x80, 0x02];
+        let result = lzf_decompress(&compressed, 9).unwrap();
+        assert_eq!(result, b"abcabcabc");
+    }
+
+    #[test]
+    fn test_lzf_invalid_length() {
+        assert!(lzf_decompress(&[0x02, b'a', b'b', b'c'], 4).is_err());
+        // the output can't exceed the declared length
+        assert!(lzf_decompress(&[0x02, b'a', b'b', b'c', 0x80, 0x02], 4).is_err());
+        // nor the max string length, which is checked before allocating
+        assert!(lzf_decompress(&[0x02, b'a', b'b', b'c'], usize::MAX).is_err());
+    }
+}
+
+#[cfg(test)]
+mod key_tests {
+    use super::read_key;
+    use crate::error::Error;
+
+    #[test]
+    fn test_read_binary_key() {
+        assert_eq!(read_key(&mut &b"\x03foo"[..]).unwrap(), "foo");
+        // the keys are kept as strings, a key that is not UTF-8 fails the load
+        let error = read_key(&mut &b"\x02\xff\xfe"[..]).unwrap_err();
+        assert!(
+            matches!(&error, Error::InvalidRdb(message) if message.contains("binary keys")),
+            "{error:?}"
+        );
+    }
+}
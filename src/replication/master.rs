@@ -1,59 +1,67 @@
-use std::{
-    io::{BufWriter, Write},
-    sync::{mpsc::channel, Arc, Mutex},
-};
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::{net::TcpStream, sync::mpsc::unbounded_channel};
+use tokio_util::codec::Framed;
 
 use crate::{
     error::Result,
-    protocol::{Cmd, Data},
-    storage::Db,
+    protocol::{Cmd, Data, RespCodec, RespVersion},
+    storage::{Db, Expired},
 };
 
-/// If the command send to the client is a `FullResync` (end of the handshake)
-/// it means the client is actualy a Redis slave! we register the slave and start the sync loop.
-pub fn register_slave<W: Send + Write + 'static>(
-    response: &Data,
-    writer: &Arc<Mutex<BufWriter<W>>>,
-    state: &Arc<Db>,
-) {
-    if !matches!(response, Data::FullResyncBinaryConent(_, _)) {
-        return;
-    }
-
-    let writer = Arc::clone(writer);
-    let state = Arc::clone(state);
-    std::thread::spawn(move || {
-        broadcast_to_slave_loop(&writer, &state).unwrap();
-    });
-}
-
-// Used to broadcast master commands to the slaves
-fn broadcast_to_slave_loop<W: Send + Write + 'static>(
-    writer: &Arc<Mutex<BufWriter<W>>>,
-    state: &Arc<Db>,
-) -> Result<()> {
+/// Once the `FullResync` has been sent (end of the handshake) the client is actualy a Redis slave!
+/// we register the slave and start the sync loop on its connection.
+pub async fn serve_slave(mut framed: Framed<TcpStream, RespCodec>, state: &Arc<Db>) -> Result<()> {
     tracing::debug!("Starting master to slave sync...");
-    let (tx, rx) = channel::<Cmd>();
+    framed.codec_mut().resp_version = RespVersion::Resp2;
+    let (tx, mut rx) = unbounded_channel::<Cmd>();
 
     state.register_slave(tx);
 
-    for cmd in rx {
-        tracing::debug!("new cmd to broadcast {cmd:?}");
-        let data = cmd.to_data()?;
-
-        let mut writer = writer.lock().unwrap();
-        data.write_resp(&mut writer)?;
+    loop {
+        tokio::select! {
+            cmd = rx.recv() => {
+                let Some(cmd) = cmd else {
+                    return Ok(());
+                };
+                tracing::debug!("new cmd to broadcast {cmd:?}");
+                framed.send(cmd.to_data()?).await?;
+            }
+            request = framed.next() => {
+                // the slave only sends acks (REPLCONF ACK <offset>), nothing to reply
+                let Some(request) = request else {
+                    tracing::debug!("slave disconnected");
+                    return Ok(());
+                };
+                tracing::debug!("slave request {:?}", request?);
+            }
+        }
     }
-
-    Ok(())
 }
 
 pub fn broadcast_cmd(cmd: &Cmd, state: &Arc<Db>) {
-    let slaves = state.connected_slaves.lock().unwrap();
-    for sender in slaves.iter() {
+    let mut slaves = state.connected_slaves.lock().unwrap();
+    // disconnected slaves are removed
+    slaves.retain(|sender| {
         tracing::debug!("broacasting cmd {cmd:?}");
         if let Err(e) = sender.send(cmd.clone()) {
             tracing::warn!("unabel to send {e}");
-        };
+            return false;
+        }
+        true
+    });
+}
+
+/// Propagate the expired keys and hash fields as DEL and HDEL, the slaves don't expire them
+pub fn broadcast_expired(expired: Expired, state: &Arc<Db>) {
+    if !expired.keys.is_empty() {
+        tracing::debug!("{} keys expired", expired.keys.len());
+        let args = expired.keys.into_iter().map(Data::bulk_string).collect();
+        broadcast_cmd(&Cmd::Del { args }, state);
+    }
+    for (key, fields) in expired.fields {
+        let mut args = vec![Data::bulk_string(key)];
+        args.extend(fields.into_iter().map(Data::BulkString));
+        broadcast_cmd(&Cmd::Hdel { args }, state);
     }
 }
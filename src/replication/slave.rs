@@ -7,7 +7,7 @@ use std::{
 use crate::{
     cmds,
     error::{Error, Result},
-    protocol::{self, Cmd, Data},
+    protocol::{self, Cmd, Data, RespVersion},
     rdb::{self},
     storage::{Config, Db},
 };
@@ -53,17 +53,17 @@ where
     W: Write,
 {
     tracing::debug!("Starting slave loop...");
+    let mut session = cmds::Session::new(state);
     loop {
-        match Data::parse_cmd(reader) {
-            Err(err) => println!("Unable to parse cmd: {err:?}"),
-            Ok(cmd) => {
-                tracing::debug!("cmd from master: {cmd:?}");
-                let response = cmds::execute(cmd, state)?;
-                //tracing::debug!("Response: {response:?}");
-                if matches!(response, Data::ConnectionClosed) {
-                    tracing::debug!("Client disconnected");
-                }
-            }
+        // the stream can't be resynchronized after a decode error, the replication stops
+        let cmd = Data::parse_cmd(reader)?;
+        if matches!(cmd, Cmd::ConnectionClosed) {
+            tracing::warn!("Master closed the replication connection");
+            return Ok(());
+        }
+        tracing::debug!("cmd from master: {cmd:?}");
+        if let Err(err) = cmds::execute(cmd, state, &mut session) {
+            tracing::warn!("Error executing cmd from master: {err:?}");
         }
     }
 }
@@ -79,15 +79,13 @@ where
 {
     tracing::debug_span!("handshake2/2").in_scope(|| {
         let psync = Cmd::Psync {
-            args: vec![
-                Data::BulkString("?".to_string()),
-                Data::BulkString("-1".to_string()),
-            ],
+            args: vec![Data::bulk_string("?"), Data::bulk_string("-1")],
         }
         .to_data()?;
         tracing::debug!(">> {psync:?}");
-        psync.write_resp(writer)?;
-        let response = protocol::Data::parse(reader)?;
+        psync.write_resp(writer, RespVersion::Resp2)?;
+        writer.flush()?;
+        let response = read_master_response(reader)?;
         tracing::debug!(">> Handshake step 2/2 - response: {response:?}");
 
         let Data::SimpleString(response) = response else {
@@ -115,40 +113,49 @@ where
     tracing::debug_span!("handshake1/1").in_scope(|| {
         let ping = Cmd::Ping.to_data()?;
         tracing::debug!("{ping:?}");
-        ping.write_resp(writer)?;
+        ping.write_resp(writer, RespVersion::Resp2)?;
+        writer.flush()?;
 
-        let response = protocol::Data::parse(reader)?;
+        let response = read_master_response(reader)?;
         tracing::debug!("<< Handshake step 1/2 - ping response: {response:?}");
 
         let replconf_listening_port = Cmd::Replconf {
             args: vec![
-                Data::BulkString("listening-port".to_string()),
-                Data::BulkString(config.port.to_string()),
+                Data::bulk_string("listening-port"),
+                Data::bulk_string(config.port.to_string()),
             ],
         }
         .to_data()?;
         tracing::debug!("{replconf_listening_port:?}");
-        replconf_listening_port.write_resp(writer)?;
-        let response = protocol::Data::parse(reader)?;
+        replconf_listening_port.write_resp(writer, RespVersion::Resp2)?;
+        writer.flush()?;
+        let response = read_master_response(reader)?;
         tracing::debug!("<< Handshake step 1/2 replconf listening response: {response:?}");
 
         let replconf_capa = Cmd::Replconf {
-            args: vec![
-                Data::BulkString("capa".to_string()),
-                Data::BulkString("psync2".to_string()),
-            ],
+            args: vec![Data::bulk_string("capa"), Data::bulk_string("psync2")],
         }
         .to_data()?;
         tracing::debug!("{replconf_capa:?}");
-        replconf_capa.write_resp(writer)?;
+        replconf_capa.write_resp(writer, RespVersion::Resp2)?;
         writer.flush()?;
-        let response = protocol::Data::parse(reader)?;
+        let response = read_master_response(reader)?;
         tracing::debug!("<< Handshake step 1/2 replconf capa response: {response:?}");
 
         Ok(())
     })
 }
 
+/// Read a reply from the master, error replies abort the handshake
+fn read_master_response<R: Read>(reader: &mut BufReader<R>) -> Result<Data> {
+    match protocol::Data::parse(reader)? {
+        Data::Error(message) => Err(Error::Replication(format!(
+            "master replied with error: {message}"
+        ))),
+        response => Ok(response),
+    }
+}
+
 fn load_db_from_request<R>(reader: &mut BufReader<R>, state: &Arc<Db>) -> Result<()>
 where
     R: Read,
@@ -0,0 +1,415 @@
+use super::keyspace::{Keyspace, Value};
+use super::{clamp_range, Db};
+use crate::error::Result;
+
+/// Unit of the ranges of BITCOUNT and BITPOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+/// Operation between strings of BITOP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// How BITFIELD SET and INCRBY handle a value that doesn't fit in the field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wrap around, both for signed and unsigned fields
+    #[default]
+    Wrap,
+    /// Saturate to the minimum or maximum value of the field
+    Sat,
+    /// Nothing is written and the operation replies nil
+    Fail,
+}
+
+/// A field of BITFIELD: up to 64 bits for signed integers and 63 for unsigned ones, at any bit
+/// offset of the string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub signed: bool,
+    pub bits: u32,
+    pub offset: usize,
+}
+
+/// A single operation of BITFIELD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get(BitField),
+    Set(BitField, i64, Overflow),
+    Incrby(BitField, i64, Overflow),
+}
+
+fn string<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a Vec<u8>>> {
+    data.get(key).map(Value::as_string).transpose()
+}
+
+/// String stored at key, an empty string is created if the key doesn't exist
+fn string_or_create<'a>(data: &'a mut Keyspace, key: &str) -> Result<&'a mut Vec<u8>> {
+    if data.get(key).is_none() {
+        data.insert(key.to_string(), Value::Data { data: Vec::new() }, None);
+    }
+    data.get_mut(key)
+        .expect("the key was just created")
+        .as_string_mut()
+}
+
+/// Bits are numbered from the most significant bit of the first byte, the string is padded with
+/// zeros after its end
+fn bit(value: &[u8], offset: usize) -> bool {
+    value
+        .get(offset / 8)
+        .is_some_and(|byte| byte & (0x80 >> (offset % 8)) != 0)
+}
+
+fn set_bit(value: &mut Vec<u8>, offset: usize, on: bool) {
+    if value.len() <= offset / 8 {
+        value.resize(offset / 8 + 1, 0);
+    }
+    let mask = 0x80 >> (offset % 8);
+    if on {
+        value[offset / 8] |= mask;
+    } else {
+        value[offset / 8] &= !mask;
+    }
+}
+
+/// Inclusive range of bits of a string of `len` bytes, from a range in bytes or in bits
+fn bit_range(len: usize, start: i64, end: i64, unit: BitUnit) -> Option<(usize, usize)> {
+    match unit {
+        BitUnit::Byte => clamp_range(len, start, end).map(|(start, end)| (start * 8, end * 8 + 7)),
+        BitUnit::Bit => clamp_range(len * 8, start, end),
+    }
+}
+
+/// Number of bits set between the inclusive bit offsets
+fn count_ones(value: &[u8], first: usize, last: usize) -> usize {
+    let bytes = &value[first / 8..=last / 8];
+    let count: usize = bytes.iter().map(|byte| byte.count_ones() as usize).sum();
+    // the bits of the first and last bytes outside of the range
+    let before = bytes[0] & !(u8::MAX >> (first % 8));
+    let after = if last % 8 == 7 {
+        0
+    } else {
+        bytes[bytes.len() - 1] & (u8::MAX >> (last % 8 + 1))
+    };
+    count - before.count_ones() as usize - after.count_ones() as usize
+}
+
+/// The value of the field, sign extended for signed fields
+fn field_value(value: &[u8], field: BitField) -> i128 {
+    let raw = (0..field.bits).fold(0i128, |raw, i| {
+        (raw << 1) | i128::from(bit(value, field.offset + i as usize))
+    });
+    if field.signed && raw >> (field.bits - 1) == 1 {
+        raw - (1 << field.bits)
+    } else {
+        raw
+    }
+}
+
+/// Write the low bits of the two's complement value in the field
+fn set_field(value: &mut Vec<u8>, field: BitField, field_value: i128) {
+    for i in 0..field.bits {
+        let on = (field_value >> (field.bits - 1 - i)) & 1 == 1;
+        set_bit(value, field.offset + i as usize, on);
+    }
+}
+
+/// The value to store in the field, None if it overflows with [`Overflow::Fail`]
+fn handle_overflow(field: BitField, value: i128, overflow: Overflow) -> Option<i128> {
+    let (min, max) = if field.signed {
+        (-(1 << (field.bits - 1)), (1 << (field.bits - 1)) - 1)
+    } else {
+        (0, (1 << field.bits) - 1)
+    };
+    if (min..=max).contains(&value) {
+        return Some(value);
+    }
+    match overflow {
+        Overflow::Wrap => Some((value - min).rem_euclid(max - min + 1) + min),
+        Overflow::Sat => Some(value.clamp(min, max)),
+        Overflow::Fail => None,
+    }
+}
+
+fn to_i64(value: i128) -> i64 {
+    i64::try_from(value).expect("a field holds at most 64 bits")
+}
+
+impl Db {
+    /// Set or clear the bit, the string is extended with zeros if needed.
+    /// Returns the previous value of the bit.
+    pub fn setbit(&self, key: &str, offset: usize, on: bool) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        let value = string_or_create(&mut data, key)?;
+        let previous = bit(value, offset);
+        set_bit(value, offset, on);
+        Ok(previous)
+    }
+
+    pub fn getbit(&self, key: &str, offset: usize) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        Ok(string(&mut data, key)?.is_some_and(|value| bit(value, offset)))
+    }
+
+    /// Number of bits set in the whole string or in the range
+    pub fn bitcount(&self, key: &str, range: Option<(i64, i64, BitUnit)>) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some(value) = string(&mut data, key)? else {
+            return Ok(0);
+        };
+        let range = match range {
+            Some((start, end, unit)) => bit_range(value.len(), start, end, unit),
+            None => clamp_range(value.len() * 8, 0, -1),
+        };
+        Ok(range.map_or(0, |(first, last)| count_ones(value, first, last)))
+    }
+
+    /// Offset of the first bit set or cleared in the range, None if there is none.
+    /// When looking for a clear bit without the end of the range, the string is considered padded
+    /// with zeros: the bit after the end of the string is returned.
+    pub fn bitpos(
+        &self,
+        key: &str,
+        on: bool,
+        start: i64,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Result<Option<usize>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(value) = string(&mut data, key)? else {
+            return Ok((!on).then_some(0));
+        };
+        let Some((first, last)) = bit_range(value.len(), start, end.unwrap_or(-1), unit) else {
+            return Ok(None);
+        };
+
+        // the bytes without the bit looked for are skipped as a whole
+        let skipped = if on { 0 } else { u8::MAX };
+        let mut offset = first;
+        while offset <= last {
+            if offset % 8 == 0 && offset + 7 <= last && value[offset / 8] == skipped {
+                offset += 8;
+                continue;
+            }
+            if bit(value, offset) == on {
+                return Ok(Some(offset));
+            }
+            offset += 1;
+        }
+        if !on && end.is_none() {
+            return Ok(Some(last + 1));
+        }
+        Ok(None)
+    }
+
+    /// Store the result of the operation between the strings in the destination, missing keys
+    /// count as empty strings and the shorter strings are padded with zeros.
+    /// An empty result removes the destination. Returns the length of the result.
+    pub fn bitop(&self, op: BitOperation, destination: &str, keys: &[&str]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let mut sources = Vec::with_capacity(keys.len());
+        for key in keys {
+            sources.push(string(&mut data, key)?.cloned().unwrap_or_default());
+        }
+        let len = sources.iter().map(Vec::len).max().unwrap_or(0);
+        let byte = |source: &Vec<u8>, i: usize| source.get(i).copied().unwrap_or(0);
+
+        let result: Vec<u8> = (0..len)
+            .map(|i| {
+                let mut bytes = sources.iter().map(|source| byte(source, i));
+                match op {
+                    BitOperation::And => bytes.fold(u8::MAX, |result, byte| result & byte),
+                    BitOperation::Or => bytes.fold(0, |result, byte| result | byte),
+                    BitOperation::Xor => bytes.fold(0, |result, byte| result ^ byte),
+                    BitOperation::Not => !bytes.next().unwrap_or(0),
+                }
+            })
+            .collect();
+
+        if result.is_empty() {
+            data.remove(destination);
+        } else {
+            data.insert(destination.to_string(), Value::Data { data: result }, None);
+        }
+        Ok(len)
+    }
+
+    /// Apply the operations in order, returns the value of each one: the value read by GET, the
+    /// previous value for SET and the new value for INCRBY, None when a write overflows with
+    /// [`Overflow::Fail`].
+    /// Only GET operations don't create the key.
+    pub fn bitfield(&self, key: &str, ops: &[BitFieldOp]) -> Result<Vec<Option<i64>>> {
+        let mut data = self.data.lock().unwrap();
+        if ops.iter().all(|op| matches!(op, BitFieldOp::Get(_))) {
+            let value = string(&mut data, key)?.map_or(&[][..], Vec::as_slice);
+            return Ok(ops
+                .iter()
+                .map(|op| match op {
+                    BitFieldOp::Get(field) => Some(to_i64(field_value(value, *field))),
+                    _ => None,
+                })
+                .collect());
+        }
+
+        let value = string_or_create(&mut data, key)?;
+        let mut replies = Vec::with_capacity(ops.len());
+        for op in ops {
+            let reply = match *op {
+                BitFieldOp::Get(field) => Some(field_value(value, field)),
+                BitFieldOp::Set(field, new, overflow) => {
+                    let previous = field_value(value, field);
+                    handle_overflow(field, i128::from(new), overflow).map(|new| {
+                        set_field(value, field, new);
+                        previous
+                    })
+                }
+                BitFieldOp::Incrby(field, increment, overflow) => {
+                    let current = field_value(value, field);
+                    handle_overflow(field, current + i128::from(increment), overflow).inspect(
+                        |new| {
+                            set_field(value, field, *new);
+                        },
+                    )
+                }
+            };
+            replies.push(reply.map(to_i64));
+        }
+        // the string is created up to the last field written, even if the writes failed
+        let written_len = ops
+            .iter()
+            .filter_map(|op| match op {
+                BitFieldOp::Get(_) => None,
+                BitFieldOp::Set(field, ..) | BitFieldOp::Incrby(field, ..) => {
+                    Some((field.offset + field.bits as usize - 1) / 8 + 1)
+                }
+            })
+            .max()
+            .unwrap_or(0);
+        if value.len() < written_len {
+            value.resize(written_len, 0);
+        }
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitField, BitFieldOp, BitOperation, BitUnit, Overflow};
+    use crate::storage::{Config, Db};
+
+    #[test]
+    fn bits_test() {
+        let db = Db::new(Config::default());
+        assert!(!db.setbit("bits", 7, true).unwrap());
+        assert!(db.setbit("bits", 7, true).unwrap());
+        db.setbit("bits", 9, true).unwrap();
+        assert_eq!(
+            db.get("bits").unwrap(),
+            Some(vec![0b0000_0001, 0b0100_0000])
+        );
+        assert!(db.getbit("bits", 9).unwrap());
+        assert!(!db.getbit("bits", 100).unwrap());
+
+        assert_eq!(db.bitcount("bits", None).unwrap(), 2);
+        assert_eq!(
+            db.bitcount("bits", Some((1, -1, BitUnit::Byte))).unwrap(),
+            1
+        );
+        assert_eq!(db.bitcount("bits", Some((8, 9, BitUnit::Bit))).unwrap(), 1);
+        assert_eq!(db.bitcount("bits", Some((0, 6, BitUnit::Bit))).unwrap(), 0);
+
+        assert_eq!(
+            db.bitpos("bits", true, 0, None, BitUnit::Byte).unwrap(),
+            Some(7)
+        );
+        assert_eq!(
+            db.bitpos("bits", true, 8, None, BitUnit::Bit).unwrap(),
+            Some(9)
+        );
+        assert_eq!(
+            db.bitpos("bits", false, 7, Some(7), BitUnit::Bit).unwrap(),
+            None
+        );
+        db.set("ones", &[0xff], None);
+        assert_eq!(
+            db.bitpos("ones", false, 0, None, BitUnit::Byte).unwrap(),
+            Some(8)
+        );
+        assert_eq!(
+            db.bitpos("ones", false, 0, Some(0), BitUnit::Byte).unwrap(),
+            None
+        );
+        assert_eq!(
+            db.bitpos("missing", false, 0, None, BitUnit::Byte).unwrap(),
+            Some(0)
+        );
+
+        assert_eq!(
+            db.bitop(BitOperation::And, "and", &["bits", "ones"])
+                .unwrap(),
+            2
+        );
+        assert_eq!(db.get("and").unwrap(), Some(vec![0b0000_0001, 0]));
+        assert_eq!(db.bitop(BitOperation::Not, "not", &["ones"]).unwrap(), 1);
+        assert_eq!(db.get("not").unwrap(), Some(vec![0]));
+        assert_eq!(db.bitop(BitOperation::Or, "and", &["missing"]).unwrap(), 0);
+        assert_eq!(db.get("and").unwrap(), None);
+    }
+
+    #[test]
+    fn bitfield_overflow_test() {
+        let db = Db::new(Config::default());
+        let u8_field = BitField {
+            signed: false,
+            bits: 8,
+            offset: 0,
+        };
+        let i8_field = BitField {
+            signed: true,
+            ..u8_field
+        };
+        let incr = |field, increment, overflow| {
+            db.bitfield("field", &[BitFieldOp::Incrby(field, increment, overflow)])
+                .unwrap()[0]
+        };
+
+        assert_eq!(incr(u8_field, 250, Overflow::Wrap), Some(250));
+        assert_eq!(incr(u8_field, 10, Overflow::Wrap), Some(4));
+        assert_eq!(incr(u8_field, 300, Overflow::Sat), Some(255));
+        assert_eq!(incr(u8_field, 1, Overflow::Fail), None);
+        assert_eq!(incr(i8_field, 0, Overflow::Wrap), Some(-1));
+        assert_eq!(incr(i8_field, -200, Overflow::Sat), Some(-128));
+        assert_eq!(incr(i8_field, -1, Overflow::Wrap), Some(127));
+
+        let ops = [
+            BitFieldOp::Set(u8_field, 1, Overflow::Wrap),
+            BitFieldOp::Get(BitField {
+                bits: 4,
+                offset: 4,
+                ..u8_field
+            }),
+        ];
+        assert_eq!(db.bitfield("field", &ops).unwrap(), [Some(127), Some(1)]);
+
+        // reads don't create the key, writes extend it up to the field even when they fail
+        db.bitfield("missing", &[BitFieldOp::Get(i8_field)])
+            .unwrap();
+        assert_eq!(db.get("missing").unwrap(), None);
+        let far = BitField {
+            offset: 20,
+            ..u8_field
+        };
+        db.bitfield("far", &[BitFieldOp::Set(far, 256, Overflow::Fail)])
+            .unwrap();
+        assert_eq!(db.get("far").unwrap(), Some(vec![0; 4]));
+    }
+}
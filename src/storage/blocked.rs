@@ -0,0 +1,175 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+use super::Db;
+
+/// A client blocked on a key, notified when the key may be ready
+#[derive(Debug)]
+struct Waiter {
+    client_id: u64,
+    notify: Arc<Notify>,
+    /// Whether the client takes the data it is served (BLPOP...) or only reads it (XREAD)
+    consumes: bool,
+}
+
+/// Clients blocked by the blocking commands (BLPOP, XREAD BLOCK...), queued on each of their keys
+/// in the order they blocked.
+///
+/// As in Redis the clients consuming data are served first come first served: when a key is ready
+/// only the first of them is woken. Once served, or when it stops waiting, the client wakes the
+/// next one, which serves itself if the key still has data. A client woken but not served passes
+/// the wakeup on to the next one.
+/// The clients only reading the key don't take anything from the others, they are all woken.
+#[derive(Debug, Default)]
+pub(super) struct BlockedClients {
+    keys: HashMap<String, VecDeque<Waiter>>,
+}
+
+impl BlockedClients {
+    fn consumers<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a Waiter> {
+        self.keys
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter(|waiter| waiter.consumes)
+    }
+
+    fn notify_first(&self, key: &str) {
+        if let Some(waiter) = self.consumers(key).next() {
+            waiter.notify.notify_one();
+        }
+    }
+
+    fn notify_readers(&self, key: &str) {
+        let waiters = self.keys.get(key).into_iter().flatten();
+        for waiter in waiters.filter(|waiter| !waiter.consumes) {
+            waiter.notify.notify_one();
+        }
+    }
+}
+
+impl Db {
+    /// Queue the client on the keys, `notify` is notified when one of them may be ready
+    pub fn block_client(
+        &self,
+        client_id: u64,
+        keys: &[String],
+        notify: &Arc<Notify>,
+        consumes: bool,
+    ) {
+        let mut blocked = self.blocked.lock().unwrap();
+        for key in keys {
+            blocked
+                .keys
+                .entry(key.clone())
+                .or_default()
+                .push_back(Waiter {
+                    client_id,
+                    notify: Arc::clone(notify),
+                    consumes,
+                });
+        }
+    }
+
+    /// Remove the client from the queues of the keys and wake the clients now first in line
+    pub fn unblock_client(&self, client_id: u64, keys: &[String]) {
+        let mut blocked = self.blocked.lock().unwrap();
+        for key in keys {
+            let Some(waiters) = blocked.keys.get_mut(key) else {
+                continue;
+            };
+            waiters.retain(|waiter| waiter.client_id != client_id);
+            if waiters.is_empty() {
+                blocked.keys.remove(key);
+            }
+            // the client may have been woken for this key, the next one gets its chance
+            blocked.notify_first(key);
+        }
+    }
+
+    /// Wake the consumers queued right after the client on the keys, called by a client woken
+    /// but not served so the wakeup isn't lost for the others
+    pub fn pass_wakeup(&self, client_id: u64, keys: &[String]) {
+        let blocked = self.blocked.lock().unwrap();
+        for key in keys {
+            let next = blocked
+                .consumers(key)
+                .skip_while(|waiter| waiter.client_id != client_id)
+                .nth(1);
+            if let Some(waiter) = next {
+                waiter.notify.notify_one();
+            }
+        }
+    }
+
+    /// Whether no other client consuming data blocked on the key before this one
+    pub fn is_first_blocked(&self, client_id: u64, key: &str) -> bool {
+        let blocked = self.blocked.lock().unwrap();
+        let first = blocked.consumers(key).next();
+        first.is_none_or(|waiter| waiter.client_id == client_id)
+    }
+
+    /// Wake the clients reading the key and the first client consuming it, called by the writes
+    /// that can serve a blocked client, including the writes replicated from the master
+    pub(super) fn signal_key_ready(&self, key: &str) {
+        let blocked = self.blocked.lock().unwrap();
+        blocked.notify_readers(key);
+        blocked.notify_first(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::Notify;
+
+    use crate::storage::{Config, Db, ListEnd};
+
+    #[tokio::test]
+    async fn first_blocked_client_is_woken_test() {
+        let db = Db::new(Config::default());
+        let keys = vec!["list".to_string()];
+        let (first, second) = (Arc::new(Notify::new()), Arc::new(Notify::new()));
+        db.block_client(1, &keys, &first, true);
+        db.block_client(2, &keys, &second, true);
+        assert!(db.is_first_blocked(1, "list"));
+        assert!(!db.is_first_blocked(2, "list"));
+
+        db.push("list", &[b"a"], ListEnd::Right).unwrap();
+        first.notified().await;
+
+        // the first client is served, the second one is next in line
+        db.unblock_client(1, &keys);
+        second.notified().await;
+        assert!(db.is_first_blocked(2, "list"));
+        db.unblock_client(2, &keys);
+        assert!(db.is_first_blocked(3, "list"));
+    }
+
+    #[tokio::test]
+    async fn all_readers_are_woken_test() {
+        let db = Db::new(Config::default());
+        let keys = vec!["list".to_string()];
+        let (reader, consumer, next) = (
+            Arc::new(Notify::new()),
+            Arc::new(Notify::new()),
+            Arc::new(Notify::new()),
+        );
+        db.block_client(1, &keys, &reader, false);
+        db.block_client(2, &keys, &consumer, true);
+        db.block_client(3, &keys, &next, true);
+        // the readers don't hold back the consumers
+        assert!(db.is_first_blocked(2, "list"));
+
+        db.push("list", &[b"a"], ListEnd::Right).unwrap();
+        reader.notified().await;
+        consumer.notified().await;
+
+        // the consumer found nothing and passes the wakeup on
+        db.pass_wakeup(2, &keys);
+        next.notified().await;
+    }
+}
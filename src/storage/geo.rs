@@ -0,0 +1,542 @@
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use super::keyspace::{Keyspace, Value};
+use super::sorted_set::SortedSet;
+use super::Db;
+use crate::error::{Error, Result};
+
+// The geospatial indexes of Redis: the locations are members of a sorted set scored by their
+// 52 bits geohash, the interleaved bits of the latitude (even bits) and longitude (odd bits)
+// offsets within the mercator limits, so the members of an area are score ranges.
+// See <https://github.com/redis/redis/blob/unstable/src/geohash.c>
+
+/// Bits of each coordinate in the scores
+const GEO_STEP: u32 = 26;
+const GEO_LAT_MIN: f64 = -85.051_128_78;
+const GEO_LAT_MAX: f64 = 85.051_128_78;
+const GEO_LONG_MIN: f64 = -180.0;
+const GEO_LONG_MAX: f64 = 180.0;
+/// The radius of the earth used by Redis
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+/// Half the circumference of the earth in the mercator projection
+const MERCATOR_MAX: f64 = 20_037_726.37;
+/// The characters of the standard geohash strings
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Distance units of the geospatial commands
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Feet,
+    Miles,
+}
+
+impl GeoUnit {
+    /// Length of the unit in meters
+    pub fn meters(self) -> f64 {
+        match self {
+            Self::Meters => 1.0,
+            Self::Kilometers => 1000.0,
+            Self::Feet => 0.3048,
+            Self::Miles => 1609.34,
+        }
+    }
+}
+
+/// Center of a search
+#[derive(Debug, Clone)]
+pub enum GeoFrom {
+    /// The location of a member of the index
+    Member(Vec<u8>),
+    LonLat(f64, f64),
+}
+
+/// Area of a search around its center, in meters
+#[derive(Debug, Clone, Copy)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+/// Order of the results of a search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoSort {
+    Unsorted,
+    Asc,
+    Desc,
+}
+
+/// Options of GEOSEARCH and GEOSEARCHSTORE
+#[derive(Debug, Clone)]
+pub struct GeoSearch {
+    pub from: GeoFrom,
+    pub shape: GeoShape,
+    pub sort: GeoSort,
+    pub count: Option<usize>,
+    /// Return the first `count` matches found instead of the closest ones
+    pub any: bool,
+}
+
+/// A member found by a search
+#[derive(Debug, Clone)]
+pub struct GeoMatch {
+    pub member: Vec<u8>,
+    /// Distance to the center in meters
+    pub distance: f64,
+    /// The geohash score of the member
+    pub score: f64,
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+/// Whether the coordinates can be indexed
+pub fn valid_lon_lat(longitude: f64, latitude: f64) -> bool {
+    (GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude)
+        && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude)
+}
+
+/// Spread the bits of the value to the even bits
+fn spread(value: u64) -> u64 {
+    let mut x = value;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Gather the even bits of the value, the inverse of `spread`
+fn squash(value: u64) -> u64 {
+    let mut x = value & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    (x | (x >> 16)) & 0x0000_0000_ffff_ffff
+}
+
+fn interleave(latitude: u64, longitude: u64) -> u64 {
+    spread(latitude) | (spread(longitude) << 1)
+}
+
+/// Offset of the value in the range, on `GEO_STEP` bits
+fn offset(value: f64, min: f64, max: f64) -> f64 {
+    (value - min) / (max - min) * f64::from(1u32 << GEO_STEP)
+}
+
+/// The 52 bits geohash of the coordinates, with the latitude within the given limits
+// the offsets are positive and at most 2^26 for valid coordinates, truncated as in Redis
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn encode_with(longitude: f64, latitude: f64, lat_min: f64, lat_max: f64) -> u64 {
+    let lat_offset = offset(latitude, lat_min, lat_max) as u64;
+    let long_offset = offset(longitude, GEO_LONG_MIN, GEO_LONG_MAX) as u64;
+    interleave(lat_offset, long_offset)
+}
+
+/// The geohash score of the coordinates
+// the geohashes have 52 bits, they are exact as f64
+#[allow(clippy::cast_precision_loss)]
+pub fn geohash_score(longitude: f64, latitude: f64) -> f64 {
+    encode_with(longitude, latitude, GEO_LAT_MIN, GEO_LAT_MAX) as f64
+}
+
+/// The coordinates of the center of the cell of the geohash score
+// the scores are integers of 52 bits, the offsets have 26 bits
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn decode_score(score: f64) -> (f64, f64) {
+    let bits = score as u64;
+    let cell = |offset: u64, min: f64, max: f64| {
+        let scale = f64::from(1u32 << GEO_STEP);
+        let low = min + (offset as f64 / scale) * (max - min);
+        let high = min + ((offset + 1) as f64 / scale) * (max - min);
+        low.midpoint(high).clamp(min, max)
+    };
+    (
+        cell(squash(bits >> 1), GEO_LONG_MIN, GEO_LONG_MAX),
+        cell(squash(bits), GEO_LAT_MIN, GEO_LAT_MAX),
+    )
+}
+
+/// The standard 11 characters geohash string of the score, whose latitudes are within ±90
+fn geohash_string(score: f64) -> String {
+    let (longitude, latitude) = decode_score(score);
+    let bits = encode_with(longitude, latitude, -90.0, 90.0);
+    (0..11)
+        .map(|i| {
+            // there are only 52 bits, the last character is 0 as in Redis
+            let index = if i == 10 {
+                0
+            } else {
+                (bits >> (52 - (i + 1) * 5)) & 0x1f
+            };
+            char::from(GEOHASH_ALPHABET[usize::try_from(index).unwrap_or_default()])
+        })
+        .collect()
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (lat2.to_radians() - lat1.to_radians()).abs()
+}
+
+/// The haversine distance in meters between the coordinates
+fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    // the same longitudes, only the latitudes matter
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let a = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+impl GeoShape {
+    /// The distance from the center to a location within the shape
+    fn distance_within(self, center: (f64, f64), longitude: f64, latitude: f64) -> Option<f64> {
+        let (center_lon, center_lat) = center;
+        match self {
+            Self::Radius(radius) => {
+                Some(distance(center_lon, center_lat, longitude, latitude)).filter(|d| *d <= radius)
+            }
+            Self::Box { width, height } => {
+                if lat_distance(latitude, center_lat) > height / 2.0
+                    || distance(longitude, latitude, center_lon, latitude) > width / 2.0
+                {
+                    return None;
+                }
+                Some(distance(center_lon, center_lat, longitude, latitude))
+            }
+        }
+    }
+
+    /// Half the width and height of the shape
+    fn half_extent(self) -> (f64, f64) {
+        match self {
+            Self::Radius(radius) => (radius, radius),
+            Self::Box { width, height } => (width / 2.0, height / 2.0),
+        }
+    }
+}
+
+/// Precision of the cells of about the size of the radius, as Redis estimates it
+fn estimate_step(radius: f64, latitude: f64) -> u32 {
+    if radius == 0.0 {
+        return GEO_STEP;
+    }
+    let (mut radius, mut step) = (radius, 1i32);
+    while radius < MERCATOR_MAX {
+        radius *= 2.0;
+        step += 1;
+    }
+    // make sure the range is included in most of the cases
+    step -= 2;
+    // the cells are narrower near the poles
+    if latitude.abs() > 66.0 {
+        step -= 1;
+        if latitude.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+    step.clamp(1, 26).unsigned_abs()
+}
+
+/// Index of the cell of the value at the precision, the longitudes are not wrapped
+// the offsets are within ±2^27 for the longitudes of the bounding boxes and latitudes clamped
+#[allow(clippy::cast_possible_truncation)]
+fn cell_index(value: f64, min: f64, max: f64, step: u32) -> i64 {
+    (offset(value, min, max).floor() as i64) >> (GEO_STEP - step)
+}
+
+/// The score ranges of the cells covering the bounding box of the shape around the center.
+/// The precision is lowered until at most 3 cells cover each side of the box.
+fn covering_ranges(center: (f64, f64), shape: GeoShape) -> Vec<(u64, u64)> {
+    let (longitude, latitude) = center;
+    let (half_width, half_height) = shape.half_extent();
+    let lat_delta = (half_height / EARTH_RADIUS_IN_METERS).to_degrees();
+    // the box is the widest at the latitude farthest from the equator
+    let farthest = latitude.abs() + lat_delta;
+    let long_delta = (farthest < 90.0)
+        .then(|| (half_width / EARTH_RADIUS_IN_METERS / farthest.to_radians().cos()).to_degrees())
+        .filter(|delta| *delta < 180.0);
+
+    let radius = match shape {
+        GeoShape::Radius(radius) => radius,
+        GeoShape::Box { .. } => half_width.hypot(half_height),
+    };
+    let mut step = estimate_step(radius, latitude);
+    loop {
+        let cells = 1i64 << step;
+        let lat_cells = (
+            cell_index(
+                (latitude - lat_delta).clamp(GEO_LAT_MIN, GEO_LAT_MAX),
+                GEO_LAT_MIN,
+                GEO_LAT_MAX,
+                step,
+            ),
+            cell_index(
+                (latitude + lat_delta).clamp(GEO_LAT_MIN, GEO_LAT_MAX),
+                GEO_LAT_MIN,
+                GEO_LAT_MAX,
+                step,
+            ),
+        );
+        // a box around a pole covers all the longitudes
+        let long_cells = long_delta
+            .map(|delta| {
+                (
+                    cell_index(longitude - delta, GEO_LONG_MIN, GEO_LONG_MAX, step),
+                    cell_index(longitude + delta, GEO_LONG_MIN, GEO_LONG_MAX, step),
+                )
+            })
+            .filter(|(low, high)| high - low < cells)
+            .unwrap_or((0, cells - 1));
+        if step > 1 && (lat_cells.1 - lat_cells.0 > 2 || long_cells.1 - long_cells.0 > 2) {
+            step -= 1;
+            continue;
+        }
+
+        let mut hashes = BTreeSet::new();
+        for lat_cell in lat_cells.0..=lat_cells.1 {
+            for long_cell in long_cells.0..=long_cells.1 {
+                hashes.insert(interleave(
+                    lat_cell.unsigned_abs(),
+                    long_cell.rem_euclid(cells).unsigned_abs(),
+                ));
+            }
+        }
+        let shift = 2 * (GEO_STEP - step);
+        return hashes
+            .into_iter()
+            .map(|hash| (hash << shift, (hash + 1) << shift))
+            .collect();
+    }
+}
+
+fn zset<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a SortedSet>> {
+    data.get(key).map(Value::as_zset).transpose()
+}
+
+/// The members of the sorted set within the shape around the center, sorted and limited as
+/// requested
+// the ranges are geohashes of 52 bits, exact as f64
+#[allow(clippy::cast_precision_loss)]
+fn search(zset: &SortedSet, center: (f64, f64), search: &GeoSearch) -> Vec<GeoMatch> {
+    let limit = search.count.filter(|_| search.any);
+    let mut matches = Vec::new();
+    'ranges: for (low, high) in covering_ranges(center, search.shape) {
+        let range = zset.range_by_score(Bound::Included(low as f64), Bound::Excluded(high as f64));
+        for (member, score) in range {
+            let (longitude, latitude) = decode_score(score);
+            if let Some(distance) = search.shape.distance_within(center, longitude, latitude) {
+                matches.push(GeoMatch {
+                    member: member.clone(),
+                    distance,
+                    score,
+                    longitude,
+                    latitude,
+                });
+                if limit.is_some_and(|limit| matches.len() >= limit) {
+                    break 'ranges;
+                }
+            }
+        }
+    }
+
+    // the closest matches are returned when there is a count
+    let sort = match (search.sort, search.count) {
+        (GeoSort::Unsorted, Some(_)) if !search.any => GeoSort::Asc,
+        (sort, _) => sort,
+    };
+    match sort {
+        GeoSort::Unsorted => {}
+        GeoSort::Asc => matches.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+        GeoSort::Desc => matches.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+    }
+    if let Some(count) = search.count {
+        matches.truncate(count);
+    }
+    matches
+}
+
+/// Search the sorted set at key, None when the key doesn't exist
+fn search_key(
+    data: &mut Keyspace,
+    key: &str,
+    options: &GeoSearch,
+) -> Result<Option<Vec<GeoMatch>>> {
+    let Some(zset) = zset(data, key)? else {
+        return Ok(None);
+    };
+    let center = match &options.from {
+        GeoFrom::Member(member) => {
+            decode_score(zset.score(member).ok_or(Error::UndecodableMember)?)
+        }
+        GeoFrom::LonLat(longitude, latitude) => (*longitude, *latitude),
+    };
+    Ok(Some(search(zset, center, options)))
+}
+
+impl Db {
+    /// The coordinates of the members of the geospatial index, None for the missing members
+    pub fn geopos(&self, key: &str, members: &[&[u8]]) -> Result<Vec<Option<(f64, f64)>>> {
+        let mut data = self.data.lock().unwrap();
+        let zset = zset(&mut data, key)?;
+        Ok(members
+            .iter()
+            .map(|member| zset.and_then(|zset| zset.score(member)).map(decode_score))
+            .collect())
+    }
+
+    /// The standard geohash strings of the members, None for the missing members
+    pub fn geohash(&self, key: &str, members: &[&[u8]]) -> Result<Vec<Option<String>>> {
+        let mut data = self.data.lock().unwrap();
+        let zset = zset(&mut data, key)?;
+        Ok(members
+            .iter()
+            .map(|member| zset.and_then(|zset| zset.score(member)).map(geohash_string))
+            .collect())
+    }
+
+    /// The distance in meters between two members, None if one of them is missing
+    pub fn geodist(&self, key: &str, member1: &[u8], member2: &[u8]) -> Result<Option<f64>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset(&mut data, key)? else {
+            return Ok(None);
+        };
+        let (Some(score1), Some(score2)) = (zset.score(member1), zset.score(member2)) else {
+            return Ok(None);
+        };
+        let ((lon1, lat1), (lon2, lat2)) = (decode_score(score1), decode_score(score2));
+        Ok(Some(distance(lon1, lat1, lon2, lat2)))
+    }
+
+    /// The members of the geospatial index within the shape of the search
+    pub fn geosearch(&self, key: &str, options: &GeoSearch) -> Result<Vec<GeoMatch>> {
+        let mut data = self.data.lock().unwrap();
+        Ok(search_key(&mut data, key, options)?.unwrap_or_default())
+    }
+
+    /// Store at the destination the members found by the search, scored by their geohash or by
+    /// their distance in the given unit. The destination is replaced, or deleted when nothing
+    /// is found. Returns the number of members stored.
+    pub fn geosearchstore(
+        &self,
+        destination: &str,
+        key: &str,
+        options: &GeoSearch,
+        store_dist: Option<GeoUnit>,
+    ) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let matches = search_key(&mut data, key, options)?.unwrap_or_default();
+        let len = matches.len();
+        if matches.is_empty() {
+            data.remove(destination);
+        } else {
+            let mut zset = SortedSet::default();
+            for found in matches {
+                let score = store_dist.map_or(found.score, |unit| found.distance / unit.meters());
+                zset.insert(found.member, score);
+            }
+            data.insert(destination.to_string(), Value::SortedSet { zset }, None);
+            self.signal_key_ready(destination);
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_score, geohash_score, geohash_string, GeoFrom, GeoSearch, GeoShape, GeoSort, GeoUnit,
+    };
+    use crate::storage::{Config, Db, ZaddOptions};
+
+    #[test]
+    // the scores are integers, exact as f64
+    #[allow(clippy::float_cmp)]
+    fn geohash_test() {
+        // the scores and geohashes of the GEOADD example of Redis
+        let palermo = geohash_score(13.361_389, 38.115_556);
+        assert_eq!(palermo, 3_479_099_956_230_698.0);
+        assert_eq!(geohash_string(palermo), "sqc8b49rny0");
+        let catania = geohash_score(15.087_269, 37.502_669);
+        assert_eq!(catania, 3_479_447_370_796_909.0);
+        assert_eq!(geohash_string(catania), "sqdtr74hyu0");
+
+        let (longitude, latitude) = decode_score(palermo);
+        assert!((longitude - 13.361_389).abs() < 1e-5, "{longitude}");
+        assert!((latitude - 38.115_556).abs() < 1e-5, "{latitude}");
+    }
+
+    #[test]
+    fn geosearch_test() {
+        let db = Db::new(Config::default());
+        let locations = [
+            (13.361_389, 38.115_556, "Palermo"),
+            (15.087_269, 37.502_669, "Catania"),
+            (12.758_489, 38.788_135, "edge1"),
+            (17.241_510, 38.788_135, "edge2"),
+        ];
+        let pairs: Vec<(f64, &[u8])> = locations
+            .iter()
+            .map(|(lon, lat, name)| (geohash_score(*lon, *lat), name.as_bytes()))
+            .collect();
+        db.zadd("Sicily", &pairs, ZaddOptions::default()).unwrap();
+
+        let dist = db
+            .geodist("Sicily", b"Palermo", b"Catania")
+            .unwrap()
+            .unwrap();
+        assert!((dist - 166_274.151_6).abs() < 0.01, "{dist}");
+
+        let mut options = GeoSearch {
+            from: GeoFrom::LonLat(15.0, 37.0),
+            shape: GeoShape::Radius(200_000.0),
+            sort: GeoSort::Asc,
+            count: None,
+            any: false,
+        };
+        let names = |options: &GeoSearch| {
+            db.geosearch("Sicily", options)
+                .unwrap()
+                .into_iter()
+                .map(|found| String::from_utf8(found.member).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&options), ["Catania", "Palermo"]);
+
+        options.shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 400_000.0,
+        };
+        options.sort = GeoSort::Desc;
+        assert_eq!(names(&options), ["edge1", "edge2", "Palermo", "Catania"]);
+        options.count = Some(1);
+        assert_eq!(names(&options), ["edge1"]);
+
+        options.from = GeoFrom::Member(b"Palermo".to_vec());
+        options.shape = GeoShape::Radius(100_000.0);
+        options.count = None;
+        assert_eq!(names(&options), ["edge1", "Palermo"]);
+        options.from = GeoFrom::Member(b"missing".to_vec());
+        assert!(db.geosearch("Sicily", &options).is_err());
+        assert!(db.geosearch("missing", &options).unwrap().is_empty());
+
+        options.from = GeoFrom::LonLat(15.0, 37.0);
+        options.shape = GeoShape::Radius(200_000.0);
+        let stored = db
+            .geosearchstore("near", "Sicily", &options, Some(GeoUnit::Kilometers))
+            .unwrap();
+        assert_eq!(stored, 2);
+        let catania = db.zscore("near", b"Catania").unwrap().unwrap();
+        assert!((catania - 56.441_3).abs() < 0.001, "{catania}");
+    }
+}
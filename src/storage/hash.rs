@@ -0,0 +1,353 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::SystemTime;
+
+use super::in_memory::glob_match;
+use super::keyspace::{Keyspace, Value};
+use super::scan::ScanMap;
+use super::{Db, ExpireCondition, ExpireOutcome};
+use crate::error::Result;
+
+/// Fields of a hash and the expirations of the fields with a TTL.
+/// The expirations are also ordered by time, so the earliest is found without a scan.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Hash {
+    fields: ScanMap<Vec<u8>, Vec<u8>>,
+    expires: HashMap<Vec<u8>, SystemTime>,
+    by_expiration: BTreeSet<(SystemTime, Vec<u8>)>,
+}
+
+impl Hash {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Earliest expiration of a field
+    pub fn next_expiration(&self) -> Option<SystemTime> {
+        self.by_expiration
+            .first()
+            .map(|(expiration, _)| *expiration)
+    }
+
+    /// Remove the fields expired at `now`, returns them
+    pub fn expire_fields(&mut self, now: SystemTime) -> Vec<Vec<u8>> {
+        let mut expired = Vec::new();
+        while self
+            .by_expiration
+            .first()
+            .is_some_and(|(expiration, _)| *expiration <= now)
+        {
+            let Some((_, field)) = self.by_expiration.pop_first() else {
+                break;
+            };
+            self.expires.remove(&field);
+            self.fields.remove(&field);
+            expired.push(field);
+        }
+        expired
+    }
+
+    fn set_expiration(&mut self, field: &[u8], expiration: SystemTime) {
+        self.persist(field);
+        self.expires.insert(field.to_vec(), expiration);
+        self.by_expiration.insert((expiration, field.to_vec()));
+    }
+
+    /// Remove the TTL of the field
+    fn persist(&mut self, field: &[u8]) {
+        if let Some(expiration) = self.expires.remove(field) {
+            self.by_expiration.remove(&(expiration, field.to_vec()));
+        }
+    }
+
+    fn remove(&mut self, field: &[u8]) -> bool {
+        self.persist(field);
+        self.fields.remove(field).is_some()
+    }
+}
+
+/// Fields and their values
+pub type HashEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Expiration of a hash field, as reported by HTTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldExpiration {
+    /// The field or the key doesn't exist
+    NoField,
+    Persistent,
+    At(SystemTime),
+}
+
+/// Hash stored at key, None if the key doesn't exist
+fn hash_mut<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut Hash>> {
+    data.get_mut(key).map(Value::as_hash_mut).transpose()
+}
+
+fn hash<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a Hash>> {
+    data.get(key).map(Value::as_hash).transpose()
+}
+
+/// Keep the keyspace consistent once the fields changed: as in Redis the key is removed with
+/// its last field, otherwise the expirations of the fields are indexed again
+fn fields_updated(data: &mut Keyspace, key: &str) -> Result<()> {
+    if hash(data, key)?.is_some_and(Hash::is_empty) {
+        data.remove(key);
+    } else {
+        data.track_field_expiration(key);
+    }
+    Ok(())
+}
+
+/// Hash commands, see <https://redis.io/docs/latest/develop/data-types/hashes/>
+impl Db {
+    /// Set the fields, their TTL is removed. Returns the number of fields added.
+    pub fn hset(&self, key: &str, pairs: &[(&[u8], &[u8])]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        if hash_mut(&mut data, key)?.is_none() {
+            let hash = Hash::default();
+            data.insert(key.to_string(), Value::Hash { hash }, None);
+        }
+        let Some(hash) = hash_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+
+        let mut added = 0;
+        for (field, value) in pairs {
+            hash.persist(field);
+            if hash.fields.insert(field.to_vec(), value.to_vec()).is_none() {
+                added += 1;
+            }
+        }
+        fields_updated(&mut data, key)?;
+        Ok(added)
+    }
+
+    pub fn hget(&self, key: &str, field: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        Ok(hash(&mut data, key)?.and_then(|hash| hash.fields.get(field).cloned()))
+    }
+
+    pub fn hmget(&self, key: &str, fields: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut data = self.data.lock().unwrap();
+        let hash = hash(&mut data, key)?;
+        Ok(fields
+            .iter()
+            .map(|field| hash.and_then(|hash| hash.fields.get(*field).cloned()))
+            .collect())
+    }
+
+    /// Remove the fields, returns the number of fields removed
+    pub fn hdel(&self, key: &str, fields: &[&[u8]]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some(hash) = hash_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+        let removed = fields.iter().filter(|field| hash.remove(field)).count();
+        fields_updated(&mut data, key)?;
+        Ok(removed)
+    }
+
+    /// All the fields and their values
+    pub fn hgetall(&self, key: &str) -> Result<HashEntries> {
+        let mut data = self.data.lock().unwrap();
+        Ok(hash(&mut data, key)?.map_or_else(Vec::new, |hash| {
+            hash.fields
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        }))
+    }
+
+    pub fn hlen(&self, key: &str) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(hash(&mut data, key)?.map_or(0, |hash| hash.fields.len()))
+    }
+
+    pub fn hexists(&self, key: &str, field: &[u8]) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        Ok(hash(&mut data, key)?.is_some_and(|hash| hash.fields.contains_key(field)))
+    }
+
+    /// Atomic read-modify-write of a field, its expiration is kept.
+    /// `update` gets the value and whether the field exists, a missing field is created with the
+    /// updated value unless `update` fails.
+    pub fn update_field<T>(
+        &self,
+        key: &str,
+        field: &[u8],
+        update: impl FnOnce(&mut Vec<u8>, bool) -> Result<T>,
+    ) -> Result<T> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(value) = hash_mut(&mut data, key)?.and_then(|hash| hash.fields.get_mut(field)) {
+            return update(value, true);
+        }
+
+        let mut value = Vec::new();
+        let result = update(&mut value, false)?;
+        if hash_mut(&mut data, key)?.is_none() {
+            let hash = Hash::default();
+            data.insert(key.to_string(), Value::Hash { hash }, None);
+        }
+        if let Some(hash) = hash_mut(&mut data, key)? {
+            hash.fields.insert(field.to_vec(), value);
+        }
+        Ok(result)
+    }
+
+    /// Incremental iteration over the fields, see [`Db::scan`]
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, HashEntries)> {
+        let mut data = self.data.lock().unwrap();
+        let Some(hash) = hash(&mut data, key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let (cursor, entries) = hash.fields.scan(cursor, count);
+        let entries = entries
+            .into_iter()
+            .filter(|(field, _)| pattern.is_none_or(|p| glob_match(p.as_bytes(), field)))
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect();
+        Ok((cursor, entries))
+    }
+
+    /// Set the expiration of the fields whose conditions are met, as [`Db::expire`].
+    /// None is returned for the fields that don't exist.
+    pub fn hexpire(
+        &self,
+        key: &str,
+        fields: &[&[u8]],
+        expiration: SystemTime,
+        conditions: &[ExpireCondition],
+    ) -> Result<Vec<Option<ExpireOutcome>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(hash) = hash_mut(&mut data, key)? else {
+            return Ok(vec![None; fields.len()]);
+        };
+
+        let now = SystemTime::now();
+        let outcomes = fields
+            .iter()
+            .map(|field| {
+                if !hash.fields.contains_key(*field) {
+                    return None;
+                }
+                let current = hash.expires.get(*field).copied();
+                if !conditions
+                    .iter()
+                    .all(|condition| condition.allows(current, expiration))
+                {
+                    return Some(ExpireOutcome::NotSet);
+                }
+                if expiration <= now {
+                    hash.remove(field);
+                    return Some(ExpireOutcome::Deleted);
+                }
+                hash.set_expiration(field, expiration);
+                Some(ExpireOutcome::Set)
+            })
+            .collect();
+        fields_updated(&mut data, key)?;
+        Ok(outcomes)
+    }
+
+    pub fn hexpiration(&self, key: &str, fields: &[&[u8]]) -> Result<Vec<FieldExpiration>> {
+        let mut data = self.data.lock().unwrap();
+        let hash = hash(&mut data, key)?;
+        Ok(fields
+            .iter()
+            .map(|field| field_expiration(hash, field))
+            .collect())
+    }
+
+    /// Remove the expiration of the fields, returns their expirations before the call
+    pub fn hpersist(&self, key: &str, fields: &[&[u8]]) -> Result<Vec<FieldExpiration>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(hash) = hash_mut(&mut data, key)? else {
+            return Ok(vec![FieldExpiration::NoField; fields.len()]);
+        };
+        let expirations = fields
+            .iter()
+            .map(|field| {
+                let expiration = field_expiration(Some(hash), field);
+                hash.persist(field);
+                expiration
+            })
+            .collect();
+        fields_updated(&mut data, key)?;
+        Ok(expirations)
+    }
+}
+
+fn field_expiration(hash: Option<&Hash>, field: &[u8]) -> FieldExpiration {
+    match hash {
+        Some(hash) if hash.fields.contains_key(field) => hash
+            .expires
+            .get(field)
+            .map_or(FieldExpiration::Persistent, |expiration| {
+                FieldExpiration::At(*expiration)
+            }),
+        _ => FieldExpiration::NoField,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::FieldExpiration;
+    use crate::storage::{Config, Db, ExpireCondition, ExpireOutcome};
+
+    #[test]
+    fn field_expiration_test() {
+        let db = Db::new(Config::default());
+        db.hset("h", &[(b"a", b"1"), (b"b", b"2"), (b"c", b"3")])
+            .unwrap();
+
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let future = SystemTime::now() + Duration::from_secs(100);
+        let outcomes = db.hexpire("h", &[b"a", b"x"], future, &[]).unwrap();
+        assert_eq!(outcomes, [Some(ExpireOutcome::Set), None]);
+        let outcomes = db
+            .hexpire("h", &[b"a", b"b"], future, &[ExpireCondition::Nx])
+            .unwrap();
+        assert_eq!(
+            outcomes,
+            [Some(ExpireOutcome::NotSet), Some(ExpireOutcome::Set)]
+        );
+        assert_eq!(
+            db.hexpiration("h", &[b"a", b"c", b"x"]).unwrap(),
+            [
+                FieldExpiration::At(future),
+                FieldExpiration::Persistent,
+                FieldExpiration::NoField
+            ]
+        );
+
+        // setting a field removes its TTL
+        db.hset("h", &[(b"a", b"4")]).unwrap();
+        assert_eq!(
+            db.hpersist("h", &[b"a"]).unwrap(),
+            [FieldExpiration::Persistent]
+        );
+
+        let outcomes = db.hexpire("h", &[b"b"], past, &[]).unwrap();
+        assert_eq!(outcomes, [Some(ExpireOutcome::Deleted)]);
+        assert_eq!(db.hlen("h").unwrap(), 2);
+    }
+
+    #[test]
+    fn hash_removed_with_last_field_test() {
+        let db = Db::new(Config::default());
+        db.hset("h", &[(b"a", b"1")]).unwrap();
+        let soon = SystemTime::now() + Duration::from_millis(10);
+        db.hexpire("h", &[b"a"], soon, &[]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(db.value_type("h"), None);
+        assert_eq!(db.stats().expired_subkeys, 1);
+    }
+}
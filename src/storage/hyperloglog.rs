@@ -0,0 +1,457 @@
+use super::keyspace::{Keyspace, Value};
+use super::Db;
+use crate::error::{Error, Result};
+
+// The HyperLogLog of Redis, stored as a string so it can be read by Redis from the RDB files:
+// a 16 bytes header, "HYLL", the encoding, 3 unused bytes and the cached cardinality, followed by
+// the 2^14 registers of 6 bits, either dense or run length encoded in the sparse encoding.
+// See <https://github.com/redis/redis/blob/unstable/src/hyperloglog.c>
+
+/// Number of bits of the hash used to select the register
+const HLL_P: u32 = 14;
+/// Number of bits of the hash used to count the leading zeros
+const HLL_Q: usize = 64 - HLL_P as usize;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HEADER_LEN: usize = 16;
+const HLL_DENSE_LEN: usize = HLL_HEADER_LEN + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_MAGIC: &[u8] = b"HYLL";
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+/// The sparse encoding is converted to the dense one above this size, as the hll-sparse-max-bytes
+/// default of Redis
+const HLL_SPARSE_MAX_BYTES: usize = 3000;
+/// Largest register value of the VAL opcode of the sparse encoding
+const HLL_SPARSE_VAL_MAX: u8 = 32;
+/// Longest run of the ZERO, XZERO and VAL opcodes of the sparse encoding
+const HLL_SPARSE_ZERO_MAX_LEN: usize = 64;
+const HLL_SPARSE_XZERO_MAX_LEN: usize = HLL_REGISTERS;
+const HLL_SPARSE_VAL_MAX_LEN: usize = 4;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+/// The seed of the hash of the elements used by Redis
+const HLL_HASH_SEED: u64 = 0xadc8_3b19;
+
+/// `MurmurHash2`, 64 bit version by Austin Appleby, as used by Redis to hash the elements.
+/// The blocks are read in little endian whatever the platform.
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let blocks = key.chunks_exact(8);
+    let tail = blocks.remainder();
+    for block in blocks {
+        let mut k = u64::from_le_bytes(block.try_into().expect("blocks of 8 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= u64::from(*byte) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// The register of the element and the length of the run of zeros of its hash, plus one
+fn pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmurhash64a(element, HLL_HASH_SEED);
+    let index = usize::try_from(hash & (HLL_REGISTERS as u64 - 1)).expect("index of a register");
+    // the bit after the Q bits ends the run if all of them are zeros
+    let hash = (hash >> HLL_P) | (1 << HLL_Q);
+    let count = u8::try_from(hash.trailing_zeros() + 1).expect("at most Q + 1 zeros");
+    (index, count)
+}
+
+/// Sigma function of the cardinality estimator of Otmar Ertl, used by Redis
+/// <https://arxiv.org/abs/1702.01284>
+fn sigma(mut x: f64) -> f64 {
+    #[allow(clippy::float_cmp)] // only an exact 1 diverges
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        #[allow(clippy::float_cmp)] // the series is summed until it doesn't change anymore
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+/// Tau function of the cardinality estimator of Otmar Ertl
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || (1.0 - x).abs() < f64::EPSILON {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        #[allow(clippy::float_cmp)] // the series is summed until it doesn't change anymore
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// A decoded hyperloglog
+#[derive(Debug, Clone)]
+struct HyperLogLog {
+    registers: Vec<u8>,
+    dense: bool,
+    /// The cached cardinality in little endian, the most significant bit is set when it's invalid
+    cache: [u8; 8],
+}
+
+impl HyperLogLog {
+    /// New hyperloglogs are sparse
+    fn new() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+            dense: false,
+            cache: [0; 8],
+        }
+    }
+
+    fn decode(value: &[u8]) -> Result<Self> {
+        let (header, payload) = value
+            .split_at_checked(HLL_HEADER_LEN)
+            .ok_or(Error::InvalidHll)?;
+        if &header[..4] != HLL_MAGIC {
+            return Err(Error::InvalidHll);
+        }
+        let cache = header[8..]
+            .try_into()
+            .expect("8 bytes of cached cardinality");
+        let registers = match header[4] {
+            HLL_DENSE if value.len() == HLL_DENSE_LEN => (0..HLL_REGISTERS)
+                .map(|index| dense_register(payload, index))
+                .collect(),
+            HLL_SPARSE => decode_sparse(payload)?,
+            _ => return Err(Error::InvalidHll),
+        };
+        Ok(Self {
+            registers,
+            dense: header[4] == HLL_DENSE,
+            cache,
+        })
+    }
+
+    /// Encode as sparse as long as it's possible, the dense encoding is never converted back
+    fn encode(&mut self) -> Vec<u8> {
+        let sparse = (!self.dense)
+            .then(|| encode_sparse(&self.registers))
+            .flatten();
+        self.dense = sparse.is_none();
+
+        let mut value = Vec::with_capacity(HLL_DENSE_LEN);
+        value.extend_from_slice(HLL_MAGIC);
+        value.extend_from_slice(&[if self.dense { HLL_DENSE } else { HLL_SPARSE }, 0, 0, 0]);
+        value.extend_from_slice(&self.cache);
+        if let Some(sparse) = sparse {
+            value.extend_from_slice(&sparse);
+        } else {
+            value.resize(HLL_DENSE_LEN, 0);
+            let payload = &mut value[HLL_HEADER_LEN..];
+            for (index, register) in self.registers.iter().enumerate() {
+                set_dense_register(payload, index, *register);
+            }
+        }
+        value
+    }
+
+    fn invalidate_cache(&mut self) {
+        self.cache[7] |= 0x80;
+    }
+
+    fn cached_count(&self) -> Option<u64> {
+        (self.cache[7] & 0x80 == 0).then(|| u64::from_le_bytes(self.cache))
+    }
+
+    /// Add the element, returns whether a register was updated
+    fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = pattern(element);
+        if self.registers[index] >= count {
+            return false;
+        }
+        self.registers[index] = count;
+        self.invalidate_cache();
+        true
+    }
+
+    /// Keep the maximum of the registers of both hyperloglogs
+    fn merge(&mut self, other: &Self) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+        self.dense |= other.dense;
+        self.invalidate_cache();
+    }
+
+    /// The cardinality estimated with the estimator of Otmar Ertl, as Redis does
+    fn count(&self) -> u64 {
+        let mut histogram = [0u32; 1 << HLL_BITS];
+        for register in &self.registers {
+            histogram[usize::from(*register)] += 1;
+        }
+        let m = f64::from(u32::try_from(HLL_REGISTERS).expect("2^14 registers"));
+        let mut z = m * tau((m - f64::from(histogram[HLL_Q + 1])) / m);
+        for count in histogram[1..=HLL_Q].iter().rev() {
+            z += f64::from(*count);
+            z *= 0.5;
+        }
+        z += m * sigma(f64::from(histogram[0]) / m);
+        // the estimate is positive and far below 2^64
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let count = (HLL_ALPHA_INF * m * m / z).round() as u64;
+        count
+    }
+}
+
+/// The registers of 6 bits are packed from the least significant bits of each byte
+fn dense_register(payload: &[u8], index: usize) -> u8 {
+    let (byte, bit) = (index * HLL_BITS / 8, index * HLL_BITS % 8);
+    let mut register = payload[byte] >> bit;
+    if bit > 8 - HLL_BITS {
+        register |= payload[byte + 1] << (8 - bit);
+    }
+    register & HLL_REGISTER_MAX
+}
+
+fn set_dense_register(payload: &mut [u8], index: usize, register: u8) {
+    let (byte, bit) = (index * HLL_BITS / 8, index * HLL_BITS % 8);
+    payload[byte] &= !(HLL_REGISTER_MAX << bit);
+    payload[byte] |= register << bit;
+    if bit > 8 - HLL_BITS {
+        payload[byte + 1] &= !(HLL_REGISTER_MAX >> (8 - bit));
+        payload[byte + 1] |= register >> (8 - bit);
+    }
+}
+
+/// The sparse encoding is a sequence of opcodes:
+///  - ZERO `00xxxxxx`: 1 to 64 registers set to 0
+///  - XZERO `01xxxxxx yyyyyyyy`: 1 to 16384 registers set to 0
+///  - VAL `1vvvvvxx`: 1 to 4 registers set to a value from 1 to 32
+fn decode_sparse(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut registers = Vec::with_capacity(HLL_REGISTERS);
+    let mut opcodes = payload.iter();
+    while let Some(opcode) = opcodes.next() {
+        let (len, value) = match opcode >> 6 {
+            0b00 => (usize::from(opcode & 0x3f) + 1, 0),
+            0b01 => {
+                let low = opcodes.next().ok_or(Error::CorruptedHll)?;
+                (
+                    ((usize::from(opcode & 0x3f) << 8) | usize::from(*low)) + 1,
+                    0,
+                )
+            }
+            _ => (usize::from(opcode & 0x03) + 1, ((opcode >> 2) & 0x1f) + 1),
+        };
+        if registers.len() + len > HLL_REGISTERS {
+            return Err(Error::CorruptedHll);
+        }
+        registers.resize(registers.len() + len, value);
+    }
+    if registers.len() != HLL_REGISTERS {
+        return Err(Error::CorruptedHll);
+    }
+    Ok(registers)
+}
+
+/// The sparse encoding of the registers, None if a register is too large for it or if it's larger
+/// than the limit of the sparse encoding
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut payload = Vec::new();
+    for run in registers.chunk_by(|a, b| a == b) {
+        let value = run[0];
+        if value > HLL_SPARSE_VAL_MAX {
+            return None;
+        }
+        let mut len = run.len();
+        while len > 0 {
+            let chunk = if value != 0 {
+                let chunk = len.min(HLL_SPARSE_VAL_MAX_LEN);
+                let chunk_bits = u8::try_from(chunk - 1).expect("at most 4 registers");
+                payload.push(0x80 | ((value - 1) << 2) | chunk_bits);
+                chunk
+            } else if len > HLL_SPARSE_ZERO_MAX_LEN {
+                let chunk = len.min(HLL_SPARSE_XZERO_MAX_LEN);
+                let [high, low] = u16::try_from(chunk - 1)
+                    .expect("at most 16384 registers")
+                    .to_be_bytes();
+                payload.extend_from_slice(&[0x40 | high, low]);
+                chunk
+            } else {
+                payload.push(u8::try_from(len - 1).expect("at most 64 registers"));
+                len
+            };
+            len -= chunk;
+        }
+    }
+    (HLL_HEADER_LEN + payload.len() <= HLL_SPARSE_MAX_BYTES).then_some(payload)
+}
+
+/// hyperloglog stored at key, None if the key doesn't exist
+fn hyperloglog(data: &mut Keyspace, key: &str) -> Result<Option<HyperLogLog>> {
+    data.get(key)
+        .map(|value| HyperLogLog::decode(value.as_string()?))
+        .transpose()
+}
+
+impl Db {
+    /// Add the elements to the hyperloglog, created if the key doesn't exist.
+    /// Returns whether the hyperloglog was created or changed.
+    pub fn pfadd(&self, key: &str, elements: &[&[u8]]) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        let (mut hll, created) = match hyperloglog(&mut data, key)? {
+            Some(hll) => (hll, false),
+            None => (HyperLogLog::new(), true),
+        };
+        let mut updated = created;
+        for element in elements {
+            updated |= hll.add(element);
+        }
+        if updated {
+            let value = hll.encode();
+            match data.get_mut(key) {
+                Some(existing) => *existing.as_string_mut()? = value,
+                None => data.insert(key.to_string(), Value::Data { data: value }, None),
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Estimated cardinality of the union of the hyperloglogs, missing keys are skipped.
+    /// The cardinality of a single hyperloglog is cached in its header.
+    pub fn pfcount(&self, keys: &[&str]) -> Result<u64> {
+        let mut data = self.data.lock().unwrap();
+        if let [key] = keys {
+            let Some(hll) = hyperloglog(&mut data, key)? else {
+                return Ok(0);
+            };
+            if let Some(count) = hll.cached_count() {
+                return Ok(count);
+            }
+            let count = hll.count();
+            let value = data
+                .get_mut(key)
+                .expect("the key was just read")
+                .as_string_mut()?;
+            value[8..HLL_HEADER_LEN].copy_from_slice(&count.to_le_bytes());
+            return Ok(count);
+        }
+
+        let mut union = HyperLogLog::new();
+        for key in keys {
+            if let Some(hll) = hyperloglog(&mut data, key)? {
+                union.merge(&hll);
+            }
+        }
+        Ok(union.count())
+    }
+
+    /// Store the union of the hyperloglogs in the destination, its own registers included.
+    /// The result is dense if one of the hyperloglogs is dense.
+    pub fn pfmerge(&self, destination: &str, keys: &[&str]) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let mut union = hyperloglog(&mut data, destination)?.unwrap_or_else(HyperLogLog::new);
+        for key in keys {
+            if let Some(hll) = hyperloglog(&mut data, key)? {
+                union.merge(&hll);
+            }
+        }
+        union.invalidate_cache();
+        let value = union.encode();
+        match data.get_mut(destination) {
+            Some(existing) => *existing.as_string_mut()? = value,
+            None => data.insert(destination.to_string(), Value::Data { data: value }, None),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HyperLogLog, HLL_DENSE_LEN, HLL_REGISTERS};
+    use crate::error::Error;
+    use crate::storage::{Config, Db};
+
+    #[test]
+    fn encoding_test() {
+        let mut hll = HyperLogLog::new();
+        // an empty HyperLogLog is a single XZERO opcode
+        assert_eq!(hll.encode(), b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff");
+
+        for i in 0..100 {
+            hll.add(format!("element:{i}").as_bytes());
+        }
+        let sparse = hll.encode();
+        assert_eq!(sparse[4], 1);
+        let decoded = HyperLogLog::decode(&sparse).unwrap();
+        assert_eq!(decoded.registers, hll.registers);
+
+        // a register too large for the sparse encoding converts it to the dense one
+        hll.registers[HLL_REGISTERS - 1] = 40;
+        let dense = hll.encode();
+        assert_eq!((dense[4], dense.len()), (0, HLL_DENSE_LEN));
+        let decoded = HyperLogLog::decode(&dense).unwrap();
+        assert_eq!(decoded.registers, hll.registers);
+        assert!(decoded.dense);
+
+        assert!(matches!(
+            HyperLogLog::decode(b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f"),
+            Err(Error::CorruptedHll)
+        ));
+        assert!(matches!(
+            HyperLogLog::decode(b"not an hll"),
+            Err(Error::InvalidHll)
+        ));
+    }
+
+    #[test]
+    fn pfcount_test() {
+        let db = Db::new(Config::default());
+        assert_eq!(db.pfcount(&["hll"]).unwrap(), 0);
+        assert!(db.pfadd("hll", &[]).unwrap());
+        assert!(!db.pfadd("hll", &[]).unwrap());
+        assert!(db.pfadd("hll", &[b"a", b"b", b"c"]).unwrap());
+        assert!(!db.pfadd("hll", &[b"a"]).unwrap());
+        assert_eq!(db.pfcount(&["hll"]).unwrap(), 3);
+
+        let elements: Vec<String> = (0..20_000).map(|i| format!("user:{i}")).collect();
+        for chunk in elements.chunks(1000) {
+            let chunk: Vec<&[u8]> = chunk.iter().map(String::as_bytes).collect();
+            db.pfadd("users", &chunk).unwrap();
+        }
+        let count = db.pfcount(&["users"]).unwrap();
+        // the standard error is 0.81%
+        assert!(count.abs_diff(20_000) < 500, "{count}");
+        // the cached cardinality is used once computed
+        assert_eq!(db.pfcount(&["users"]).unwrap(), count);
+
+        let union = db.pfcount(&["hll", "users", "missing"]).unwrap();
+        assert!(union.abs_diff(count) <= 3, "{union}");
+        db.pfmerge("merged", &["hll", "users"]).unwrap();
+        assert_eq!(db.pfcount(&["merged"]).unwrap(), union);
+
+        db.set("string", b"value", None);
+        assert!(matches!(db.pfadd("string", &[]), Err(Error::InvalidHll)));
+    }
+}
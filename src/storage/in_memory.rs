@@ -1,30 +1,89 @@
 use std::{
-    collections::HashMap,
     path::{Path, PathBuf},
-    sync::{mpsc::Sender, Mutex},
-    time::SystemTime,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use super::info::Info;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::blocked::BlockedClients;
+use super::info::{Info, Stats};
+use super::keyspace::{Expired, Keyspace, Value};
+use crate::error::{Error, Result};
 use crate::{protocol::Cmd, Args};
 
+/// Stop the active expiration cycle once the expired keys are below this percentage of the
+/// sampled keys, as Redis `ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE`
+const ACTIVE_EXPIRE_ACCEPTABLE_STALE: usize = 10;
+
 #[derive(Default)]
 pub struct Db {
     pub config: Config,
     info: Mutex<Info>,
-    data: Mutex<HashMap<String, Value>>,
-    pub connected_slaves: Mutex<Vec<Sender<Cmd>>>,
+    pub(super) data: Mutex<Keyspace>,
+    pub connected_slaves: Mutex<Vec<UnboundedSender<Cmd>>>,
+    pub(super) blocked: Mutex<BlockedClients>,
+    /// Held by the writes of the master from their application until they are queued to the
+    /// slaves, so the slaves apply them in the same order
+    write_order: Mutex<()>,
+    client_ids: AtomicU64,
 }
 
-#[derive(Debug)]
-enum Value {
-    Data {
-        data: String,
-    },
-    DataWithTTL {
-        data: String,
-        expiration: SystemTime,
-    },
+/// Options of the EXPIRE family of commands, a key without TTL is considered to have an infinite TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Set the expiration only when the key has no expiration
+    Nx,
+    /// Set the expiration only when the key has an existing expiration
+    Xx,
+    /// Set the expiration only when the new expiration is greater than the current one
+    Gt,
+    /// Set the expiration only when the new expiration is less than the current one
+    Lt,
+}
+
+impl ExpireCondition {
+    pub(super) fn allows(self, current: Option<SystemTime>, expiration: SystemTime) -> bool {
+        match self {
+            ExpireCondition::Nx => current.is_none(),
+            ExpireCondition::Xx => current.is_some(),
+            ExpireCondition::Gt => current.is_some_and(|current| expiration > current),
+            ExpireCondition::Lt => current.is_none_or(|current| expiration < current),
+        }
+    }
+}
+
+/// What happened to the key when setting its expiration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireOutcome {
+    /// The key doesn't exist or a condition was not met
+    NotSet,
+    Set,
+    /// The expiration is in the past, the key was deleted
+    Deleted,
+}
+
+/// Existence condition of the SET command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// Only set the key if it doesn't exist
+    Nx,
+    /// Only set the key if it already exists
+    Xx,
+}
+
+/// Expiration of a key whose value is set or read, as the SET and GETEX options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SetExpiration {
+    /// Remove the expiration, the default of SET
+    #[default]
+    Clear,
+    /// Keep the expiration, KEEPTTL for SET and the default of GETEX
+    Keep,
+    At(SystemTime),
 }
 
 #[derive(Debug, Default, Clone)]
@@ -33,9 +92,13 @@ pub struct Config {
     pub replicaof: Option<String>,
     pub dir: Option<String>,
     pub dbfilename: Option<String>,
+    pub hz: u32,
 }
 
 const DEFAULT_PORT: u16 = 6379;
+const DEFAULT_HZ: u32 = 10;
+const MIN_HZ: u32 = 1;
+const MAX_HZ: u32 = 500;
 impl Config {
     pub fn config_from_args(args: &Args) -> Self {
         Self {
@@ -43,6 +106,7 @@ impl Config {
             replicaof: args.replicaof.clone(),
             dir: args.dir.clone(),
             dbfilename: args.dbfilename.clone(),
+            hz: args.hz.unwrap_or(DEFAULT_HZ).clamp(MIN_HZ, MAX_HZ),
         }
     }
     pub fn db_path(&self) -> Option<PathBuf> {
@@ -56,46 +120,323 @@ impl Db {
     pub fn new(config: Config) -> Self {
         Self {
             connected_slaves: Mutex::new(Vec::new()),
+            blocked: Mutex::new(BlockedClients::default()),
             info: Mutex::new(Info::from(&config)),
             config,
-            data: Mutex::new(HashMap::default()),
+            data: Mutex::new(Keyspace::default()),
+            write_order: Mutex::new(()),
+            client_ids: AtomicU64::new(1),
         }
     }
-    pub fn set(&self, key: &str, value: &str, expiration_time: Option<SystemTime>) {
-        let mut data: std::sync::MutexGuard<'_, HashMap<String, Value>> = self.data.lock().unwrap();
+
+    /// Unique id for each client connection
+    pub fn next_client_id(&self) -> u64 {
+        self.client_ids.fetch_add(1, Ordering::Relaxed)
+    }
+    pub fn set(&self, key: &str, value: &[u8], expiration_time: Option<SystemTime>) {
+        let mut data = self.data.lock().unwrap();
         tracing::debug!("set {key} expiration {expiration_time:?}");
-        let value = expiration_time.map_or_else(
-            || Value::Data {
-                data: value.to_owned(),
-            },
-            |expiration| Value::DataWithTTL {
-                data: value.to_owned(),
-                expiration,
-            },
-        );
-
-        data.insert(key.to_string(), value);
-    }
-
-    pub fn get(&self, key: &str) -> Option<String> {
-        let mut hash_map = self.data.lock().unwrap();
-
-        let value = hash_map.get(key)?;
-
-        match value {
-            Value::Data { data } => Some(data.to_owned()),
-            Value::DataWithTTL { data, expiration } => {
-                get_with_ttl(key, data, expiration).or_else(|| {
-                    delete(&mut hash_map, key);
-                    None
-                })
+        let value = Value::Data {
+            data: value.to_owned(),
+        };
+        data.insert(key.to_string(), value, expiration_time);
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.read_string(key, <[u8]>::to_vec)
+    }
+
+    /// Read the string value in place, None if the key doesn't exist
+    pub fn read_string<T>(&self, key: &str, read: impl FnOnce(&[u8]) -> T) -> Result<Option<T>> {
+        let mut data = self.data.lock().unwrap();
+        data.get(key)
+            .map(|value| value.as_string().map(|value| read(value)))
+            .transpose()
+    }
+
+    /// Values of the keys, read at once. Keys holding other types than string are reported missing.
+    pub fn mget(&self, keys: &[&str]) -> Vec<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        keys.iter()
+            .map(|key| data.get(key)?.as_string().ok().cloned())
+            .collect()
+    }
+
+    /// String values of the keys, read at once
+    pub fn get_strings(&self, keys: &[&str]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut data = self.data.lock().unwrap();
+        keys.iter()
+            .map(|key| {
+                data.get(key)
+                    .map(|value| value.as_string().cloned())
+                    .transpose()
+            })
+            .collect()
+    }
+
+    /// Set all the values at once, with `nx` nothing is set if any of the keys exists.
+    /// Returns true if the values were set.
+    pub fn mset(&self, pairs: &[(&str, &[u8])], nx: bool) -> bool {
+        let mut data = self.data.lock().unwrap();
+        if nx && pairs.iter().any(|(key, _)| data.contains_key(key)) {
+            return false;
+        }
+        for (key, value) in pairs {
+            let value = Value::Data {
+                data: value.to_vec(),
+            };
+            data.insert((*key).to_string(), value, None);
+        }
+        true
+    }
+
+    /// Set the value if the condition is met, returns true if the value was set.
+    /// With `get` the previous value is also returned, it must be a string.
+    pub fn set_with(
+        &self,
+        key: &str,
+        value: &[u8],
+        condition: Option<SetCondition>,
+        expiration: SetExpiration,
+        get: bool,
+    ) -> Result<(bool, Option<Vec<u8>>)> {
+        let mut data = self.data.lock().unwrap();
+        let exists = data.contains_key(key);
+        let previous = if get {
+            data.get(key).map(Value::as_string).transpose()?.cloned()
+        } else {
+            None
+        };
+        match condition {
+            Some(SetCondition::Nx) if exists => return Ok((false, previous)),
+            Some(SetCondition::Xx) if !exists => return Ok((false, previous)),
+            _ => {}
+        }
+
+        let expiration = match expiration {
+            SetExpiration::Clear => None,
+            SetExpiration::Keep => data.expiration(key),
+            SetExpiration::At(expiration) => Some(expiration),
+        };
+        let value = Value::Data {
+            data: value.to_owned(),
+        };
+        data.insert(key.to_string(), value, expiration);
+        Ok((true, previous))
+    }
+
+    /// Get the value and update its expiration, an expiration in the past deletes the key
+    pub fn get_ex(&self, key: &str, expiration: SetExpiration) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(value) = data.get(key) else {
+            return Ok(None);
+        };
+        let value = value.as_string()?.clone();
+        match expiration {
+            SetExpiration::Keep => {}
+            SetExpiration::Clear => data.set_expiration(key, None),
+            SetExpiration::At(expiration) if expiration <= SystemTime::now() => {
+                data.remove(key);
             }
+            SetExpiration::At(expiration) => data.set_expiration(key, Some(expiration)),
+        }
+        Ok(Some(value))
+    }
+
+    /// Remove the key and return its value
+    pub fn get_del(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(value) = data.get(key) else {
+            return Ok(None);
+        };
+        value.as_string()?;
+        match data.remove(key) {
+            Some((Value::Data { data: value }, _)) => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Atomic read-modify-write of a string value, the expiration is kept.
+    /// `update` gets the value and whether the key exists, a missing key is created with the
+    /// updated value unless `update` fails.
+    pub fn update_string<T>(
+        &self,
+        key: &str,
+        update: impl FnOnce(&mut Vec<u8>, bool) -> Result<T>,
+    ) -> Result<T> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(value) = data.get_mut(key) {
+            return update(value.as_string_mut()?, true);
+        }
+
+        let mut value = Vec::new();
+        let result = update(&mut value, false)?;
+        data.insert(key.to_string(), Value::Data { data: value }, None);
+        Ok(result)
+    }
+
+    /// Remove the keys, returns the number of keys that were removed
+    pub fn del(&self, keys: &[&str]) -> usize {
+        let mut data = self.data.lock().unwrap();
+        keys.iter().filter(|key| data.remove(key).is_some()).count()
+    }
+
+    /// Number of existing keys, a key mentioned multiple times is counted multiple times
+    pub fn exists(&self, keys: &[&str]) -> usize {
+        let mut data = self.data.lock().unwrap();
+        keys.iter().filter(|key| data.contains_key(key)).count()
+    }
+
+    /// Type name of the value stored at key, None if the key doesn't exist
+    pub fn value_type(&self, key: &str) -> Option<&'static str> {
+        let mut data = self.data.lock().unwrap();
+        data.get(key).map(Value::type_name)
+    }
+
+    /// Rename the key, the TTL is moved with the value.
+    /// With `nx` the key is only renamed if the new key doesn't exist, returns true if renamed.
+    pub fn rename(&self, key: &str, new_key: &str, nx: bool) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        if !data.contains_key(key) {
+            return Err(Error::NoSuchKey);
+        }
+        if nx && data.contains_key(new_key) {
+            return Ok(false);
+        }
+        if let Some((value, expiration)) = data.remove(key) {
+            data.insert(new_key.to_string(), value, expiration);
         }
+        self.signal_key_ready(new_key);
+        Ok(true)
     }
 
-    pub fn keys(&self, _: &str) -> Vec<String> {
-        let hash_map = self.data.lock().unwrap();
-        hash_map.keys().map(String::to_owned).collect()
+    /// Copy the value and its TTL to the destination key, returns true if copied.
+    pub fn copy(&self, source: &str, destination: &str, replace: bool) -> bool {
+        let mut data = self.data.lock().unwrap();
+        let Some(value) = data.get(source).cloned() else {
+            return false;
+        };
+        if !replace && data.contains_key(destination) {
+            return false;
+        }
+        let expiration = data.expiration(source);
+        data.insert(destination.to_string(), value, expiration);
+        self.signal_key_ready(destination);
+        true
+    }
+
+    /// Expiration of the key, None if it has no expiration
+    pub fn expiration(&self, key: &str) -> Result<Option<SystemTime>> {
+        let mut data = self.data.lock().unwrap();
+        if !data.contains_key(key) {
+            return Err(Error::NoSuchKey);
+        }
+        Ok(data.expiration(key))
+    }
+
+    /// Set the expiration of the key if all the conditions are met.
+    /// As in Redis, an expiration in the past deletes the key.
+    pub fn expire(
+        &self,
+        key: &str,
+        expiration: SystemTime,
+        conditions: &[ExpireCondition],
+    ) -> ExpireOutcome {
+        let mut data = self.data.lock().unwrap();
+        if !data.contains_key(key) {
+            return ExpireOutcome::NotSet;
+        }
+        let current = data.expiration(key);
+        if !conditions
+            .iter()
+            .all(|condition| condition.allows(current, expiration))
+        {
+            return ExpireOutcome::NotSet;
+        }
+
+        if expiration <= SystemTime::now() {
+            data.remove(key);
+            return ExpireOutcome::Deleted;
+        }
+        data.set_expiration(key, Some(expiration));
+        ExpireOutcome::Set
+    }
+
+    /// Remove the expiration of the key, returns true if the key had one
+    pub fn persist(&self, key: &str) -> bool {
+        let mut data = self.data.lock().unwrap();
+        if !data.contains_key(key) || data.expiration(key).is_none() {
+            return false;
+        }
+        data.set_expiration(key, None);
+        true
+    }
+
+    /// Keys matching the glob-style pattern, expired keys are skipped
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        let data = self.data.lock().unwrap();
+        data.iter()
+            .filter(|(key, _)| glob_match(pattern.as_bytes(), key.as_bytes()))
+            .map(|(key, _)| key.to_owned())
+            .collect()
+    }
+
+    /// Incremental iteration over the keyspace, only `count` keys are visited while holding the lock.
+    /// As in Redis, the pattern and type filters are applied after the keys are visited,
+    /// so an iteration may return no keys but a non zero cursor.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+        value_type: Option<&str>,
+    ) -> (u64, Vec<String>) {
+        let data = self.data.lock().unwrap();
+        let (cursor, entries) = data.scan(cursor, count);
+        let keys = entries
+            .into_iter()
+            .filter(|(_, value)| {
+                value_type.is_none_or(|t| t.eq_ignore_ascii_case(value.type_name()))
+            })
+            .filter(|(key, _)| pattern.is_none_or(|p| glob_match(p.as_bytes(), key.as_bytes())))
+            .map(|(key, _)| key.to_owned())
+            .collect();
+        (cursor, keys)
+    }
+
+    /// Active expiration as Redis `activeExpireCycle`: batches of keys with a TTL are sampled
+    /// and their expired keys removed, until the share of expired keys in a batch is acceptable
+    /// or the time limit is reached. The lock is released between batches.
+    /// Returns the removed keys and hash fields.
+    pub fn active_expire_cycle(&self, time_limit: Duration) -> Expired {
+        let start = Instant::now();
+        let (mut sampled, mut expired) = (0, Expired::default());
+        loop {
+            let (batch_sampled, batch_expired) = self.data.lock().unwrap().expire_batch();
+            sampled += batch_sampled;
+            let stale = batch_expired.count() * 100;
+            expired.extend(batch_expired);
+
+            if batch_sampled == 0
+                || stale <= batch_sampled * ACTIVE_EXPIRE_ACCEPTABLE_STALE
+                || start.elapsed() >= time_limit
+            {
+                break;
+            }
+        }
+
+        let mut data = self.data.lock().unwrap();
+        data.record_expire_cycle(sampled, expired.count(), start.elapsed());
+        expired
+    }
+
+    /// Keys and hash fields removed when accessed since the last call, to propagate the deletes
+    pub fn take_lazily_expired(&self) -> Expired {
+        self.data.lock().unwrap().take_lazily_expired()
+    }
+
+    pub fn has_lazily_expired(&self) -> bool {
+        self.data.lock().unwrap().has_lazily_expired()
     }
 
     pub fn info(&self) -> Info {
@@ -103,28 +444,158 @@ impl Db {
         info.clone()
     }
 
-    pub fn register_slave(&self, writer_to_slave: Sender<Cmd>) {
+    pub fn stats(&self) -> Stats {
+        self.data.lock().unwrap().stats.clone()
+    }
+
+    /// Serialize the writes with their propagation to the slaves, until the guard is dropped
+    pub fn order_writes(&self) -> MutexGuard<'_, ()> {
+        self.write_order.lock().unwrap()
+    }
+
+    pub fn register_slave(&self, writer_to_slave: UnboundedSender<Cmd>) {
         let mut slaves = self.connected_slaves.lock().unwrap();
         slaves.push(writer_to_slave);
     }
 }
 
-fn get_with_ttl(key: &str, data: &str, expiration: &SystemTime) -> Option<String> {
-    let now = SystemTime::now();
-
-    //let elapsed = now.duration_since(*expiration);
-    let Some(elapsed) = expiration.duration_since(now).ok() else {
-        tracing::debug!("key {key} expired");
+/// Inclusive range of a sequence of `len` elements, negative indexes count from the end.
+/// The range is clamped to the sequence, None if it is empty.
+pub fn clamp_range(len: usize, start: i64, end: i64) -> Option<(usize, usize)> {
+    let len = i64::try_from(len).ok()?;
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+    if start > end || len == 0 {
         return None;
-    };
+    }
+    Some((usize::try_from(start).ok()?, usize::try_from(end).ok()?))
+}
+
+/// Glob-style pattern matching with the same semantics as Redis `stringmatchlen`:
+///  - `*` matches any sequence, `?` matches any single character
+///  - `[abc]`, `[^abc]` and `[a-z]` match a character class
+///  - `\` escapes the next character
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // position to resume from on mismatch, after the last `*` seen
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        let matched = match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, s));
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match_class(pattern, p, string[s]),
+            Some(b'\\') if p + 1 < pattern.len() => (pattern[p + 1] == string[s]).then_some(p + 2),
+            Some(c) => (*c == string[s]).then_some(p + 1),
+            None => None,
+        };
+
+        if let Some(next) = matched {
+            p = next;
+            s += 1;
+        } else if let Some((star_p, star_s)) = backtrack {
+            // let the last `*` consume one more character
+            p = star_p;
+            s = star_s + 1;
+            backtrack = Some((star_p, s));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
 
-    tracing::debug!(
-        "key {key} will expire in {}ms now:{now:?}, expiration:{expiration:?}",
-        elapsed.as_millis()
-    );
-    Some(data.to_owned())
+/// Match a character class starting at `pattern[start] == '['`,
+/// returns the position after the class if the character matched.
+fn match_class(pattern: &[u8], start: usize, c: u8) -> Option<usize> {
+    let mut i = start + 1;
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    // an unterminated class ends with the pattern
+    while let Some(&current) = pattern.get(i) {
+        match current {
+            b']' => {
+                i += 1;
+                break;
+            }
+            b'\\' if i + 1 < pattern.len() => {
+                matched |= pattern[i + 1] == c;
+                i += 2;
+            }
+            _ if i + 2 < pattern.len() && pattern[i + 1] == b'-' => {
+                let (low, high) = (current.min(pattern[i + 2]), current.max(pattern[i + 2]));
+                matched |= (low..=high).contains(&c);
+                i += 3;
+            }
+            _ => {
+                matched |= current == c;
+                i += 1;
+            }
+        }
+    }
+
+    (matched != negate).then_some(i)
 }
 
-fn delete<T>(data: &mut std::sync::MutexGuard<'_, HashMap<String, T>>, key: &str) {
-    data.remove(key);
+#[cfg(test)]
+mod tests {
+    use super::{clamp_range, glob_match};
+
+    #[test]
+    fn glob_match_test() {
+        let cases: &[(&str, &str, bool)] = &[
+            ("*", "anything", true),
+            ("*", "", true),
+            ("user:*", "user:1000", true),
+            ("user:*", "session:1", false),
+            ("h?llo", "hello", true),
+            ("h?llo", "hllo", false),
+            ("h*llo", "heeeello", true),
+            ("h*llo", "hello world", false),
+            ("h[ae]llo", "hallo", true),
+            ("h[ae]llo", "hillo", false),
+            ("h[^e]llo", "hallo", true),
+            ("h[^e]llo", "hello", false),
+            ("h[a-b]llo", "hbllo", true),
+            ("h[b-a]llo", "hallo", true),
+            ("h[a-b]llo", "hcllo", false),
+            ("h\\*llo", "h*llo", true),
+            ("h\\*llo", "hello", false),
+            ("[\\]]", "]", true),
+            ("*a*b*c*", "xxaxxbxxcxx", true),
+            ("*a*b*c", "xxaxxbxxcxx", false),
+            ("a*", "b", false),
+            ("h[a", "ha", true),
+        ];
+        for (pattern, string, expected) in cases {
+            assert_eq!(
+                glob_match(pattern.as_bytes(), string.as_bytes()),
+                *expected,
+                "{pattern} {string}"
+            );
+        }
+    }
+
+    #[test]
+    fn clamp_range_test() {
+        assert_eq!(clamp_range(10, 0, -1), Some((0, 9)));
+        assert_eq!(clamp_range(10, -3, -1), Some((7, 9)));
+        assert_eq!(clamp_range(10, 5, 100), Some((5, 9)));
+        assert_eq!(clamp_range(10, -100, 2), Some((0, 2)));
+        assert_eq!(clamp_range(10, 5, 3), None);
+        assert_eq!(clamp_range(10, -1, -3), None);
+        assert_eq!(clamp_range(0, 0, -1), None);
+    }
 }
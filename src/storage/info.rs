@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::time::Duration;
 
 use uuid::Uuid;
 
@@ -17,6 +18,19 @@ pub struct Replication {
     pub master_repl_offset: u32,
 }
 
+/// Keyspace statistics, reported in the stats section of INFO
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Keys removed by the lazy and the active expiration
+    pub expired_keys: u64,
+    /// Hash fields removed by the lazy and the active expiration
+    pub expired_subkeys: u64,
+    /// Estimation of the share of expired keys still in memory, between 0 and 1
+    pub expired_stale_perc: f64,
+    /// Time spent in the active expiration cycles
+    pub expire_cycle_cpu: Duration,
+}
+
 impl Info {
     pub fn from(config: &Config) -> Self {
         let my_uuid = Uuid::now_v7();
@@ -48,3 +62,22 @@ impl Display for Replication {
         Ok(())
     }
 }
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# Stats")?;
+        writeln!(f, "expired_keys:{}", self.expired_keys)?;
+        writeln!(f, "expired_subkeys:{}", self.expired_subkeys)?;
+        writeln!(
+            f,
+            "expired_stale_perc:{:.2}",
+            self.expired_stale_perc * 100.0
+        )?;
+        writeln!(
+            f,
+            "expire_cycle_cpu_milliseconds:{}",
+            self.expire_cycle_cpu.as_millis()
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use super::hash::Hash;
+use super::info::Stats;
+use super::scan::ScanMap;
+use super::set::Set;
+use super::sorted_set::SortedSet;
+use super::stream::Stream;
+use crate::error::{Error, Result};
+
+/// Number of keys with a TTL sampled at once by the active expiration, as Redis
+/// `ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP`
+const ACTIVE_EXPIRE_KEYS_PER_LOOP: usize = 20;
+
+#[derive(Debug, Clone)]
+pub(super) enum Value {
+    Data { data: Vec<u8> },
+    List { list: VecDeque<Vec<u8>> },
+    Hash { hash: Hash },
+    Set { set: Set },
+    SortedSet { zset: SortedSet },
+    Stream { stream: Stream },
+}
+
+impl Value {
+    /// Type name as reported by the TYPE command
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Data { .. } => "string",
+            Value::List { .. } => "list",
+            Value::Hash { .. } => "hash",
+            Value::Set { .. } => "set",
+            Value::SortedSet { .. } => "zset",
+            Value::Stream { .. } => "stream",
+        }
+    }
+
+    /// The string payload, commands against another type fail with WRONGTYPE
+    pub fn as_string(&self) -> Result<&Vec<u8>> {
+        match self {
+            Value::Data { data } => Ok(data),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Result<&mut Vec<u8>> {
+        match self {
+            Value::Data { data } => Ok(data),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&VecDeque<Vec<u8>>> {
+        match self {
+            Value::List { list } => Ok(list),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut VecDeque<Vec<u8>>> {
+        match self {
+            Value::List { list } => Ok(list),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_hash(&self) -> Result<&Hash> {
+        match self {
+            Value::Hash { hash } => Ok(hash),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Result<&mut Hash> {
+        match self {
+            Value::Hash { hash } => Ok(hash),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_set(&self) -> Result<&Set> {
+        match self {
+            Value::Set { set } => Ok(set),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Result<&mut Set> {
+        match self {
+            Value::Set { set } => Ok(set),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_zset(&self) -> Result<&SortedSet> {
+        match self {
+            Value::SortedSet { zset } => Ok(zset),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_zset_mut(&mut self) -> Result<&mut SortedSet> {
+        match self {
+            Value::SortedSet { zset } => Ok(zset),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_stream(&self) -> Result<&Stream> {
+        match self {
+            Value::Stream { stream } => Ok(stream),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Result<&mut Stream> {
+        match self {
+            Value::Stream { stream } => Ok(stream),
+            _ => Err(Error::WrongType),
+        }
+    }
+
+    /// Earliest expiration of the fields of a hash
+    fn next_field_expiration(&self) -> Option<SystemTime> {
+        match self {
+            Value::Hash { hash } => hash.next_expiration(),
+            _ => None,
+        }
+    }
+}
+
+/// Keys and hash fields removed by the active or the lazy expiration
+#[derive(Debug, Default)]
+pub struct Expired {
+    pub keys: Vec<String>,
+    /// The expired fields of each hash
+    pub fields: Vec<(String, Vec<Vec<u8>>)>,
+}
+
+impl Expired {
+    /// Number of keys expired or with expired fields
+    pub fn count(&self) -> usize {
+        self.keys.len() + self.fields.len()
+    }
+
+    pub fn extend(&mut self, other: Expired) {
+        self.keys.extend(other.keys);
+        self.fields.extend(other.fields);
+    }
+}
+
+/// The values and their expirations.
+///
+/// As in Redis, the expirations are kept in their own index so the active expiration only
+/// samples the keys with a TTL. Expired keys are never returned, they are removed when accessed.
+/// Hash fields with a TTL are expired the same way, the hashes with such fields are indexed by
+/// the earliest expiration of their fields.
+#[derive(Debug, Default)]
+pub(super) struct Keyspace {
+    data: ScanMap<String, Value>,
+    expires: ScanMap<String, SystemTime>,
+    field_expires: ScanMap<String, SystemTime>,
+    // where the next active expiration batches start in `expires` and `field_expires`
+    expire_cursor: u64,
+    field_expire_cursor: u64,
+    // removed when accessed, until the master propagates them
+    lazily_expired: Expired,
+    pub stats: Stats,
+}
+
+impl Keyspace {
+    /// Value stored at key, the key is removed if expired
+    pub fn get(&mut self, key: &str) -> Option<&Value> {
+        self.expire_if_needed(key);
+        self.data.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.expire_if_needed(key);
+        self.data.get_mut(key)
+    }
+
+    /// Value stored at key, without removing the key if expired.
+    /// Used to read several keys at once.
+    pub fn peek(&self, key: &str) -> Option<&Value> {
+        if self.is_expired(key, SystemTime::now()) {
+            return None;
+        }
+        self.data.get(key)
+    }
+
+    pub fn contains_key(&mut self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Store the value, the previous value and expiration of the key are replaced
+    pub fn insert(&mut self, key: String, value: Value, expiration: Option<SystemTime>) {
+        match expiration {
+            Some(expiration) => {
+                self.expires.insert(key.clone(), expiration);
+            }
+            None => {
+                self.expires.remove(key.as_str());
+            }
+        }
+        match value.next_field_expiration() {
+            Some(expiration) => {
+                self.field_expires.insert(key.clone(), expiration);
+            }
+            None => {
+                self.field_expires.remove(key.as_str());
+            }
+        }
+        self.data.insert(key, value);
+    }
+
+    /// Remove the key, returns its value and expiration if the key existed
+    pub fn remove(&mut self, key: &str) -> Option<(Value, Option<SystemTime>)> {
+        self.expire_if_needed(key);
+        self.remove_entry(key)
+    }
+
+    fn remove_entry(&mut self, key: &str) -> Option<(Value, Option<SystemTime>)> {
+        let value = self.data.remove(key)?;
+        self.field_expires.remove(key);
+        Some((value, self.expires.remove(key)))
+    }
+
+    /// Expiration of an existing key
+    pub fn expiration(&self, key: &str) -> Option<SystemTime> {
+        self.expires.get(key).copied()
+    }
+
+    /// Set or remove (with None) the expiration of an existing key
+    pub fn set_expiration(&mut self, key: &str, expiration: Option<SystemTime>) {
+        if !self.contains_key(key) {
+            return;
+        }
+        match expiration {
+            Some(expiration) => {
+                self.expires.insert(key.to_string(), expiration);
+            }
+            None => {
+                self.expires.remove(key);
+            }
+        }
+    }
+
+    /// Update the field expiration index once the fields of the hash or their TTLs changed
+    pub fn track_field_expiration(&mut self, key: &str) {
+        match self.data.get(key).and_then(Value::next_field_expiration) {
+            Some(expiration) => {
+                self.field_expires.insert(key.to_string(), expiration);
+            }
+            None => {
+                self.field_expires.remove(key);
+            }
+        }
+    }
+
+    fn is_expired(&self, key: &str, now: SystemTime) -> bool {
+        self.expires
+            .get(key)
+            .is_some_and(|expiration| *expiration <= now)
+    }
+
+    /// Lazy expiration, the key is removed when accessed after its expiration,
+    /// and so are the expired fields of a hash
+    fn expire_if_needed(&mut self, key: &str) {
+        let now = SystemTime::now();
+        if self.is_expired(key, now) {
+            tracing::debug!("key {key} expired");
+            self.remove_entry(key);
+            self.stats.expired_keys += 1;
+            self.lazily_expired.keys.push(key.to_string());
+            return;
+        }
+        let fields = self.expire_fields(key, now);
+        if !fields.is_empty() {
+            self.lazily_expired.fields.push((key.to_string(), fields));
+        }
+    }
+
+    /// Keys and hash fields removed when accessed since the last call
+    pub fn take_lazily_expired(&mut self) -> Expired {
+        std::mem::take(&mut self.lazily_expired)
+    }
+
+    pub fn has_lazily_expired(&self) -> bool {
+        self.lazily_expired.count() > 0
+    }
+
+    /// Remove the expired fields of the hash, the hash is removed with its last field.
+    /// Returns the removed fields.
+    fn expire_fields(&mut self, key: &str, now: SystemTime) -> Vec<Vec<u8>> {
+        if self
+            .field_expires
+            .get(key)
+            .is_none_or(|expiration| *expiration > now)
+        {
+            return Vec::new();
+        }
+        let Some(Value::Hash { hash }) = self.data.get_mut(key) else {
+            return Vec::new();
+        };
+        let expired = hash.expire_fields(now);
+        tracing::debug!("{} fields of {key} expired", expired.len());
+        self.stats.expired_subkeys += expired.len() as u64;
+        if hash.is_empty() {
+            self.remove_entry(key);
+        } else {
+            self.track_field_expiration(key);
+        }
+        expired
+    }
+
+    /// All the live entries
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        let now = SystemTime::now();
+        self.data
+            .iter()
+            .filter(move |(key, _)| !self.is_expired(key, now))
+    }
+
+    /// Incremental iteration over the live entries, see [`ScanMap::scan`]
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&String, &Value)>) {
+        let now = SystemTime::now();
+        let (cursor, mut entries) = self.data.scan(cursor, count);
+        entries.retain(|(key, _)| !self.is_expired(key, now));
+        (cursor, entries)
+    }
+
+    /// Sample the next batch of keys with a TTL and the next batch of hashes with fields with a TTL,
+    /// and remove what expired. Returns the number of keys sampled and what was removed.
+    pub fn expire_batch(&mut self) -> (usize, Expired) {
+        let now = SystemTime::now();
+        let (cursor, sampled) = self
+            .expires
+            .scan(self.expire_cursor, ACTIVE_EXPIRE_KEYS_PER_LOOP);
+        self.expire_cursor = cursor;
+
+        let mut sampled_count = sampled.len();
+        let keys: Vec<String> = sampled
+            .into_iter()
+            .filter(|(_, expiration)| **expiration <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            self.remove_entry(key);
+        }
+        self.stats.expired_keys += keys.len() as u64;
+
+        let (cursor, sampled) = self
+            .field_expires
+            .scan(self.field_expire_cursor, ACTIVE_EXPIRE_KEYS_PER_LOOP);
+        self.field_expire_cursor = cursor;
+
+        sampled_count += sampled.len();
+        let hashes: Vec<String> = sampled
+            .into_iter()
+            .filter(|(_, expiration)| **expiration <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let fields = hashes
+            .into_iter()
+            .map(|key| {
+                let fields = self.expire_fields(&key, now);
+                (key, fields)
+            })
+            .collect();
+        (sampled_count, Expired { keys, fields })
+    }
+
+    /// Update the statistics at the end of an active expiration cycle
+    pub fn record_expire_cycle(&mut self, sampled: usize, expired: usize, elapsed: Duration) {
+        if sampled > 0 {
+            // moving average, as Redis `stat_expired_stale_perc`
+            // a cycle samples a few thousand keys at most
+            let as_f64 = |n: usize| f64::from(u32::try_from(n).unwrap_or(u32::MAX));
+            let current = as_f64(expired) / as_f64(sampled);
+            self.stats.expired_stale_perc = current * 0.05 + self.stats.expired_stale_perc * 0.95;
+        }
+        self.stats.expire_cycle_cpu += elapsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Keyspace, Value};
+    use std::time::{Duration, SystemTime};
+
+    fn value() -> Value {
+        Value::Data {
+            data: b"v".to_vec(),
+        }
+    }
+
+    #[test]
+    fn expired_keys_are_hidden_and_removed_test() {
+        let mut keyspace = Keyspace::default();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        keyspace.insert("expired".to_string(), value(), Some(past));
+        keyspace.insert("live".to_string(), value(), None);
+
+        assert_eq!(keyspace.iter().count(), 1);
+        assert!(!keyspace.contains_key("expired"));
+        assert_eq!(keyspace.stats.expired_keys, 1);
+        assert!(keyspace.contains_key("live"));
+    }
+
+    #[test]
+    fn expire_batch_test() {
+        let mut keyspace = Keyspace::default();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let future = SystemTime::now() + Duration::from_secs(100);
+        for i in 0..100 {
+            let expiration = if i % 2 == 0 { past } else { future };
+            keyspace.insert(format!("key:{i}"), value(), Some(expiration));
+        }
+        keyspace.insert("persistent".to_string(), value(), None);
+
+        // a full pass over the keys with a TTL removes all the expired keys
+        let mut expired = 0;
+        loop {
+            let (sampled, removed) = keyspace.expire_batch();
+            assert!(sampled > 0);
+            expired += removed.count();
+            if keyspace.expire_cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(expired, 50);
+        assert_eq!(keyspace.data.iter().count(), 51);
+        assert_eq!(keyspace.stats.expired_keys, 50);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+
+use super::in_memory::clamp_range;
+use super::keyspace::{Keyspace, Value};
+use super::Db;
+use crate::error::{Error, Result};
+
+/// Side of a list, the left side is the head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    fn push(self, list: &mut VecDeque<Vec<u8>>, element: Vec<u8>) {
+        match self {
+            ListEnd::Left => list.push_front(element),
+            ListEnd::Right => list.push_back(element),
+        }
+    }
+
+    fn pop(self, list: &mut VecDeque<Vec<u8>>) -> Option<Vec<u8>> {
+        match self {
+            ListEnd::Left => list.pop_front(),
+            ListEnd::Right => list.pop_back(),
+        }
+    }
+}
+
+/// List stored at key, None if the key doesn't exist
+fn list_mut<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut VecDeque<Vec<u8>>>> {
+    data.get_mut(key).map(Value::as_list_mut).transpose()
+}
+
+fn list<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a VecDeque<Vec<u8>>>> {
+    data.get(key).map(Value::as_list).transpose()
+}
+
+/// Empty lists are not kept, as in Redis the key is removed with its last element
+fn remove_if_empty(data: &mut Keyspace, key: &str) -> Result<()> {
+    if list(data, key)?.is_some_and(VecDeque::is_empty) {
+        data.remove(key);
+    }
+    Ok(())
+}
+
+/// Push the elements one after the other, the list is created if the key doesn't exist
+fn push_elements(
+    data: &mut Keyspace,
+    key: &str,
+    elements: impl IntoIterator<Item = Vec<u8>>,
+    end: ListEnd,
+) -> Result<usize> {
+    if let Some(list) = list_mut(data, key)? {
+        elements
+            .into_iter()
+            .for_each(|element| end.push(list, element));
+        return Ok(list.len());
+    }
+
+    let mut list = VecDeque::new();
+    elements
+        .into_iter()
+        .for_each(|element| end.push(&mut list, element));
+    let len = list.len();
+    data.insert(key.to_string(), Value::List { list }, None);
+    Ok(len)
+}
+
+/// Position of the index in a list of `len` elements, negative indexes count from the end
+fn list_index(len: usize, index: i64) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let index = if index < 0 { len + index } else { index };
+    (0..len)
+        .contains(&index)
+        .then(|| usize::try_from(index).ok())
+        .flatten()
+}
+
+/// List commands, see <https://redis.io/docs/latest/develop/data-types/lists/>
+impl Db {
+    /// Push the elements, returns the length of the list
+    pub fn push(&self, key: &str, elements: &[&[u8]], end: ListEnd) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let len = push_elements(
+            &mut data,
+            key,
+            elements.iter().map(|element| element.to_vec()),
+            end,
+        )?;
+        self.signal_key_ready(key);
+        Ok(len)
+    }
+
+    /// Pop up to `count` elements, None if the key doesn't exist
+    pub fn pop(&self, key: &str, end: ListEnd, count: usize) -> Result<Option<Vec<Vec<u8>>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list_mut(&mut data, key)? else {
+            return Ok(None);
+        };
+        let popped = (0..count).map_while(|_| end.pop(list)).collect();
+        remove_if_empty(&mut data, key)?;
+        Ok(Some(popped))
+    }
+
+    /// Elements between the inclusive indexes
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list(&mut data, key)? else {
+            return Ok(Vec::new());
+        };
+        Ok(clamp_range(list.len(), start, stop)
+            .map(|(start, stop)| list.range(start..=stop).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    pub fn llen(&self, key: &str) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(list(&mut data, key)?.map_or(0, VecDeque::len))
+    }
+
+    pub fn lindex(&self, key: &str, index: i64) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list(&mut data, key)? else {
+            return Ok(None);
+        };
+        Ok(list_index(list.len(), index).map(|index| list[index].clone()))
+    }
+
+    pub fn lset(&self, key: &str, index: i64, element: &[u8]) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let list = list_mut(&mut data, key)?.ok_or(Error::NoSuchKey)?;
+        let index = list_index(list.len(), index).ok_or(Error::IndexOutOfRange)?;
+        list[index] = element.to_vec();
+        Ok(())
+    }
+
+    /// Remove `count` occurrences of the element, from the tail if `count` is negative and all
+    /// of them if 0. Returns the number of removed elements.
+    pub fn lrem(&self, key: &str, count: i64, element: &[u8]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+
+        let limit = match count {
+            0 => usize::MAX,
+            count => usize::try_from(count.unsigned_abs()).unwrap_or(usize::MAX),
+        };
+        let mut removed = 0;
+        let mut retain = |list: &mut VecDeque<Vec<u8>>| {
+            list.retain(|e| {
+                let remove = removed < limit && e == element;
+                removed += usize::from(remove);
+                !remove
+            });
+        };
+        if count < 0 {
+            list.make_contiguous().reverse();
+            retain(list);
+            list.make_contiguous().reverse();
+        } else {
+            retain(list);
+        }
+
+        remove_if_empty(&mut data, key)?;
+        Ok(removed)
+    }
+
+    /// Only keep the elements between the inclusive indexes
+    pub fn ltrim(&self, key: &str, start: i64, stop: i64) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list_mut(&mut data, key)? else {
+            return Ok(());
+        };
+        if let Some((start, stop)) = clamp_range(list.len(), start, stop) {
+            list.truncate(stop + 1);
+            list.drain(..start);
+        } else {
+            list.clear();
+        }
+        remove_if_empty(&mut data, key)
+    }
+
+    /// Insert the element next to the pivot, returns the length of the list,
+    /// -1 if the pivot is not found and 0 if the key doesn't exist
+    pub fn linsert(&self, key: &str, before: bool, pivot: &[u8], element: &[u8]) -> Result<i64> {
+        let mut data = self.data.lock().unwrap();
+        let Some(list) = list_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+        let Some(position) = list.iter().position(|e| e == pivot) else {
+            return Ok(-1);
+        };
+        let position = if before { position } else { position + 1 };
+        list.insert(position, element.to_vec());
+        Ok(i64::try_from(list.len()).unwrap_or(i64::MAX))
+    }
+
+    /// Atomically pop an element from the source and push it to the destination,
+    /// the source and the destination can be the same list.
+    pub fn lmove(
+        &self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        // nothing is popped if the destination can't receive the element
+        list(&mut data, destination)?;
+        let Some(element) = list_mut(&mut data, source)?.and_then(|list| from.pop(list)) else {
+            return Ok(None);
+        };
+        push_elements(&mut data, destination, [element.clone()], to)?;
+        remove_if_empty(&mut data, source)?;
+        self.signal_key_ready(destination);
+        Ok(Some(element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{list_index, ListEnd};
+    use crate::storage::{Config, Db};
+
+    fn elements(db: &Db, key: &str) -> Vec<String> {
+        db.lrange(key, 0, -1)
+            .unwrap()
+            .into_iter()
+            .map(|e| String::from_utf8(e).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn list_index_test() {
+        assert_eq!(list_index(3, 0), Some(0));
+        assert_eq!(list_index(3, -1), Some(2));
+        assert_eq!(list_index(3, 3), None);
+        assert_eq!(list_index(3, -4), None);
+    }
+
+    #[test]
+    fn lrem_test() {
+        let db = Db::new(Config::default());
+        let list: Vec<&[u8]> = vec![b"a", b"b", b"a", b"c", b"a"];
+        db.push("l", &list, ListEnd::Right).unwrap();
+
+        assert_eq!(db.lrem("l", -2, b"a").unwrap(), 2);
+        assert_eq!(elements(&db, "l"), ["a", "b", "c"]);
+        assert_eq!(db.lrem("l", 0, b"a").unwrap(), 1);
+        assert_eq!(elements(&db, "l"), ["b", "c"]);
+    }
+
+    #[test]
+    fn lmove_rotates_test() {
+        let db = Db::new(Config::default());
+        let list: Vec<&[u8]> = vec![b"a", b"b", b"c"];
+        db.push("l", &list, ListEnd::Right).unwrap();
+
+        let moved = db.lmove("l", "l", ListEnd::Left, ListEnd::Right).unwrap();
+        assert_eq!(moved, Some(b"a".to_vec()));
+        assert_eq!(elements(&db, "l"), ["b", "c", "a"]);
+
+        db.ltrim("l", 5, 10).unwrap();
+        assert_eq!(db.llen("l").unwrap(), 0);
+        assert_eq!(db.value_type("l"), None);
+    }
+}
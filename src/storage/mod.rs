@@ -1,4 +1,31 @@
+mod bitmap;
+mod blocked;
+mod geo;
+mod hash;
+mod hyperloglog;
 mod in_memory;
 mod info;
+mod keyspace;
+mod list;
+mod scan;
+mod set;
+mod skiplist;
+mod sorted_set;
+mod stream;
+pub use bitmap::{BitField, BitFieldOp, BitOperation, BitUnit, Overflow};
+pub use geo::{
+    geohash_score, valid_lon_lat, GeoFrom, GeoMatch, GeoSearch, GeoShape, GeoSort, GeoUnit,
+};
+pub use hash::FieldExpiration;
+pub use in_memory::clamp_range;
 pub use in_memory::Config;
 pub use in_memory::Db;
+pub use in_memory::{ExpireCondition, ExpireOutcome, SetCondition, SetExpiration};
+pub use keyspace::Expired;
+pub use list::ListEnd;
+pub use set::SetOperation;
+pub use sorted_set::{Aggregate, LexBound, ZRange, ZRangeBy, ZaddOptions};
+pub use stream::{
+    ClaimOptions, Claimed, GroupReadFrom, PendingFilter, StreamFields, StreamId, StreamTrim,
+    TrimThreshold, XaddId,
+};
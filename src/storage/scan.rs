@@ -0,0 +1,202 @@
+use std::{
+    borrow::Borrow,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use rand::seq::SliceRandom;
+
+/// Hash map that can be iterated incrementally with a cursor, as needed by SCAN like commands.
+///
+/// The entries are also indexed by the hash of their key, the cursor is the hash to resume from.
+/// Every entry present during the whole iteration is returned, no matter the inserts and deletes in
+/// between. Entries inserted or deleted during the iteration may or may not be returned.
+#[derive(Debug, Clone)]
+pub struct ScanMap<K, V> {
+    entries: HashMap<K, V>,
+    // keys with the same hash are kept in the same bucket, so a cursor never splits them
+    order: BTreeMap<u64, Vec<K>>,
+}
+
+impl<K, V> Default for ScanMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+/// Stable hash of the key, the cursor must remain valid between calls
+fn scan_hash<Q: Hash + ?Sized>(key: &Q) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<K, V> ScanMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get_mut(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(previous) = self.entries.get_mut(&key) {
+            return Some(std::mem::replace(previous, value));
+        }
+        self.order
+            .entry(scan_hash(&key))
+            .or_default()
+            .push(key.clone());
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let value = self.entries.remove(key)?;
+        let hash = scan_hash(key);
+        if let Some(bucket) = self.order.get_mut(&hash) {
+            bucket.retain(|k| k.borrow() != key);
+            if bucket.is_empty() {
+                self.order.remove(&hash);
+            }
+        }
+        Some(value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// A random key, the first key at or after a random hash.
+    /// As Redis `dictGetRandomKey` the distribution is not perfectly fair.
+    pub fn random_key(&self) -> Option<&K> {
+        let hash = rand::random::<u64>();
+        let (_, bucket) = self
+            .order
+            .range(hash..)
+            .next()
+            .or_else(|| self.order.iter().next())?;
+        bucket.choose(&mut rand::thread_rng())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter()
+    }
+
+    /// Return at least `count` entries (if any) starting at the cursor, and the cursor to continue from.
+    /// The iteration starts and ends with the cursor 0.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&K, &V)>) {
+        let mut items = Vec::with_capacity(count.min(self.entries.len()));
+        let mut buckets = self.order.range(cursor..);
+
+        for (_, bucket) in buckets.by_ref() {
+            items.extend(
+                bucket
+                    .iter()
+                    .filter_map(|key| self.entries.get_key_value(key)),
+            );
+            if items.len() >= count {
+                break;
+            }
+        }
+
+        let next_cursor = buckets.next().map_or(0, |(hash, _)| *hash);
+        (next_cursor, items)
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for ScanMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = ScanMap::default();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScanMap;
+    use std::collections::HashSet;
+
+    fn scan_all(map: &ScanMap<String, u32>, count: usize) -> Vec<String> {
+        let mut keys = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, items) = map.scan(cursor, count);
+            keys.extend(items.into_iter().map(|(k, _)| k.clone()));
+            if next == 0 {
+                return keys;
+            }
+            cursor = next;
+        }
+    }
+
+    #[test]
+    fn scan_returns_all_entries_test() {
+        let map: ScanMap<String, u32> = (0..100).map(|i| (format!("key:{i}"), i)).collect();
+        let keys = scan_all(&map, 7);
+        assert_eq!(keys.len(), 100);
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 100);
+    }
+
+    #[test]
+    fn scan_cursor_stable_across_updates_test() {
+        let mut map: ScanMap<String, u32> = (0..50).map(|i| (format!("key:{i}"), i)).collect();
+        let (cursor, first) = map.scan(0, 10);
+        let mut seen: HashSet<String> = first.into_iter().map(|(k, _)| k.clone()).collect();
+
+        // delete already returned keys and insert new ones, the remaining keys are still returned
+        let returned: Vec<String> = seen.iter().cloned().collect();
+        for key in &returned {
+            map.remove(key.as_str());
+        }
+        for i in 50..80 {
+            map.insert(format!("key:{i}"), i);
+        }
+
+        let mut cursor = cursor;
+        while cursor != 0 {
+            let (next, items) = map.scan(cursor, 10);
+            seen.extend(items.into_iter().map(|(k, _)| k.clone()));
+            cursor = next;
+        }
+        for i in 0..50 {
+            assert!(seen.contains(&format!("key:{i}")), "key:{i} missing");
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+
+use rand::seq::IteratorRandom;
+
+use super::in_memory::glob_match;
+use super::keyspace::{Keyspace, Value};
+use super::scan::ScanMap;
+use super::Db;
+use crate::error::{Error, Result};
+
+/// Members of a set, a map so members can be picked at random and scanned
+pub(super) type Set = ScanMap<Vec<u8>, ()>;
+
+/// Operation between sets of the SINTER, SUNION and SDIFF families
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    Diff,
+}
+
+/// Set stored at key, None if the key doesn't exist
+fn set_mut<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut Set>> {
+    data.get_mut(key).map(Value::as_set_mut).transpose()
+}
+
+fn set<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a Set>> {
+    data.get(key).map(Value::as_set).transpose()
+}
+
+/// Empty sets are not kept, as in Redis the key is removed with its last member
+fn remove_if_empty(data: &mut Keyspace, key: &str) -> Result<()> {
+    if set(data, key)?.is_some_and(Set::is_empty) {
+        data.remove(key);
+    }
+    Ok(())
+}
+
+/// Sets stored at the keys, None for the missing keys
+fn lookup<'a>(data: &'a Keyspace, keys: &[&str]) -> Result<Vec<Option<&'a Set>>> {
+    keys.iter()
+        .map(|key| data.peek(key).map(Value::as_set).transpose())
+        .collect()
+}
+
+/// Members of all the sets, missing keys are empty sets
+fn intersection(sets: Vec<Option<&Set>>) -> impl Iterator<Item = &Vec<u8>> {
+    let mut sets: Vec<&Set> = sets.into_iter().collect::<Option<_>>().unwrap_or_default();
+    // the smallest set is iterated, the others are probed
+    sets.sort_by_key(|set| set.len());
+    let smallest = (!sets.is_empty()).then(|| sets.remove(0));
+    smallest
+        .into_iter()
+        .flat_map(|set| set.iter().map(|(member, ())| member))
+        .filter(move |member| sets.iter().all(|set| set.contains_key(*member)))
+}
+
+/// Result of the operation between the sets stored at the keys, missing keys are empty sets
+fn operation(data: &Keyspace, keys: &[&str], op: SetOperation) -> Result<Vec<Vec<u8>>> {
+    let sets = lookup(data, keys)?;
+    let members = match op {
+        SetOperation::Inter => intersection(sets).cloned().collect(),
+        SetOperation::Union => {
+            let union: HashSet<&Vec<u8>> = sets
+                .into_iter()
+                .flatten()
+                .flat_map(|set| set.iter().map(|(member, ())| member))
+                .collect();
+            union.into_iter().cloned().collect()
+        }
+        SetOperation::Diff => {
+            let Some((Some(first), others)) = sets.split_first() else {
+                return Ok(Vec::new());
+            };
+            first
+                .iter()
+                .map(|(member, ())| member)
+                .filter(|member| !others.iter().flatten().any(|set| set.contains_key(*member)))
+                .cloned()
+                .collect()
+        }
+    };
+    Ok(members)
+}
+
+/// Up to `count` distinct random members
+fn random_members(set: &Set, count: usize) -> Vec<Vec<u8>> {
+    if count >= set.len() {
+        return set.iter().map(|(member, ())| member.clone()).collect();
+    }
+    // as in Redis, when most of the set is requested it is cheaper to pick from all the members
+    if count * 3 > set.len() {
+        return set
+            .iter()
+            .map(|(member, ())| member.clone())
+            .choose_multiple(&mut rand::thread_rng(), count);
+    }
+    let mut picked = HashSet::with_capacity(count);
+    while picked.len() < count {
+        if let Some(member) = set.random_key() {
+            picked.insert(member.clone());
+        }
+    }
+    picked.into_iter().collect()
+}
+
+/// Set commands, see <https://redis.io/docs/latest/develop/data-types/sets/>
+impl Db {
+    /// Add the members, returns the number of members added
+    pub fn sadd(&self, key: &str, members: &[&[u8]]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        if set_mut(&mut data, key)?.is_none() {
+            let set = Set::default();
+            data.insert(key.to_string(), Value::Set { set }, None);
+        }
+        let Some(set) = set_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+        let added = members
+            .iter()
+            .filter(|member| set.insert(member.to_vec(), ()).is_none())
+            .count();
+        remove_if_empty(&mut data, key)?;
+        Ok(added)
+    }
+
+    /// Remove the members, returns the number of members removed
+    pub fn srem(&self, key: &str, members: &[&[u8]]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some(set) = set_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+        let removed = members
+            .iter()
+            .filter(|member| set.remove(**member).is_some())
+            .count();
+        remove_if_empty(&mut data, key)?;
+        Ok(removed)
+    }
+
+    pub fn smembers(&self, key: &str) -> Result<Vec<Vec<u8>>> {
+        let mut data = self.data.lock().unwrap();
+        Ok(set(&mut data, key)?.map_or_else(Vec::new, |set| {
+            set.iter().map(|(member, ())| member.clone()).collect()
+        }))
+    }
+
+    /// Whether each member is in the set
+    pub fn smismember(&self, key: &str, members: &[&[u8]]) -> Result<Vec<bool>> {
+        let mut data = self.data.lock().unwrap();
+        let set = set(&mut data, key)?;
+        Ok(members
+            .iter()
+            .map(|member| set.is_some_and(|set| set.contains_key(*member)))
+            .collect())
+    }
+
+    pub fn scard(&self, key: &str) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(set(&mut data, key)?.map_or(0, Set::len))
+    }
+
+    /// Members of the intersection, union or difference of the sets
+    pub fn set_operation(&self, keys: &[&str], op: SetOperation) -> Result<Vec<Vec<u8>>> {
+        let data = self.data.lock().unwrap();
+        operation(&data, keys, op)
+    }
+
+    /// Store the result of the operation at the destination, which is replaced.
+    /// Returns the number of members of the result.
+    pub fn set_operation_store(
+        &self,
+        destination: &str,
+        keys: &[&str],
+        op: SetOperation,
+    ) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let members = operation(&data, keys, op)?;
+        let len = members.len();
+        if members.is_empty() {
+            data.remove(destination);
+        } else {
+            let set = members.into_iter().map(|member| (member, ())).collect();
+            data.insert(destination.to_string(), Value::Set { set }, None);
+        }
+        Ok(len)
+    }
+
+    /// Cardinality of the intersection, the computation stops once `limit` is reached (0 for no limit)
+    pub fn sintercard(&self, keys: &[&str], limit: usize) -> Result<usize> {
+        let data = self.data.lock().unwrap();
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        Ok(intersection(lookup(&data, keys)?).take(limit).count())
+    }
+
+    /// Random members, `count` distinct members if positive and `-count` members that may repeat
+    /// if negative. None if the key doesn't exist.
+    pub fn srandmember(&self, key: &str, count: i64) -> Result<Option<Vec<Vec<u8>>>> {
+        // as Redis, the counts are limited so the repeated members can't exhaust the memory
+        if !(-i64::MAX / 2..=i64::MAX / 2).contains(&count) {
+            return Err(Error::ValueOutOfRange);
+        }
+        let mut data = self.data.lock().unwrap();
+        let Some(set) = set(&mut data, key)? else {
+            return Ok(None);
+        };
+        let n = usize::try_from(count.unsigned_abs()).unwrap_or(usize::MAX);
+        if count >= 0 {
+            return Ok(Some(random_members(set, n)));
+        }
+        Ok(Some(
+            (0..n).filter_map(|_| set.random_key().cloned()).collect(),
+        ))
+    }
+
+    /// Incremental iteration over the members, see [`Db::scan`]
+    pub fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, Vec<Vec<u8>>)> {
+        let mut data = self.data.lock().unwrap();
+        let Some(set) = set(&mut data, key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let (cursor, members) = set.scan(cursor, count);
+        let members = members
+            .into_iter()
+            .filter(|(member, ())| pattern.is_none_or(|p| glob_match(p.as_bytes(), member)))
+            .map(|(member, ())| member.clone())
+            .collect();
+        Ok((cursor, members))
+    }
+
+    /// Remove up to `count` random members, returns them. None if the key doesn't exist.
+    pub fn spop(&self, key: &str, count: usize) -> Result<Option<Vec<Vec<u8>>>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(set) = set_mut(&mut data, key)? else {
+            return Ok(None);
+        };
+        let popped = random_members(set, count);
+        for member in &popped {
+            set.remove(member.as_slice());
+        }
+        remove_if_empty(&mut data, key)?;
+        Ok(Some(popped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::SetOperation;
+    use crate::error::Error;
+    use crate::storage::{Config, Db};
+
+    fn members(members: Vec<Vec<u8>>) -> HashSet<String> {
+        members
+            .into_iter()
+            .map(|m| String::from_utf8(m).unwrap())
+            .collect()
+    }
+
+    fn expected(members: &[&str]) -> HashSet<String> {
+        members.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn set_operation_test() {
+        let db = Db::new(Config::default());
+        db.sadd("a", &[b"1", b"2", b"3"]).unwrap();
+        db.sadd("b", &[b"2", b"3", b"4"]).unwrap();
+
+        let inter = db.set_operation(&["a", "b"], SetOperation::Inter).unwrap();
+        assert_eq!(members(inter), expected(&["2", "3"]));
+        let union = db.set_operation(&["a", "b"], SetOperation::Union).unwrap();
+        assert_eq!(members(union), expected(&["1", "2", "3", "4"]));
+        let diff = db
+            .set_operation(&["a", "b", "missing"], SetOperation::Diff)
+            .unwrap();
+        assert_eq!(members(diff), expected(&["1"]));
+        let inter = db
+            .set_operation(&["a", "missing"], SetOperation::Inter)
+            .unwrap();
+        assert!(inter.is_empty());
+
+        // an empty result removes the destination
+        db.set_operation_store("b", &["a", "missing"], SetOperation::Inter)
+            .unwrap();
+        assert_eq!(db.value_type("b"), None);
+    }
+
+    #[test]
+    fn random_members_test() {
+        let db = Db::new(Config::default());
+        let all: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let all: Vec<&[u8]> = all.iter().map(String::as_bytes).collect();
+        db.sadd("s", &all).unwrap();
+
+        for count in [5, 50, 200] {
+            let picked = db.srandmember("s", count).unwrap().unwrap();
+            assert_eq!(picked.len(), usize::try_from(count.min(100)).unwrap());
+            assert_eq!(members(picked.clone()).len(), picked.len());
+        }
+        assert_eq!(db.srandmember("s", -150).unwrap().unwrap().len(), 150);
+        assert!(matches!(
+            db.srandmember("s", i64::MIN),
+            Err(Error::ValueOutOfRange)
+        ));
+        assert!(matches!(
+            db.srandmember("missing", i64::MAX),
+            Err(Error::ValueOutOfRange)
+        ));
+
+        let popped = db.spop("s", 60).unwrap().unwrap();
+        assert_eq!(members(popped).len(), 60);
+        assert_eq!(db.scard("s").unwrap(), 40);
+        db.spop("s", 60).unwrap();
+        assert_eq!(db.value_type("s"), None);
+    }
+
+    #[test]
+    fn sscan_cursor_stable_test() {
+        let db = Db::new(Config::default());
+        let all: Vec<String> = (0..50).map(|i| format!("m:{i}")).collect();
+        let all: Vec<&[u8]> = all.iter().map(String::as_bytes).collect();
+        db.sadd("s", &all).unwrap();
+
+        let (mut cursor, first) = db.sscan("s", 0, 10, None).unwrap();
+        let mut seen = members(first);
+        // the members returned are removed and new ones added, the others are still returned
+        let returned: Vec<Vec<u8>> = seen.iter().map(|m| m.clone().into_bytes()).collect();
+        let returned: Vec<&[u8]> = returned.iter().map(Vec::as_slice).collect();
+        db.srem("s", &returned).unwrap();
+        db.sadd("s", &[b"new:1", b"new:2"]).unwrap();
+        while cursor != 0 {
+            let (next, scanned) = db.sscan("s", cursor, 10, None).unwrap();
+            seen.extend(members(scanned));
+            cursor = next;
+        }
+        for i in 0..50 {
+            assert!(seen.contains(&format!("m:{i}")), "m:{i} missing");
+        }
+
+        let (_, matched) = db.sscan("s", 0, 100, Some("new:*")).unwrap();
+        assert_eq!(members(matched), expected(&["new:1", "new:2"]));
+    }
+}
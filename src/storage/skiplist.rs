@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Maximum number of levels, as Redis `ZSKIPLIST_MAXLEVEL`
+const MAX_LEVEL: usize = 32;
+/// Probability for a node to have one more level, as Redis `ZSKIPLIST_P`
+const LEVEL_P: f64 = 0.25;
+/// The head of the list is the first node of the arena, it has no entry
+const HEAD: usize = 0;
+
+/// Link of a node to the next node of a level, with the number of entries it skips over: the
+/// difference between the ranks of the two nodes, or the number of entries after the node when
+/// it is the last one of the level
+#[derive(Debug, Clone, Copy, Default)]
+struct Link {
+    next: Option<usize>,
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    /// None for the head and the free nodes
+    entry: Option<T>,
+    levels: Vec<Link>,
+    prev: Option<usize>,
+}
+
+/// Ordered entries that can be looked up by rank, the skiplist of the Redis sorted sets.
+///
+/// Each link records how many entries it skips over, so the rank of an entry and the entry at a
+/// rank are found in logarithmic time, as the entries themselves. The nodes are kept in an arena
+/// and linked by their position, the positions of the removed nodes are reused.
+#[derive(Debug, Clone)]
+pub(super) struct SkipList<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    /// Number of levels in use
+    level: usize,
+    tail: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for SkipList<T> {
+    fn default() -> Self {
+        let head = Node {
+            entry: None,
+            levels: vec![Link::default(); MAX_LEVEL],
+            prev: None,
+        };
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            level: 1,
+            tail: None,
+            len: 0,
+        }
+    }
+}
+
+/// Level of a new node, each level being `LEVEL_P` times less likely than the previous one
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && rand::random::<f64>() < LEVEL_P {
+        level += 1;
+    }
+    level
+}
+
+impl<T: Ord> SkipList<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn entry(&self, node: usize) -> &T {
+        self.nodes[node]
+            .entry
+            .as_ref()
+            .expect("only the entries are linked")
+    }
+
+    /// The last node of each level before the key, or at the key if `inclusive`, and its rank.
+    /// The ranks of the nodes start at 1, the head is 0.
+    fn predecessors(&self, key: &T, inclusive: bool) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let (mut update, mut rank) = ([HEAD; MAX_LEVEL], [0; MAX_LEVEL]);
+        let (mut node, mut traversed) = (HEAD, 0);
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[node].levels[i];
+                let Some(next) = link.next else {
+                    break;
+                };
+                match self.entry(next).cmp(key) {
+                    Ordering::Less => {}
+                    Ordering::Equal if inclusive => {}
+                    _ => break,
+                }
+                traversed += link.span;
+                node = next;
+            }
+            update[i] = node;
+            rank[i] = traversed;
+        }
+        (update, rank)
+    }
+
+    /// Node at the 0 based rank
+    fn node_at(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let (mut node, mut traversed) = (HEAD, 0);
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[node].levels[i];
+                match link.next {
+                    Some(next) if traversed + link.span <= target => {
+                        traversed += link.span;
+                        node = next;
+                    }
+                    _ => break,
+                }
+            }
+            if traversed == target {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// Insert an entry not already in the list
+    pub fn insert(&mut self, entry: T) {
+        let (update, rank) = self.predecessors(&entry, false);
+        let level = random_level();
+        if level > self.level {
+            // the new levels start from the head, which skips over all the entries
+            let len = self.len;
+            for link in &mut self.nodes[HEAD].levels[self.level..level] {
+                link.span = len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            entry: Some(entry),
+            levels: vec![Link::default(); level],
+            prev: (update[0] != HEAD).then_some(update[0]),
+        };
+        let node = if let Some(free) = self.free.pop() {
+            self.nodes[free] = node;
+            free
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        };
+
+        for (i, &previous) in update.iter().enumerate().take(self.level) {
+            if i < level {
+                // the new node takes over the link of its predecessor from its own rank
+                let skipped = rank[0] - rank[i];
+                let link = self.nodes[previous].levels[i];
+                self.nodes[node].levels[i] = Link {
+                    next: link.next,
+                    span: link.span - skipped,
+                };
+                self.nodes[previous].levels[i] = Link {
+                    next: Some(node),
+                    span: skipped + 1,
+                };
+            } else {
+                self.nodes[previous].levels[i].span += 1;
+            }
+        }
+        match self.nodes[node].levels[0].next {
+            Some(next) => self.nodes[next].prev = Some(node),
+            None => self.tail = Some(node),
+        }
+        self.len += 1;
+    }
+
+    /// Remove the entry, returns whether it was in the list
+    pub fn remove(&mut self, entry: &T) -> bool {
+        let (update, _) = self.predecessors(entry, false);
+        let Some(node) = self.nodes[update[0]].levels[0]
+            .next
+            .filter(|next| self.entry(*next) == entry)
+        else {
+            return false;
+        };
+
+        for (i, &previous) in update.iter().enumerate().take(self.level) {
+            let removed = self.nodes[node].levels.get(i).copied();
+            let link = &mut self.nodes[previous].levels[i];
+            match removed {
+                Some(removed) if link.next == Some(node) => {
+                    link.span = link.span + removed.span - 1;
+                    link.next = removed.next;
+                }
+                _ => link.span -= 1,
+            }
+        }
+        let prev = self.nodes[node].prev;
+        match self.nodes[node].levels[0].next {
+            Some(next) => self.nodes[next].prev = prev,
+            None => self.tail = prev,
+        }
+        while self.level > 1 && self.nodes[HEAD].levels[self.level - 1].next.is_none() {
+            self.level -= 1;
+        }
+
+        self.nodes[node] = Node {
+            entry: None,
+            levels: Vec::new(),
+            prev: None,
+        };
+        self.free.push(node);
+        self.len -= 1;
+        true
+    }
+
+    /// Number of entries before the key, including the key itself if `inclusive`
+    pub fn count_below(&self, key: &T, inclusive: bool) -> usize {
+        let (_, rank) = self.predecessors(key, inclusive);
+        rank[0]
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.nodes[HEAD].levels[0].next.map(|node| self.entry(node))
+    }
+
+    /// Entries of the 0 based ranks, in order. The ranks are clamped to the list.
+    pub fn range(&self, ranks: Range<usize>) -> Iter<'_, T> {
+        let end = ranks.end.min(self.len);
+        let start = ranks.start.min(end);
+        let remaining = end - start;
+        let (front, back) = if remaining == 0 {
+            (None, None)
+        } else if end == self.len {
+            (self.node_at(start), self.tail)
+        } else {
+            (self.node_at(start), self.node_at(end - 1))
+        };
+        Iter {
+            list: self,
+            front,
+            back,
+            remaining,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(0..self.len)
+    }
+}
+
+/// Iterator over consecutive entries of a skiplist, in both directions
+#[derive(Debug)]
+pub(super) struct Iter<'a, T> {
+    list: &'a SkipList<T>,
+    front: Option<usize>,
+    back: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a, T: Ord> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.front?;
+        self.remaining -= 1;
+        self.front = self.list.nodes[node].levels[0].next;
+        Some(self.list.entry(node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: Ord> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.back?;
+        self.remaining -= 1;
+        self.back = self.list.nodes[node].prev;
+        Some(self.list.entry(node))
+    }
+}
+
+impl<T: Ord> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::ops::Range;
+
+    use super::SkipList;
+
+    #[test]
+    fn ranks_test() {
+        let mut list = SkipList::default();
+        let mut expected = BTreeSet::new();
+        // inserts and removes in a scrambled order, the values repeat after 1000
+        for i in 0..1500u32 {
+            let value = i.wrapping_mul(7919) % 1000;
+            if expected.insert(value) {
+                list.insert(value);
+            } else {
+                assert!(list.remove(&value));
+                expected.remove(&value);
+            }
+        }
+        assert!(!list.remove(&5000));
+        let expected: Vec<u32> = expected.into_iter().collect();
+        assert_eq!(list.len(), expected.len());
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            expected.iter().rev().copied().collect::<Vec<_>>()
+        );
+
+        for (rank, value) in expected.iter().enumerate() {
+            assert_eq!(list.count_below(value, false), rank);
+            assert_eq!(list.count_below(value, true), rank + 1);
+            assert_eq!(list.range(rank..rank + 1).next(), Some(value));
+        }
+        assert_eq!(list.count_below(&1000, false), expected.len());
+        assert_eq!(list.first(), expected.first());
+
+        let range: Vec<u32> = list.range(10..20).rev().copied().collect();
+        let expected_range: Vec<u32> = expected[10..20].iter().rev().copied().collect();
+        assert_eq!(range, expected_range);
+        assert_eq!(list.range(Range { start: 20, end: 10 }).count(), 0);
+        assert_eq!(list.range(expected.len() - 1..usize::MAX).count(), 1);
+    }
+}
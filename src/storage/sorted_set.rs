@@ -0,0 +1,682 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Bound, Range};
+
+use super::in_memory::{clamp_range, glob_match};
+use super::keyspace::{Keyspace, Value};
+use super::scan::ScanMap;
+use super::skiplist::SkipList;
+use super::{Db, SetCondition, SetOperation};
+use crate::error::{Error, Result};
+
+/// Score of a member, totally ordered so it can be indexed. NaN scores are never stored.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+type IndexEntry = (Score, Vec<u8>);
+
+/// Members ordered by score, then lexicographically for the members with the same score.
+///
+/// The scores are kept in a map for constant time lookups and ZSCAN, and the order in a skiplist
+/// that finds the ranks in logarithmic time, as Redis does with a dict and a skiplist.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SortedSet {
+    scores: ScanMap<Vec<u8>, f64>,
+    index: SkipList<IndexEntry>,
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Set the score of the member, returns its previous score
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        // -0 and 0 are the same score
+        let score = score + 0.0;
+        let previous = self.remove(&member);
+        self.index.insert((Score(score), member.clone()));
+        self.scores.insert(member, score);
+        previous
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.index.remove(&(Score(score), member.to_vec()));
+        Some(score)
+    }
+
+    /// Members with their scores, in order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.index.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Members with their scores at the 0 based ranks, in order
+    fn entries(&self, ranks: Range<usize>) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.index
+            .range(ranks)
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// Position of the member in the order
+    fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(
+            self.index
+                .count_below(&(Score(score), member.to_vec()), false),
+        )
+    }
+
+    /// Ranks of the entries between the bounds, empty if the lower bound is after the upper bound
+    fn ranks(&self, lower: Bound<IndexEntry>, upper: Bound<IndexEntry>) -> Range<usize> {
+        let start = match lower {
+            Bound::Included(start) => self.index.count_below(&start, false),
+            Bound::Excluded(start) => self.index.count_below(&start, true),
+            Bound::Unbounded => 0,
+        };
+        let end = match upper {
+            Bound::Included(end) => self.index.count_below(&end, true),
+            Bound::Excluded(end) => self.index.count_below(&end, false),
+            Bound::Unbounded => self.index.len(),
+        };
+        start..end.max(start)
+    }
+
+    /// Entries with a score between the bounds, in order
+    pub fn range_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> impl DoubleEndedIterator<Item = (&Vec<u8>, f64)> {
+        self.entries(self.score_ranks(min, max))
+    }
+
+    /// Ranks of the entries with a score between the bounds
+    fn score_ranks(&self, min: Bound<f64>, max: Bound<f64>) -> Range<usize> {
+        // the empty member is the first member of a score
+        let first_of = |score: f64| (Score(score), Vec::new());
+        let lower = match min {
+            // nothing is above +inf
+            Bound::Excluded(min) if min == f64::INFINITY => return 0..0,
+            Bound::Included(min) => Bound::Included(first_of(min)),
+            Bound::Excluded(min) => Bound::Included(first_of(min.next_up())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let upper = match max {
+            Bound::Included(max) if max == f64::INFINITY => Bound::Unbounded,
+            Bound::Included(max) => Bound::Excluded(first_of(max.next_up())),
+            Bound::Excluded(max) => Bound::Excluded(first_of(max)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.ranks(lower, upper)
+    }
+
+    /// Ranks of the entries between the lexicographical bounds. As Redis, the lexicographical
+    /// ranges assume all the members have the same score
+    fn lex_ranks(&self, min: &LexBound, max: &LexBound) -> Range<usize> {
+        let score = self.index.first().map_or(Score(0.0), |(score, _)| *score);
+        let entry = |member: &[u8]| (score, member.to_vec());
+        if matches!((min, max), (LexBound::Max, _) | (_, LexBound::Min)) {
+            return 0..0;
+        }
+        let lower = match min {
+            LexBound::Included(member) => Bound::Included(entry(member)),
+            LexBound::Excluded(member) => Bound::Excluded(entry(member)),
+            _ => Bound::Included(entry(b"")),
+        };
+        let upper = match max {
+            LexBound::Included(member) => Bound::Included(entry(member)),
+            LexBound::Excluded(member) => Bound::Excluded(entry(member)),
+            _ if score.0 == f64::INFINITY => Bound::Unbounded,
+            _ => Bound::Excluded((Score(score.0.next_up()), Vec::new())),
+        };
+        self.ranks(lower, upper)
+    }
+}
+
+/// Bound of a lexicographical range: `[member`, `(member`, `-` and `+`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Included(Vec<u8>),
+    Excluded(Vec<u8>),
+    /// `-`, before all the members
+    Min,
+    /// `+`, after all the members
+    Max,
+}
+
+/// Members selected by ZRANGE
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    /// Inclusive ranks, negative ranks count from the end
+    Rank(i64, i64),
+    Score(Bound<f64>, Bound<f64>),
+    Lex(LexBound, LexBound),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRange {
+    pub by: ZRangeBy,
+    /// Highest scores first, the ranks count from the highest score
+    pub rev: bool,
+    /// Number of members skipped and maximum number of members returned
+    pub limit: Option<(usize, usize)>,
+}
+
+/// Options of ZADD
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZaddOptions {
+    pub condition: Option<SetCondition>,
+    /// Only update the scores of existing members if the new score is greater (GT)
+    pub greater: bool,
+    /// Only update the scores of existing members if the new score is less (LT)
+    pub less: bool,
+    /// Increment the score instead of setting it
+    pub incr: bool,
+}
+
+/// What ZADD did
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ZaddOutcome {
+    pub added: usize,
+    /// Members added or whose score changed
+    pub changed: usize,
+    /// With INCR, the new score, None if the member was not updated
+    pub score: Option<f64>,
+}
+
+/// How the scores of a member in several sorted sets are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // as Redis, inf + -inf is 0
+            Aggregate::Sum => zero_if_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+/// Members and their scores
+pub type ScoredMembers = Vec<(Vec<u8>, f64)>;
+
+/// Sorted set stored at key, None if the key doesn't exist
+fn zset_mut<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut SortedSet>> {
+    data.get_mut(key).map(Value::as_zset_mut).transpose()
+}
+
+fn zset<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a SortedSet>> {
+    data.get(key).map(Value::as_zset).transpose()
+}
+
+/// Empty sorted sets are not kept, as in Redis the key is removed with its last member
+fn remove_if_empty(data: &mut Keyspace, key: &str) -> Result<()> {
+    if zset(data, key)?.is_some_and(SortedSet::is_empty) {
+        data.remove(key);
+    }
+    Ok(())
+}
+
+/// Members and scores of the sorted set or set stored at key, the members of a set have a score of 1
+fn weighted_scores(data: &Keyspace, key: &str, weight: f64) -> Result<HashMap<Vec<u8>, f64>> {
+    let weighted = |score: f64| zero_if_nan(score * weight);
+    Ok(match data.peek(key) {
+        None => HashMap::new(),
+        Some(Value::Set { set }) => set
+            .iter()
+            .map(|(member, ())| (member.clone(), weighted(1.0)))
+            .collect(),
+        Some(value) => value
+            .as_zset()?
+            .iter()
+            .map(|(member, score)| (member.clone(), weighted(score)))
+            .collect(),
+    })
+}
+
+/// Sorted set commands, see <https://redis.io/docs/latest/develop/data-types/sorted-sets/>
+impl Db {
+    /// Add the members or update their scores, as allowed by the options
+    pub fn zadd(
+        &self,
+        key: &str,
+        pairs: &[(f64, &[u8])],
+        options: ZaddOptions,
+    ) -> Result<ZaddOutcome> {
+        let mut data = self.data.lock().unwrap();
+        if zset_mut(&mut data, key)?.is_none() {
+            if options.condition == Some(SetCondition::Xx) {
+                return Ok(ZaddOutcome::default());
+            }
+            let zset = SortedSet::default();
+            data.insert(key.to_string(), Value::SortedSet { zset }, None);
+        }
+        let Some(zset) = zset_mut(&mut data, key)? else {
+            return Ok(ZaddOutcome::default());
+        };
+
+        let mut outcome = ZaddOutcome::default();
+        for (score, member) in pairs {
+            let current = zset.score(member);
+            match (current, options.condition) {
+                (Some(_), Some(SetCondition::Nx)) | (None, Some(SetCondition::Xx)) => continue,
+                _ => {}
+            }
+            let score = match (options.incr, current) {
+                (true, Some(current)) => current + score,
+                _ => *score,
+            };
+            if score.is_nan() {
+                return Err(Error::ScoreNan);
+            }
+            if let Some(current) = current {
+                if (options.greater && score <= current) || (options.less && score >= current) {
+                    continue;
+                }
+                // an exact comparison as in Redis, an equal score is not a change
+                #[allow(clippy::float_cmp)]
+                let unchanged = score == current;
+                if !unchanged {
+                    zset.insert(member.to_vec(), score);
+                    outcome.changed += 1;
+                }
+            } else {
+                zset.insert(member.to_vec(), score);
+                outcome.added += 1;
+                outcome.changed += 1;
+            }
+            outcome.score = Some(score);
+        }
+        remove_if_empty(&mut data, key)?;
+        self.signal_key_ready(key);
+        Ok(outcome)
+    }
+
+    /// Remove the members, returns the number of members removed
+    pub fn zrem(&self, key: &str, members: &[&[u8]]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset_mut(&mut data, key)? else {
+            return Ok(0);
+        };
+        let removed = members
+            .iter()
+            .filter(|member| zset.remove(member).is_some())
+            .count();
+        remove_if_empty(&mut data, key)?;
+        Ok(removed)
+    }
+
+    /// Remove up to `count` members with the lowest scores, or the highest if `max`,
+    /// returns them in the order they were popped
+    pub fn zpop(&self, key: &str, count: usize, max: bool) -> Result<Vec<(Vec<u8>, f64)>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset_mut(&mut data, key)? else {
+            return Ok(Vec::new());
+        };
+        let entries: Box<dyn Iterator<Item = _>> = if max {
+            Box::new(zset.iter().rev())
+        } else {
+            Box::new(zset.iter())
+        };
+        let popped: Vec<(Vec<u8>, f64)> = entries
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect();
+        for (member, _) in &popped {
+            zset.remove(member);
+        }
+        remove_if_empty(&mut data, key)?;
+        Ok(popped)
+    }
+
+    pub fn zcard(&self, key: &str) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(zset(&mut data, key)?.map_or(0, SortedSet::len))
+    }
+
+    pub fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>> {
+        let mut data = self.data.lock().unwrap();
+        Ok(zset(&mut data, key)?.and_then(|zset| zset.score(member)))
+    }
+
+    /// Rank of the member and its score, from the highest score if `rev`
+    pub fn zrank(&self, key: &str, member: &[u8], rev: bool) -> Result<Option<(usize, f64)>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset(&mut data, key)? else {
+            return Ok(None);
+        };
+        Ok(zset
+            .rank(member)
+            .zip(zset.score(member))
+            .map(|(rank, score)| {
+                let rank = if rev { zset.len() - 1 - rank } else { rank };
+                (rank, score)
+            }))
+    }
+
+    /// Members and scores selected by the range, in the order of the range
+    pub fn zrange(&self, key: &str, range: &ZRange) -> Result<Vec<(Vec<u8>, f64)>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset(&mut data, key)? else {
+            return Ok(Vec::new());
+        };
+
+        let ranks = match &range.by {
+            ZRangeBy::Rank(start, stop) => {
+                let Some((start, stop)) = clamp_range(zset.len(), *start, *stop) else {
+                    return Ok(Vec::new());
+                };
+                if range.rev {
+                    zset.len() - 1 - stop..zset.len() - start
+                } else {
+                    start..stop + 1
+                }
+            }
+            ZRangeBy::Score(min, max) => zset.score_ranks(*min, *max),
+            ZRangeBy::Lex(min, max) => zset.lex_ranks(min, max),
+        };
+        // LIMIT is applied on the ranks, from the end of the range in reverse
+        let (offset, count) = range.limit.unwrap_or((0, usize::MAX));
+        let ranks = if range.rev {
+            let end = ranks.end.saturating_sub(offset).max(ranks.start);
+            end.saturating_sub(count).max(ranks.start)..end
+        } else {
+            let start = ranks.start.saturating_add(offset).min(ranks.end);
+            start..start.saturating_add(count).min(ranks.end)
+        };
+
+        let entries = zset.entries(ranks);
+        let entries: Box<dyn Iterator<Item = _>> = if range.rev {
+            Box::new(entries.rev())
+        } else {
+            Box::new(entries)
+        };
+        Ok(entries
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
+    }
+
+    /// Incremental iteration over the members and their scores, see [`Db::scan`]
+    pub fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&str>,
+    ) -> Result<(u64, ScoredMembers)> {
+        let mut data = self.data.lock().unwrap();
+        let Some(zset) = zset(&mut data, key)? else {
+            return Ok((0, Vec::new()));
+        };
+        let (cursor, entries) = zset.scores.scan(cursor, count);
+        let entries = entries
+            .into_iter()
+            .filter(|(member, _)| pattern.is_none_or(|p| glob_match(p.as_bytes(), member)))
+            .map(|(member, score)| (member.clone(), *score))
+            .collect();
+        Ok((cursor, entries))
+    }
+
+    /// Store at the destination the members of the union, intersection or difference of the
+    /// sorted sets (or sets), with their weighted scores combined by `aggregate`.
+    /// The destination is replaced, returns the number of members stored.
+    pub fn zstore(
+        &self,
+        destination: &str,
+        keys: &[&str],
+        weights: &[f64],
+        aggregate: Aggregate,
+        op: SetOperation,
+    ) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let inputs = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| weighted_scores(&data, key, weights.get(i).copied().unwrap_or(1.0)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut inputs = inputs.into_iter();
+        let mut result = inputs.next().unwrap_or_default();
+        for input in inputs {
+            match op {
+                SetOperation::Union => {
+                    for (member, score) in input {
+                        result
+                            .entry(member)
+                            .and_modify(|current| *current = aggregate.apply(*current, score))
+                            .or_insert(score);
+                    }
+                }
+                SetOperation::Inter => {
+                    result.retain(|member, current| {
+                        input.get(member).is_some_and(|score| {
+                            *current = aggregate.apply(*current, *score);
+                            true
+                        })
+                    });
+                }
+                SetOperation::Diff => result.retain(|member, _| !input.contains_key(member)),
+            }
+        }
+
+        let len = result.len();
+        if result.is_empty() {
+            data.remove(destination);
+        } else {
+            let mut zset = SortedSet::default();
+            for (member, score) in result {
+                zset.insert(member, score);
+            }
+            data.insert(destination.to_string(), Value::SortedSet { zset }, None);
+            self.signal_key_ready(destination);
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::ops::Bound;
+
+    use super::{Aggregate, LexBound, ZRange, ZRangeBy, ZaddOptions};
+    use crate::storage::{Config, Db, SetOperation};
+
+    fn members(
+        db: &Db,
+        key: &str,
+        by: ZRangeBy,
+        rev: bool,
+        limit: Option<(usize, usize)>,
+    ) -> Vec<String> {
+        db.zrange(key, &ZRange { by, rev, limit })
+            .unwrap()
+            .into_iter()
+            .map(|(member, _)| String::from_utf8(member).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn zrange_test() {
+        let db = Db::new(Config::default());
+        let pairs: Vec<(f64, &[u8])> =
+            vec![(1.0, b"a"), (2.0, b"b"), (2.0, b"c"), (f64::INFINITY, b"d")];
+        db.zadd("z", &pairs, ZaddOptions::default()).unwrap();
+
+        assert_eq!(
+            members(&db, "z", ZRangeBy::Rank(0, -1), false, None),
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            members(&db, "z", ZRangeBy::Rank(0, 1), true, None),
+            ["d", "c"]
+        );
+        let by_score = ZRangeBy::Score(Bound::Excluded(1.0), Bound::Included(2.0));
+        assert_eq!(members(&db, "z", by_score, false, None), ["b", "c"]);
+        let by_score = ZRangeBy::Score(Bound::Included(2.0), Bound::Included(f64::INFINITY));
+        assert_eq!(members(&db, "z", by_score, true, Some((1, 2))), ["c", "b"]);
+        let by_score = ZRangeBy::Score(Bound::Unbounded, Bound::Unbounded);
+        assert_eq!(members(&db, "z", by_score, false, Some((1, 2))), ["b", "c"]);
+        let by_score = ZRangeBy::Score(Bound::Unbounded, Bound::Unbounded);
+        assert!(members(&db, "z", by_score, true, Some((4, 1))).is_empty());
+        assert_eq!(db.zrank("z", b"c", false).unwrap(), Some((2, 2.0)));
+        assert_eq!(db.zrank("z", b"c", true).unwrap(), Some((1, 2.0)));
+        let by_score = ZRangeBy::Score(Bound::Included(3.0), Bound::Excluded(2.0));
+        assert!(members(&db, "z", by_score, false, None).is_empty());
+        let by_score = ZRangeBy::Score(Bound::Excluded(f64::INFINITY), Bound::Unbounded);
+        assert!(members(&db, "z", by_score, false, None).is_empty());
+
+        let pairs: Vec<(f64, &[u8])> = vec![(0.0, b"a"), (0.0, b"b"), (0.0, b"c")];
+        db.zadd("lex", &pairs, ZaddOptions::default()).unwrap();
+        let by_lex = ZRangeBy::Lex(LexBound::Excluded(b"a".to_vec()), LexBound::Max);
+        assert_eq!(members(&db, "lex", by_lex, false, None), ["b", "c"]);
+        let by_lex = ZRangeBy::Lex(LexBound::Min, LexBound::Included(b"b".to_vec()));
+        assert_eq!(members(&db, "lex", by_lex, true, None), ["b", "a"]);
+        let by_lex = ZRangeBy::Lex(LexBound::Max, LexBound::Min);
+        assert!(members(&db, "lex", by_lex, false, None).is_empty());
+    }
+
+    #[test]
+    fn zadd_options_test() {
+        let db = Db::new(Config::default());
+        db.zadd("z", &[(5.0, b"a")], ZaddOptions::default())
+            .unwrap();
+
+        let gt = ZaddOptions {
+            greater: true,
+            ..ZaddOptions::default()
+        };
+        let outcome = db.zadd("z", &[(3.0, b"a"), (1.0, b"b")], gt).unwrap();
+        assert_eq!((outcome.added, outcome.changed), (1, 1));
+        assert_eq!(db.zscore("z", b"a").unwrap(), Some(5.0));
+
+        let incr = ZaddOptions {
+            incr: true,
+            ..ZaddOptions::default()
+        };
+        assert_eq!(db.zadd("z", &[(2.5, b"a")], incr).unwrap().score, Some(7.5));
+        assert_eq!(db.zrank("z", b"a", true).unwrap(), Some((0, 7.5)));
+    }
+
+    #[test]
+    fn zstore_test() {
+        let db = Db::new(Config::default());
+        db.zadd("z1", &[(1.0, b"a"), (2.0, b"b")], ZaddOptions::default())
+            .unwrap();
+        db.zadd("z2", &[(10.0, b"b"), (20.0, b"c")], ZaddOptions::default())
+            .unwrap();
+
+        let len = db
+            .zstore(
+                "out",
+                &["z1", "z2"],
+                &[2.0, 1.0],
+                Aggregate::Sum,
+                SetOperation::Union,
+            )
+            .unwrap();
+        assert_eq!(len, 3);
+        assert_eq!(db.zscore("out", b"b").unwrap(), Some(14.0));
+        let len = db
+            .zstore(
+                "out",
+                &["z1", "z2"],
+                &[],
+                Aggregate::Max,
+                SetOperation::Inter,
+            )
+            .unwrap();
+        assert_eq!(len, 1);
+        assert_eq!(db.zscore("out", b"b").unwrap(), Some(10.0));
+        db.zstore(
+            "out",
+            &["z1", "missing"],
+            &[],
+            Aggregate::Sum,
+            SetOperation::Inter,
+        )
+        .unwrap();
+        assert_eq!(db.value_type("out"), None);
+    }
+
+    #[test]
+    // the scores compared are set as is
+    #[allow(clippy::float_cmp)]
+    fn zscan_cursor_stable_test() {
+        let db = Db::new(Config::default());
+        let names: Vec<String> = (0..50).map(|i| format!("m:{i}")).collect();
+        let pairs: Vec<(f64, &[u8])> = names
+            .iter()
+            .zip(0..)
+            .map(|(name, i)| (f64::from(i), name.as_bytes()))
+            .collect();
+        db.zadd("z", &pairs, ZaddOptions::default()).unwrap();
+
+        let (mut cursor, first) = db.zscan("z", 0, 10, None).unwrap();
+        let mut seen: HashSet<Vec<u8>> = first.into_iter().map(|(member, _)| member).collect();
+        // the members returned are removed, the others rescored or added, all the members
+        // present during the whole iteration are still returned
+        let returned: Vec<&[u8]> = seen.iter().map(Vec::as_slice).collect();
+        db.zrem("z", &returned).unwrap();
+        let updates: Vec<(f64, &[u8])> = names.iter().map(|n| (-1.0, n.as_bytes())).collect();
+        db.zadd("z", &updates, ZaddOptions::default()).unwrap();
+        db.zadd("z", &[(1.0, b"new")], ZaddOptions::default())
+            .unwrap();
+        while cursor != 0 {
+            let (next, entries) = db.zscan("z", cursor, 10, None).unwrap();
+            seen.extend(entries.into_iter().map(|(member, _)| member));
+            cursor = next;
+        }
+        for name in &names {
+            assert!(seen.contains(name.as_bytes()), "{name} missing");
+        }
+
+        let (_, matched) = db.zscan("z", 0, 100, Some("m:4?")).unwrap();
+        assert_eq!(matched.len(), 10);
+        assert!(matched.iter().all(|(_, score)| *score == -1.0));
+    }
+}
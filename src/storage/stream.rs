@@ -0,0 +1,825 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Bound;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::keyspace::{Keyspace, Value};
+use super::Db;
+use crate::error::{Error, Result};
+
+/// ID of a stream entry, the unix time in milliseconds of its insertion and a sequence number
+/// for the entries inserted in the same millisecond
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// The smallest ID greater than this one, None for the last possible ID
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_add(1)?,
+                seq: 0,
+            }),
+        }
+    }
+
+    /// The greatest ID smaller than this one, None for 0-0
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => Some(StreamId {
+                ms: self.ms.checked_sub(1)?,
+                seq: u64::MAX,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// Field value pairs of an entry, in insertion order
+pub type StreamFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+pub type StreamEntry = (StreamId, StreamFields);
+
+type Entries = BTreeMap<StreamId, StreamFields>;
+
+/// ID of the entry added by XADD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XaddId {
+    /// `*`, generated from the current time
+    Auto,
+    /// `ms-*`, the sequence number is generated
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// Entries removed by XTRIM and the trimming option of XADD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimThreshold {
+    /// Keep at most the given number of entries
+    MaxLen(usize),
+    /// Remove the entries with a smaller ID
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub threshold: TrimThreshold,
+    /// Maximum number of entries removed, None for no limit
+    pub limit: Option<usize>,
+}
+
+/// An entry delivered to a consumer of a group and not yet acknowledged
+#[derive(Debug, Clone)]
+struct PendingEntry {
+    consumer: Vec<u8>,
+    delivery_time: SystemTime,
+    delivery_count: u64,
+}
+
+impl PendingEntry {
+    fn idle(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.delivery_time).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ConsumerGroup {
+    last_delivered: StreamId,
+    /// The pending entries list (PEL) of the group
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeSet<Vec<u8>>,
+}
+
+impl ConsumerGroup {
+    /// Assign the pending entry to the consumer, creating it if needed
+    fn claim(
+        &mut self,
+        id: StreamId,
+        consumer: &[u8],
+        delivery_time: SystemTime,
+        delivery_count: Option<u64>,
+    ) -> u64 {
+        let pending = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: Vec::new(),
+            delivery_time,
+            delivery_count: 0,
+        });
+        pending.consumer = consumer.to_vec();
+        pending.delivery_time = delivery_time;
+        pending.delivery_count = delivery_count.unwrap_or(pending.delivery_count + 1);
+        pending.delivery_count
+    }
+}
+
+/// Entries ordered by ID, and the consumer groups reading them.
+///
+/// Unlike the other types an empty stream is kept, its last ID and groups are still meaningful.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Stream {
+    entries: Entries,
+    /// Greatest ID ever added, new IDs must be greater even after the entry is trimmed
+    last_id: StreamId,
+    groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+}
+
+impl Stream {
+    fn next_id(&self, id: XaddId, now_ms: u64) -> Result<StreamId> {
+        let last = self.last_id;
+        let id = match id {
+            XaddId::Auto if now_ms > last.ms => StreamId { ms: now_ms, seq: 0 },
+            XaddId::Auto => last.next().ok_or(Error::StreamIdExhausted)?,
+            XaddId::AutoSeq(ms) if ms > last.ms => StreamId { ms, seq: 0 },
+            XaddId::AutoSeq(ms) if ms == last.ms => {
+                let seq = last.seq.checked_add(1).ok_or(Error::StreamIdTooSmall)?;
+                StreamId { ms, seq }
+            }
+            XaddId::AutoSeq(_) => return Err(Error::StreamIdTooSmall),
+            XaddId::Explicit(StreamId::MIN) => return Err(Error::StreamIdZero),
+            XaddId::Explicit(id) if id <= last => return Err(Error::StreamIdTooSmall),
+            XaddId::Explicit(id) => id,
+        };
+        Ok(id)
+    }
+
+    /// Remove the oldest entries, returns the number of entries removed
+    fn trim(&mut self, trim: &StreamTrim) -> usize {
+        let mut removed = 0;
+        while removed < trim.limit.unwrap_or(usize::MAX) {
+            let len = self.entries.len();
+            let Some(entry) = self.entries.first_entry() else {
+                break;
+            };
+            let over = match trim.threshold {
+                TrimThreshold::MaxLen(max_len) => len > max_len,
+                TrimThreshold::MinId(id) => *entry.key() < id,
+            };
+            if !over {
+                break;
+            }
+            entry.remove();
+            removed += 1;
+        }
+        removed
+    }
+
+    /// The entries and the group, borrowed together
+    fn group_mut(&mut self, name: &[u8]) -> Option<(&Entries, &mut ConsumerGroup)> {
+        let group = self.groups.get_mut(name)?;
+        Some((&self.entries, group))
+    }
+}
+
+/// Entries read by a consumer of a group from a stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupRead {
+    pub key: String,
+    /// None for the entries of the history that were deleted from the stream
+    pub entries: Vec<(StreamId, Option<StreamFields>)>,
+    /// Whether the consumer was created by the read
+    pub new_consumer: bool,
+    /// Whether new entries were delivered, the last one is the new last delivered ID of the group
+    pub delivered: bool,
+}
+
+/// What XREADGROUP reads from each stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupReadFrom {
+    /// `>`, the entries never delivered to the group
+    New,
+    /// The pending entries of the consumer after the ID
+    History(StreamId),
+}
+
+/// Overview of the pending entries of a group, as returned by XPENDING without range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    /// The smallest and greatest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    /// Number of pending entries of each consumer, only the consumers with pending entries
+    pub consumers: Vec<(Vec<u8>, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingFilter {
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<Vec<u8>>,
+    pub min_idle: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: Vec<u8>,
+    pub idle: Duration,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimOptions {
+    pub min_idle: Duration,
+    /// New delivery time of the claimed entries
+    pub delivery_time: SystemTime,
+    /// New delivery count, otherwise incremented unless `just_id`
+    pub retry_count: Option<u64>,
+    /// Claim the entries of the stream that are not pending
+    pub force: bool,
+    pub just_id: bool,
+    /// Raise the last delivered ID of the group
+    pub last_id: Option<StreamId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimedEntry {
+    pub id: StreamId,
+    pub fields: StreamFields,
+    pub delivery_count: u64,
+}
+
+/// Entries claimed by XCLAIM and XAUTOCLAIM
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Claimed {
+    pub entries: Vec<ClaimedEntry>,
+    /// Pending entries no longer in the stream, removed from the pending entries list
+    pub deleted: Vec<StreamId>,
+    /// The ID XAUTOCLAIM continues from, 0-0 once the whole list was scanned
+    pub next: StreamId,
+    /// Whether the consumer was created by the claim
+    pub new_consumer: bool,
+    /// The last delivered ID of the group, when raised by the claim
+    pub last_delivered: Option<StreamId>,
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| {
+        u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+    })
+}
+
+fn no_group(key: &str, group: &[u8]) -> Error {
+    Error::NoGroup(format!(
+        "No such key '{key}' or consumer group '{}'",
+        String::from_utf8_lossy(group)
+    ))
+}
+
+/// Stream stored at key, None if the key doesn't exist
+fn stream_mut<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut Stream>> {
+    data.get_mut(key).map(Value::as_stream_mut).transpose()
+}
+
+fn stream<'a>(data: &'a mut Keyspace, key: &str) -> Result<Option<&'a Stream>> {
+    data.get(key).map(Value::as_stream).transpose()
+}
+
+/// The entries of the stream and its group, NOGROUP if either doesn't exist
+fn group_mut<'a>(
+    data: &'a mut Keyspace,
+    key: &str,
+    group: &[u8],
+) -> Result<(&'a Entries, &'a mut ConsumerGroup)> {
+    stream_mut(data, key)?
+        .and_then(|stream| stream.group_mut(group))
+        .ok_or_else(|| no_group(key, group))
+}
+
+/// Claim the entry for the consumer, a pending entry deleted from the stream is removed instead
+fn claim(
+    entries: &Entries,
+    group: &mut ConsumerGroup,
+    id: StreamId,
+    consumer: &[u8],
+    options: &ClaimOptions,
+    claimed: &mut Claimed,
+) {
+    let Some(fields) = entries.get(&id) else {
+        if group.pending.remove(&id).is_some() {
+            claimed.deleted.push(id);
+        }
+        return;
+    };
+    let retry_count = match options.retry_count {
+        Some(count) => Some(count),
+        None if options.just_id => group.pending.get(&id).map(|p| p.delivery_count),
+        None => None,
+    };
+    let delivery_count = group.claim(id, consumer, options.delivery_time, retry_count);
+    claimed.entries.push(ClaimedEntry {
+        id,
+        fields: fields.clone(),
+        delivery_count,
+    });
+}
+
+/// Stream commands, see <https://redis.io/docs/latest/develop/data-types/streams/>
+impl Db {
+    /// Add an entry and trim the stream, returns the ID of the entry.
+    /// None if the key doesn't exist and `make_stream` is false.
+    pub fn xadd(
+        &self,
+        key: &str,
+        id: XaddId,
+        fields: &[(&[u8], &[u8])],
+        make_stream: bool,
+        trim: Option<&StreamTrim>,
+    ) -> Result<Option<StreamId>> {
+        let mut data = self.data.lock().unwrap();
+        let now_ms = unix_ms(SystemTime::now());
+        let id = match stream(&mut data, key)? {
+            Some(stream) => stream.next_id(id, now_ms)?,
+            None if make_stream => Stream::default().next_id(id, now_ms)?,
+            None => return Ok(None),
+        };
+        if stream_mut(&mut data, key)?.is_none() {
+            let stream = Stream::default();
+            data.insert(key.to_string(), Value::Stream { stream }, None);
+        }
+        let Some(stream) = stream_mut(&mut data, key)? else {
+            return Ok(None);
+        };
+        let fields = fields
+            .iter()
+            .map(|(field, value)| (field.to_vec(), value.to_vec()))
+            .collect();
+        stream.entries.insert(id, fields);
+        stream.last_id = id;
+        if let Some(trim) = trim {
+            stream.trim(trim);
+        }
+        self.signal_key_ready(key);
+        Ok(Some(id))
+    }
+
+    pub fn xlen(&self, key: &str) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(stream(&mut data, key)?.map_or(0, |stream| stream.entries.len()))
+    }
+
+    /// Entries with an ID between start and end included, up to `count` entries
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>> {
+        let mut data = self.data.lock().unwrap();
+        let Some(stream) = stream(&mut data, key)? else {
+            return Ok(Vec::new());
+        };
+        if start > end {
+            return Ok(Vec::new());
+        }
+        Ok(stream
+            .entries
+            .range(start..=end)
+            .take(count)
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect())
+    }
+
+    /// Remove the oldest entries, returns the number of entries removed
+    pub fn xtrim(&self, key: &str, trim: &StreamTrim) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        Ok(stream_mut(&mut data, key)?.map_or(0, |stream| stream.trim(trim)))
+    }
+
+    /// Greatest ID ever added to the stream, what `$` stands for. 0-0 if the key doesn't exist.
+    pub fn stream_last_id(&self, key: &str) -> Result<StreamId> {
+        let mut data = self.data.lock().unwrap();
+        Ok(stream(&mut data, key)?.map_or(StreamId::MIN, |stream| stream.last_id))
+    }
+
+    /// Up to `count` entries after the ID of each stream, only the streams with such entries
+    pub fn xread(
+        &self,
+        streams: &[(&str, StreamId)],
+        count: usize,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>> {
+        let mut data = self.data.lock().unwrap();
+        let mut read = Vec::new();
+        for (key, id) in streams {
+            let Some(stream) = stream(&mut data, key)? else {
+                continue;
+            };
+            let entries: Vec<StreamEntry> = stream
+                .entries
+                .range((Bound::Excluded(id), Bound::Unbounded))
+                .take(count)
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect();
+            if !entries.is_empty() {
+                read.push((key.to_string(), entries));
+            }
+        }
+        Ok(read)
+    }
+
+    /// Create the group, starting after the ID or after the last ID of the stream if None
+    pub fn xgroup_create(
+        &self,
+        key: &str,
+        group: &[u8],
+        id: Option<StreamId>,
+        make_stream: bool,
+    ) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        if stream_mut(&mut data, key)?.is_none() {
+            if !make_stream {
+                return Err(Error::XgroupKeyRequired);
+            }
+            let stream = Stream::default();
+            data.insert(key.to_string(), Value::Stream { stream }, None);
+        }
+        let stream = stream_mut(&mut data, key)?.ok_or(Error::XgroupKeyRequired)?;
+        if stream.groups.contains_key(group) {
+            return Err(Error::BusyGroup);
+        }
+        let last_delivered = id.unwrap_or(stream.last_id);
+        stream.groups.insert(
+            group.to_vec(),
+            ConsumerGroup {
+                last_delivered,
+                ..ConsumerGroup::default()
+            },
+        );
+        Ok(())
+    }
+
+    /// Set the last delivered ID of the group, the last ID of the stream if None
+    pub fn xgroup_setid(&self, key: &str, group: &[u8], id: Option<StreamId>) -> Result<()> {
+        let mut data = self.data.lock().unwrap();
+        let stream = stream_mut(&mut data, key)?.ok_or(Error::XgroupKeyRequired)?;
+        let last_id = stream.last_id;
+        let group = stream
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| no_group(key, group))?;
+        group.last_delivered = id.unwrap_or(last_id);
+        Ok(())
+    }
+
+    /// Returns whether the group existed
+    pub fn xgroup_destroy(&self, key: &str, group: &[u8]) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        let stream = stream_mut(&mut data, key)?.ok_or(Error::XgroupKeyRequired)?;
+        Ok(stream.groups.remove(group).is_some())
+    }
+
+    /// Returns whether the consumer was created
+    pub fn xgroup_createconsumer(&self, key: &str, group: &[u8], consumer: &[u8]) -> Result<bool> {
+        let mut data = self.data.lock().unwrap();
+        stream_mut(&mut data, key)?.ok_or(Error::XgroupKeyRequired)?;
+        let (_, group) = group_mut(&mut data, key, group)?;
+        Ok(group.consumers.insert(consumer.to_vec()))
+    }
+
+    /// Delete the consumer and its pending entries, returns the number of pending entries it had
+    pub fn xgroup_delconsumer(&self, key: &str, group: &[u8], consumer: &[u8]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        stream_mut(&mut data, key)?.ok_or(Error::XgroupKeyRequired)?;
+        let (_, group) = group_mut(&mut data, key, group)?;
+        group.consumers.remove(consumer);
+        let before = group.pending.len();
+        group
+            .pending
+            .retain(|_, pending| pending.consumer != consumer);
+        Ok(before - group.pending.len())
+    }
+
+    /// Read the entries of each stream as the consumer of the group, see [`GroupReadFrom`].
+    /// New entries are added to the pending entries list unless `no_ack`, delivered at `now`
+    /// so the replicas claim them with the same delivery time.
+    pub fn xreadgroup(
+        &self,
+        group: &[u8],
+        consumer: &[u8],
+        streams: &[(&str, GroupReadFrom)],
+        count: usize,
+        no_ack: bool,
+        now: SystemTime,
+    ) -> Result<Vec<GroupRead>> {
+        let mut data = self.data.lock().unwrap();
+        // nothing is read unless every group exists
+        for (key, _) in streams {
+            group_mut(&mut data, key, group)?;
+        }
+
+        let mut reads = Vec::with_capacity(streams.len());
+        for (key, from) in streams {
+            let (entries, group) = group_mut(&mut data, key, group)?;
+            let new_consumer = group.consumers.insert(consumer.to_vec());
+            let read: Vec<(StreamId, Option<StreamFields>)> = match from {
+                GroupReadFrom::New => entries
+                    .range((Bound::Excluded(group.last_delivered), Bound::Unbounded))
+                    .take(count)
+                    .map(|(id, fields)| (*id, Some(fields.clone())))
+                    .collect(),
+                GroupReadFrom::History(after) => group
+                    .pending
+                    .range((Bound::Excluded(after), Bound::Unbounded))
+                    .filter(|(_, pending)| pending.consumer == consumer)
+                    .take(count)
+                    .map(|(id, _)| (*id, entries.get(id).cloned()))
+                    .collect(),
+            };
+            let delivered = *from == GroupReadFrom::New && !read.is_empty();
+            if delivered {
+                for (id, _) in &read {
+                    group.last_delivered = *id;
+                    if !no_ack {
+                        group.claim(*id, consumer, now, Some(1));
+                    }
+                }
+            }
+            reads.push(GroupRead {
+                key: key.to_string(),
+                entries: read,
+                new_consumer,
+                delivered,
+            });
+        }
+        Ok(reads)
+    }
+
+    /// Acknowledge the entries, returns the number of entries removed from the pending entries list
+    pub fn xack(&self, key: &str, group: &[u8], ids: &[StreamId]) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let Some((_, group)) = stream_mut(&mut data, key)?.and_then(|s| s.group_mut(group)) else {
+            return Ok(0);
+        };
+        Ok(ids
+            .iter()
+            .filter(|id| group.pending.remove(id).is_some())
+            .count())
+    }
+
+    pub fn xpending_summary(&self, key: &str, group: &[u8]) -> Result<PendingSummary> {
+        let mut data = self.data.lock().unwrap();
+        let (_, group) = group_mut(&mut data, key, group)?;
+        let range = group
+            .pending
+            .first_key_value()
+            .zip(group.pending.last_key_value())
+            .map(|((first, _), (last, _))| (*first, *last));
+        let mut consumers: BTreeMap<&[u8], usize> = BTreeMap::new();
+        for pending in group.pending.values() {
+            *consumers.entry(&pending.consumer).or_default() += 1;
+        }
+        Ok(PendingSummary {
+            count: group.pending.len(),
+            range,
+            consumers: consumers
+                .into_iter()
+                .map(|(consumer, count)| (consumer.to_vec(), count))
+                .collect(),
+        })
+    }
+
+    /// The pending entries of the group matching the filter
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &[u8],
+        filter: &PendingFilter,
+    ) -> Result<Vec<PendingInfo>> {
+        let mut data = self.data.lock().unwrap();
+        let (_, group) = group_mut(&mut data, key, group)?;
+        if filter.start > filter.end {
+            return Ok(Vec::new());
+        }
+        let now = SystemTime::now();
+        Ok(group
+            .pending
+            .range(filter.start..=filter.end)
+            .filter(|(_, pending)| {
+                filter
+                    .consumer
+                    .as_ref()
+                    .is_none_or(|consumer| pending.consumer == *consumer)
+                    && pending.idle(now) >= filter.min_idle
+            })
+            .take(filter.count)
+            .map(|(id, pending)| PendingInfo {
+                id: *id,
+                consumer: pending.consumer.clone(),
+                idle: pending.idle(now),
+                delivery_count: pending.delivery_count,
+            })
+            .collect())
+    }
+
+    /// Change the owner of the pending entries idle for at least `min_idle` to the consumer
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &[u8],
+        consumer: &[u8],
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Claimed> {
+        let mut data = self.data.lock().unwrap();
+        let (entries, group) = group_mut(&mut data, key, group)?;
+        let now = SystemTime::now();
+        let mut claimed = Claimed {
+            new_consumer: group.consumers.insert(consumer.to_vec()),
+            ..Claimed::default()
+        };
+        for id in ids {
+            let idle = match group.pending.get(id) {
+                Some(pending) => pending.idle(now),
+                None if options.force && entries.contains_key(id) => Duration::MAX,
+                None => continue,
+            };
+            if idle >= options.min_idle {
+                claim(entries, group, *id, consumer, options, &mut claimed);
+            }
+        }
+        if let Some(last_id) = options.last_id.filter(|id| *id > group.last_delivered) {
+            group.last_delivered = last_id;
+            claimed.last_delivered = Some(last_id);
+        }
+        Ok(claimed)
+    }
+
+    /// Claim up to `count` pending entries idle for at least `min_idle`, scanning the pending
+    /// entries list from `start`. As in Redis at most ten times `count` entries are scanned.
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &[u8],
+        consumer: &[u8],
+        start: StreamId,
+        count: usize,
+        options: &ClaimOptions,
+    ) -> Result<Claimed> {
+        let mut data = self.data.lock().unwrap();
+        let (entries, group) = group_mut(&mut data, key, group)?;
+        let now = SystemTime::now();
+        let mut claimed = Claimed {
+            new_consumer: group.consumers.insert(consumer.to_vec()),
+            ..Claimed::default()
+        };
+        let mut attempts = count.saturating_mul(10);
+        let mut cursor = Some(start);
+        while attempts > 0 && claimed.entries.len() < count {
+            let Some(from) = cursor else {
+                break;
+            };
+            let Some(id) = group.pending.range(from..).next().map(|(id, _)| *id) else {
+                cursor = None;
+                break;
+            };
+            attempts -= 1;
+            cursor = id.next();
+            let deleted = !entries.contains_key(&id);
+            let idle = group.pending.get(&id).map(|pending| pending.idle(now));
+            if deleted || idle.is_some_and(|idle| idle >= options.min_idle) {
+                claim(entries, group, id, consumer, options, &mut claimed);
+            }
+        }
+        // the scan continues from the next pending entry, 0-0 once the end of the list is reached
+        claimed.next = cursor
+            .and_then(|from| group.pending.range(from..).next().map(|(id, _)| *id))
+            .unwrap_or(StreamId::MIN);
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{
+        ClaimOptions, GroupReadFrom, PendingFilter, StreamId, StreamTrim, TrimThreshold, XaddId,
+    };
+    use crate::error::Error;
+    use crate::storage::{Config, Db};
+
+    fn id(ms: u64, seq: u64) -> StreamId {
+        StreamId { ms, seq }
+    }
+
+    #[test]
+    fn xadd_id_test() {
+        let db = Db::new(Config::default());
+        let fields: &[(&[u8], &[u8])] = &[(b"f", b"v")];
+        let add = |xadd_id| db.xadd("s", xadd_id, fields, true, None);
+
+        assert_eq!(add(XaddId::AutoSeq(0)).unwrap(), Some(id(0, 1)));
+        assert_eq!(add(XaddId::AutoSeq(5)).unwrap(), Some(id(5, 0)));
+        assert_eq!(add(XaddId::AutoSeq(5)).unwrap(), Some(id(5, 1)));
+        assert!(matches!(
+            add(XaddId::Explicit(id(5, 1))),
+            Err(Error::StreamIdTooSmall)
+        ));
+        assert!(add(XaddId::Auto).unwrap().unwrap() > id(5, 1));
+        assert_eq!(
+            db.xadd("missing", XaddId::Auto, fields, false, None)
+                .unwrap(),
+            None
+        );
+
+        // the last ID is kept when the entries are trimmed
+        let trim = StreamTrim {
+            threshold: TrimThreshold::MaxLen(0),
+            limit: None,
+        };
+        assert_eq!(db.xtrim("s", &trim).unwrap(), 4);
+        assert_eq!(db.xlen("s").unwrap(), 0);
+        assert!(matches!(
+            add(XaddId::AutoSeq(5)),
+            Err(Error::StreamIdTooSmall)
+        ));
+    }
+
+    #[test]
+    fn consumer_group_test() {
+        let db = Db::new(Config::default());
+        for ms in 1..=3 {
+            db.xadd("s", XaddId::AutoSeq(ms), &[(b"f", b"v")], true, None)
+                .unwrap();
+        }
+        db.xgroup_create("s", b"g", Some(StreamId::MIN), false)
+            .unwrap();
+        assert!(matches!(
+            db.xgroup_create("s", b"g", None, false),
+            Err(Error::BusyGroup)
+        ));
+
+        let new = [("s", GroupReadFrom::New)];
+        // alice's entries were delivered a minute ago
+        let delivered = SystemTime::now() - Duration::from_mins(1);
+        let read = db
+            .xreadgroup(b"g", b"alice", &new, 2, false, delivered)
+            .unwrap();
+        assert_eq!(read[0].entries.len(), 2);
+        assert!(read[0].new_consumer && read[0].delivered);
+        let now = SystemTime::now();
+        let read = db.xreadgroup(b"g", b"bob", &new, 10, false, now).unwrap();
+        assert_eq!(read[0].entries[0].0, id(3, 0));
+
+        let summary = db.xpending_summary("s", b"g").unwrap();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.range, Some((id(1, 0), id(3, 0))));
+        assert_eq!(
+            summary.consumers,
+            vec![(b"alice".to_vec(), 2), (b"bob".to_vec(), 1)]
+        );
+        let idle = PendingFilter {
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: 10,
+            consumer: None,
+            min_idle: Duration::from_secs(59),
+        };
+        let idle: Vec<StreamId> = db
+            .xpending("s", b"g", &idle)
+            .unwrap()
+            .iter()
+            .map(|pending| pending.id)
+            .collect();
+        assert_eq!(idle, [id(1, 0), id(2, 0)]);
+        assert_eq!(db.xack("s", b"g", &[id(1, 0), id(9, 0)]).unwrap(), 1);
+
+        // bob takes over the remaining entry of alice, 2-0
+        let options = ClaimOptions {
+            min_idle: Duration::ZERO,
+            delivery_time: SystemTime::now(),
+            retry_count: None,
+            force: false,
+            just_id: false,
+            last_id: None,
+        };
+        let claimed = db
+            .xautoclaim("s", b"g", b"bob", StreamId::MIN, 1, &options)
+            .unwrap();
+        assert_eq!(claimed.entries[0].id, id(2, 0));
+        assert_eq!(claimed.entries[0].delivery_count, 2);
+        assert_eq!(claimed.next, id(3, 0));
+        let history = [("s", GroupReadFrom::History(StreamId::MIN))];
+        let read = db
+            .xreadgroup(b"g", b"alice", &history, 10, false, now)
+            .unwrap();
+        assert!(read[0].entries.is_empty());
+        assert_eq!(db.xgroup_delconsumer("s", b"g", b"bob").unwrap(), 2);
+    }
+}